@@ -0,0 +1,167 @@
+use arrow::array::UInt64Array;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use pgrx::prelude::*;
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::scanner::{OpenOptions, ReadConsistency, ScanOptions};
+use crate::types::downcast_array;
+use crate::{open_scanner, raise_scan_error, BatchJsonEncoder, ROW_ADDRESS_COLUMN};
+
+const TOKEN_PREFIX: &str = "c1";
+
+/// Position after the last row returned by `lance_scan_cursor`
+///
+/// Tokens pin the table version they were created at, so fragment ids and
+/// offsets keep their meaning while later versions are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScanCursor {
+    pub version: u64,
+    pub fragment_id: u64,
+    /// Rows of `fragment_id` already returned
+    pub offset: u64,
+}
+
+impl ScanCursor {
+    /// Encode the cursor as an opaque token bound to `table_path`
+    pub fn encode(&self, table_path: &str) -> String {
+        let body = format!(
+            "{}:{}:{}:{}",
+            TOKEN_PREFIX, self.version, self.fragment_id, self.offset
+        );
+        let checksum = token_checksum(table_path, &body);
+        URL_SAFE_NO_PAD.encode(format!("{}:{:016x}", body, checksum))
+    }
+
+    /// Decode a token, rejecting tokens that were altered or made for another table
+    pub fn decode(token: &str, table_path: &str) -> Result<Self, PgSqlErrorCode> {
+        let invalid = PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE;
+        let decoded = URL_SAFE_NO_PAD.decode(token).map_err(|_| invalid)?;
+        let decoded = String::from_utf8(decoded).map_err(|_| invalid)?;
+        let (body, checksum) = decoded.rsplit_once(':').ok_or(invalid)?;
+        if u64::from_str_radix(checksum, 16).ok() != Some(token_checksum(table_path, body)) {
+            return Err(invalid);
+        }
+
+        let parts: Vec<&str> = body.split(':').collect();
+        match parts.as_slice() {
+            [TOKEN_PREFIX, version, fragment_id, offset] => Ok(Self {
+                version: version.parse().map_err(|_| invalid)?,
+                fragment_id: fragment_id.parse().map_err(|_| invalid)?,
+                offset: offset.parse().map_err(|_| invalid)?,
+            }),
+            _ => Err(invalid),
+        }
+    }
+}
+
+fn token_checksum(table_path: &str, body: &str) -> u64 {
+    xxh3_64(format!("{}\n{}", table_path, body).as_bytes())
+}
+
+/// Read up to `batch` rows, resuming after the position in `after_token`
+///
+/// Each row comes with the token of the position after it; pass the last
+/// row's token to continue. Fewer than `batch` rows means the scan is done.
+/// A token keeps reading the version it was created at.
+#[pg_extern]
+pub fn lance_scan_cursor(
+    table_path: &str,
+    after_token: default!(Option<&str>, "NULL"),
+    batch: default!(i32, 1000),
+) -> TableIterator<'static, (name!(row_data, pgrx::JsonB), name!(next_token, String))> {
+    if batch < 1 {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            format!("batch must be positive, got {}", batch)
+        );
+    }
+
+    let cursor = after_token.map(|token| {
+        ScanCursor::decode(token, table_path).unwrap_or_else(|code| {
+            ereport!(
+                ERROR,
+                code,
+                format!(
+                    "Invalid continuation token for Lance table at: {}",
+                    table_path
+                )
+            )
+        })
+    });
+
+    let scanner = match cursor {
+        Some(cursor) => {
+            let options = OpenOptions {
+                consistency: ReadConsistency::Version(cursor.version),
+                ..Default::default()
+            };
+            crate::scanner::LanceScanner::open(table_path, &options).unwrap_or_else(|code| {
+                ereport!(
+                    ERROR,
+                    code,
+                    format!(
+                        "Failed to open version {} of Lance table at: {}",
+                        cursor.version, table_path
+                    )
+                )
+            })
+        }
+        None => open_scanner(table_path, None),
+    };
+    if scanner.schema().field_with_name(ROW_ADDRESS_COLUMN).is_ok() {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_DUPLICATE_COLUMN,
+            format!(
+                "Lance table has a column named \"{}\", which would hide the row address",
+                ROW_ADDRESS_COLUMN
+            )
+        );
+    }
+
+    let version = scanner.version();
+    let limit = i64::from(batch);
+    let scan_result = match cursor {
+        Some(cursor) => scanner.scan_from_fragment(cursor.fragment_id, cursor.offset, limit),
+        None => scanner.scan_with_row_address(&ScanOptions::new().with_limit(limit)),
+    };
+    let scan_iter = scan_result.unwrap_or_else(|e| raise_scan_error(e));
+
+    let mut position = cursor.unwrap_or(ScanCursor {
+        version,
+        fragment_id: 0,
+        offset: 0,
+    });
+    let mut results = Vec::new();
+    for record_batch in scan_iter.batches {
+        let addr_idx = record_batch.num_columns() - 1;
+        let addresses =
+            downcast_array::<UInt64Array>(record_batch.column(addr_idx).as_ref(), "UInt64Array")
+                .unwrap_or_else(|e| ereport!(ERROR, e.code, e.message))
+                .clone();
+        let schema = record_batch.schema();
+        let columns: Vec<&str> = schema.fields()[..addr_idx]
+            .iter()
+            .map(|f| f.name().as_str())
+            .collect();
+        let encoder = BatchJsonEncoder::new(&record_batch).only(&columns);
+
+        for row_idx in 0..record_batch.num_rows() {
+            let fragment_id = addresses.value(row_idx) >> 32;
+            if fragment_id == position.fragment_id {
+                position.offset += 1;
+            } else {
+                position.fragment_id = fragment_id;
+                position.offset = 1;
+            }
+            let row = encoder
+                .encode_row(row_idx)
+                .unwrap_or_else(|e| ereport!(ERROR, e.code, e.message));
+            results.push((pgrx::JsonB(row), position.encode(table_path)));
+        }
+    }
+    results.truncate(batch as usize);
+
+    TableIterator::new(results)
+}
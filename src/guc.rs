@@ -0,0 +1,154 @@
+use pgrx::{GucContext, GucFlags, GucRegistry, GucSetting, PostgresGucEnum};
+
+/// Behavior when a single row cannot be converted
+#[derive(PostgresGucEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OnRowError {
+    /// Fail the whole query
+    Abort,
+    /// Log a warning and omit the row
+    Skip,
+}
+
+/// How date and timestamp values are rendered in JSON output
+#[derive(PostgresGucEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TimestampJsonFormat {
+    /// ISO 8601 strings
+    Iso,
+    /// Integer microseconds since the Unix epoch
+    #[name = c"epoch_micros"]
+    EpochMicros,
+}
+
+/// Which time zone timestamps are shown in when rendered as ISO strings
+#[derive(PostgresGucEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TimestampJsonTimezone {
+    /// UTC wall time, followed by the column's time zone if it has one
+    Utc,
+    /// Time zone aware timestamps in the session's `TimeZone`; naive ones as stored
+    Session,
+    /// All timestamps in the session's `TimeZone`, reading naive ones as UTC
+    #[name = c"session_assume_utc"]
+    SessionAssumeUtc,
+}
+
+/// What to do with values whose Arrow type has no PostgreSQL conversion
+#[derive(PostgresGucEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OnUnsupportedType {
+    /// Emit the value's textual rendering
+    Text,
+    /// Fail the conversion
+    Error,
+}
+
+pub static ON_ROW_ERROR: GucSetting<OnRowError> = GucSetting::<OnRowError>::new(OnRowError::Abort);
+
+pub static ON_UNSUPPORTED_TYPE: GucSetting<OnUnsupportedType> =
+    GucSetting::<OnUnsupportedType>::new(OnUnsupportedType::Text);
+
+pub static UINT64_AS_BIGINT: GucSetting<bool> = GucSetting::<bool>::new(false);
+
+pub static PROGRESS_INTERVAL: GucSetting<i32> = GucSetting::<i32>::new(0);
+
+pub static IO_THREADS: GucSetting<i32> = GucSetting::<i32>::new(0);
+
+pub static SCAN_MEM_BUDGET_BYTES: GucSetting<i32> = GucSetting::<i32>::new(0);
+
+pub static STATS_CACHE_TTL_SECONDS: GucSetting<i32> = GucSetting::<i32>::new(0);
+
+pub static TIMESTAMP_JSON_FORMAT: GucSetting<TimestampJsonFormat> =
+    GucSetting::<TimestampJsonFormat>::new(TimestampJsonFormat::Iso);
+
+pub static TIMESTAMP_JSON_TIMEZONE: GucSetting<TimestampJsonTimezone> =
+    GucSetting::<TimestampJsonTimezone>::new(TimestampJsonTimezone::Utc);
+
+/// Register pglance configuration parameters
+pub fn init() {
+    GucRegistry::define_enum_guc(
+        c"pglance.on_row_error",
+        c"Behavior when a row cannot be converted.",
+        c"'abort' fails the query on the first row that cannot be converted; 'skip' logs a warning and omits the row.",
+        &ON_ROW_ERROR,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+
+    GucRegistry::define_int_guc(
+        c"pglance.progress_interval",
+        c"Rows between progress notices during scans.",
+        c"When greater than zero, scans emit a NOTICE every this many rows reporting how many rows have been processed. 0 disables progress notices.",
+        &PROGRESS_INTERVAL,
+        0,
+        i32::MAX,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+
+    GucRegistry::define_enum_guc(
+        c"pglance.timestamp_json_format",
+        c"How dates and timestamps are rendered in JSON output.",
+        c"'iso' renders ISO 8601 strings; 'epoch_micros' renders integer microseconds since the Unix epoch.",
+        &TIMESTAMP_JSON_FORMAT,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+
+    GucRegistry::define_enum_guc(
+        c"pglance.timestamp_json_timezone",
+        c"Time zone timestamps are shown in when rendered as ISO strings in JSON output.",
+        c"'utc' renders UTC wall time followed by the column's time zone; 'session' converts time zone aware timestamps to the session TimeZone and leaves naive ones as stored; 'session_assume_utc' also converts naive timestamps, reading them as UTC.",
+        &TIMESTAMP_JSON_TIMEZONE,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+
+    GucRegistry::define_int_guc(
+        c"pglance.stats_cache_ttl_seconds",
+        c"Seconds lance_table_stats results are cached.",
+        c"When greater than zero, lance_table_stats returns cached statistics for a table path for this many seconds before reopening the table. 0 disables the cache.",
+        &STATS_CACHE_TTL_SECONDS,
+        0,
+        i32::MAX,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+
+    GucRegistry::define_int_guc(
+        c"pglance.io_threads",
+        c"Concurrent IO requests per Lance scan.",
+        c"Limits how many fragments and batches a scan reads from the object store at once. 0 keeps Lance's default, which is sized for a whole machine rather than a single backend.",
+        &IO_THREADS,
+        0,
+        1024,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+
+    GucRegistry::define_int_guc(
+        c"pglance.scan_mem_budget_bytes",
+        c"Most bytes of Arrow data a single scan may hold.",
+        c"When greater than zero, a scan that accumulates more than this many bytes of record batches fails with out_of_memory instead of growing the backend further. 0 disables the limit.",
+        &SCAN_MEM_BUDGET_BYTES,
+        0,
+        i32::MAX,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+
+    GucRegistry::define_enum_guc(
+        c"pglance.on_unsupported_type",
+        c"Behavior for values of Arrow types without a PostgreSQL conversion.",
+        c"'text' emits a textual rendering of the value; 'error' fails the query instead of returning a placeholder string.",
+        &ON_UNSUPPORTED_TYPE,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+
+    GucRegistry::define_bool_guc(
+        c"pglance.uint64_as_bigint",
+        c"Map UInt64 columns to bigint instead of numeric.",
+        c"UInt64 values above the bigint range do not fit in int8, so UInt64 columns map to numeric by default. Enable this for tables whose values are known to fit; values that do not fit then fail with numeric_value_out_of_range.",
+        &UINT64_AS_BIGINT,
+        GucContext::Userset,
+        GucFlags::default(),
+    );
+}
@@ -0,0 +1,452 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use arrow::array::{
+    ArrayRef, BinaryArray, BooleanArray, Date32Array, Float32Array, Float64Array, Int16Array,
+    Int32Array, Int64Array, StringArray, TimestampMicrosecondArray,
+};
+use arrow::compute::CastOptions;
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::json::ReaderBuilder;
+use arrow::record_batch::RecordBatch;
+use pgrx::prelude::*;
+use pgrx::spi::{quote_identifier, SpiError, SpiHeapTupleData};
+use pgrx::{pg_sys, PgBuiltInOids, PgOid};
+use serde_json::{json, Map, Value};
+
+use crate::types::pg_type_name;
+use crate::{open_scanner, version_to_bigint};
+
+/// Values of one Lance column read from a query result
+///
+/// Each Arrow type is read through the closest PostgreSQL type and cast to the
+/// exact Arrow type afterwards, so narrowing casts are checked for overflow.
+enum ColumnValues {
+    Bool(Vec<Option<bool>>),
+    Int16(Vec<Option<i16>>),
+    Int32(Vec<Option<i32>>),
+    Int64(Vec<Option<i64>>),
+    Float32(Vec<Option<f32>>),
+    Float64(Vec<Option<f64>>),
+    Text(Vec<Option<String>>),
+    Bytes(Vec<Option<Vec<u8>>>),
+    /// Days since the Unix epoch
+    Date(Vec<Option<i32>>),
+    /// Microseconds since the Unix epoch
+    Timestamp(Vec<Option<i64>>),
+}
+
+impl ColumnValues {
+    fn for_type(data_type: &DataType) -> Option<Self> {
+        let values = match data_type {
+            DataType::Boolean => Self::Bool(Vec::new()),
+            DataType::Int8 | DataType::Int16 | DataType::UInt8 => Self::Int16(Vec::new()),
+            DataType::Int32 | DataType::UInt16 => Self::Int32(Vec::new()),
+            DataType::Int64 | DataType::UInt32 | DataType::UInt64 => Self::Int64(Vec::new()),
+            DataType::Float32 => Self::Float32(Vec::new()),
+            DataType::Float64 => Self::Float64(Vec::new()),
+            DataType::Utf8 | DataType::LargeUtf8 => Self::Text(Vec::new()),
+            DataType::Binary | DataType::LargeBinary => Self::Bytes(Vec::new()),
+            DataType::Date32 | DataType::Date64 => Self::Date(Vec::new()),
+            DataType::Timestamp(_, _) => Self::Timestamp(Vec::new()),
+            _ => return None,
+        };
+        Some(values)
+    }
+
+    /// PostgreSQL type the query column must be assignable to
+    fn pg_type(&self, data_type: &DataType) -> PgOid {
+        let oid = match self {
+            Self::Bool(_) => PgBuiltInOids::BOOLOID,
+            Self::Int16(_) => PgBuiltInOids::INT2OID,
+            Self::Int32(_) => PgBuiltInOids::INT4OID,
+            Self::Int64(_) => PgBuiltInOids::INT8OID,
+            Self::Float32(_) => PgBuiltInOids::FLOAT4OID,
+            Self::Float64(_) => PgBuiltInOids::FLOAT8OID,
+            Self::Text(_) => PgBuiltInOids::TEXTOID,
+            Self::Bytes(_) => PgBuiltInOids::BYTEAOID,
+            Self::Date(_) => PgBuiltInOids::DATEOID,
+            Self::Timestamp(_) => match data_type {
+                DataType::Timestamp(_, Some(_)) => PgBuiltInOids::TIMESTAMPTZOID,
+                _ => PgBuiltInOids::TIMESTAMPOID,
+            },
+        };
+        PgOid::BuiltIn(oid)
+    }
+
+    /// Expression reading `column` of the subquery `q` in this column's form
+    fn select_expr(&self, column: &str, pg_type: PgOid) -> String {
+        let cast = format!("q.{}::{}", quote_identifier(column), pg_type_name(pg_type));
+        match self {
+            Self::Date(_) => format!("({} - DATE '1970-01-01')", cast),
+            Self::Timestamp(_) => format!("(EXTRACT(EPOCH FROM {}) * 1000000)::int8", cast),
+            _ => cast,
+        }
+    }
+
+    fn push(&mut self, row: &SpiHeapTupleData, ordinal: usize) -> Result<(), SpiError> {
+        match self {
+            Self::Bool(values) => values.push(row.get(ordinal)?),
+            Self::Int16(values) => values.push(row.get(ordinal)?),
+            Self::Int32(values) | Self::Date(values) => values.push(row.get(ordinal)?),
+            Self::Int64(values) | Self::Timestamp(values) => values.push(row.get(ordinal)?),
+            Self::Float32(values) => values.push(row.get(ordinal)?),
+            Self::Float64(values) => values.push(row.get(ordinal)?),
+            Self::Text(values) => values.push(row.get(ordinal)?),
+            Self::Bytes(values) => values.push(row.get(ordinal)?),
+        }
+        Ok(())
+    }
+
+    /// Build the column as an array of exactly the field's type
+    fn finish(self, field: &Field) -> Result<ArrayRef, arrow::error::ArrowError> {
+        let array: ArrayRef = match self {
+            Self::Bool(values) => Arc::new(BooleanArray::from(values)),
+            Self::Int16(values) => Arc::new(Int16Array::from(values)),
+            Self::Int32(values) => Arc::new(Int32Array::from(values)),
+            Self::Int64(values) => Arc::new(Int64Array::from(values)),
+            Self::Float32(values) => Arc::new(Float32Array::from(values)),
+            Self::Float64(values) => Arc::new(Float64Array::from(values)),
+            Self::Text(values) => Arc::new(StringArray::from(values)),
+            Self::Bytes(values) => {
+                Arc::new(values.iter().map(|v| v.as_deref()).collect::<BinaryArray>())
+            }
+            Self::Date(values) => Arc::new(Date32Array::from(values)),
+            // The timezone is attached before casting so that the instants are
+            // kept rather than reinterpreted as local times in that zone
+            Self::Timestamp(values) => {
+                let timezone = match field.data_type() {
+                    DataType::Timestamp(_, timezone) => timezone.clone(),
+                    _ => None,
+                };
+                Arc::new(TimestampMicrosecondArray::from(values).with_timezone_opt(timezone))
+            }
+        };
+        let options = CastOptions {
+            safe: false,
+            ..Default::default()
+        };
+        arrow::compute::cast_with_options(&array, field.data_type(), &options)
+    }
+}
+
+/// Append the result of a SQL query to a Lance table
+///
+/// The query's columns are matched to the table's by name, in any order, and
+/// each must be assignable to the PostgreSQL type of its Lance column. Returns
+/// the table version created by the append, or the current version when the
+/// query returns no rows.
+#[pg_extern]
+pub fn lance_insert_from_query(table_path: &str, query: &str) -> i64 {
+    let scanner = open_scanner(table_path, None);
+    let schema = scanner.schema();
+
+    let mut columns: Vec<ColumnValues> = schema
+        .fields()
+        .iter()
+        .map(|field| {
+            ColumnValues::for_type(field.data_type()).unwrap_or_else(|| {
+                ereport!(
+                    ERROR,
+                    PgSqlErrorCode::ERRCODE_FEATURE_NOT_SUPPORTED,
+                    format!(
+                        "Cannot insert into column \"{}\" of type {:?}",
+                        field.name(),
+                        field.data_type()
+                    )
+                )
+            })
+        })
+        .collect();
+    let mismatch = |message: String| -> ! {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_DATATYPE_MISMATCH,
+            format!(
+                "Query result does not match Lance table at {}: {}",
+                table_path, message
+            )
+        )
+    };
+
+    let result = Spi::connect(|client| {
+        let described = client.select(
+            &format!("SELECT * FROM ({}) AS q LIMIT 0", query),
+            None,
+            &[],
+        )?;
+        let mut query_columns = HashMap::new();
+        for ordinal in 1..=described.columns()? {
+            let name = described.column_name(ordinal)?;
+            let oid = described.column_type_oid(ordinal)?;
+            if query_columns.insert(name.clone(), oid).is_some() {
+                mismatch(format!("column \"{}\" is returned more than once", name));
+            }
+        }
+        if query_columns.len() != schema.fields().len() {
+            mismatch(format!(
+                "query returns {} columns, but the table has {}",
+                query_columns.len(),
+                schema.fields().len()
+            ));
+        }
+
+        let mut select_exprs = Vec::with_capacity(columns.len());
+        for (field, values) in schema.fields().iter().zip(&columns) {
+            let source = query_columns
+                .get(field.name())
+                .unwrap_or_else(|| mismatch(format!("column \"{}\" is missing", field.name())));
+            let target = values.pg_type(field.data_type());
+            let coercible = unsafe {
+                pg_sys::can_coerce_type(
+                    1,
+                    &source.value(),
+                    &target.value(),
+                    pg_sys::CoercionContext::COERCION_ASSIGNMENT,
+                )
+            };
+            if !coercible {
+                mismatch(format!(
+                    "column \"{}\" must be assignable to {}",
+                    field.name(),
+                    pg_type_name(target)
+                ));
+            }
+            select_exprs.push(values.select_expr(field.name(), target));
+        }
+
+        let rows = client.select(
+            &format!("SELECT {} FROM ({}) AS q", select_exprs.join(", "), query),
+            None,
+            &[],
+        )?;
+        for row in rows {
+            for (idx, values) in columns.iter_mut().enumerate() {
+                values.push(&row, idx + 1)?;
+            }
+        }
+        Ok::<_, SpiError>(())
+    });
+    result.unwrap_or_else(|e| pgrx::error!("Failed to run query for Lance insert: {}", e));
+
+    let arrays: Vec<ArrayRef> = columns
+        .into_iter()
+        .zip(schema.fields())
+        .map(|(values, field)| {
+            values.finish(field).unwrap_or_else(|e| {
+                ereport!(
+                    ERROR,
+                    PgSqlErrorCode::ERRCODE_NUMERIC_VALUE_OUT_OF_RANGE,
+                    format!("Value out of range for column \"{}\": {}", field.name(), e)
+                )
+            })
+        })
+        .collect();
+    let batch = RecordBatch::try_new(Arc::clone(&schema), arrays)
+        .unwrap_or_else(|e| pgrx::error!("Failed to build record batch: {}", e));
+    if batch.num_rows() == 0 {
+        return version_to_bigint(scanner.version());
+    }
+
+    let version = scanner.append(schema, vec![batch]).unwrap_or_else(|code| {
+        ereport!(
+            ERROR,
+            code,
+            format!("Failed to append to Lance table at: {}", table_path)
+        )
+    });
+    version_to_bigint(version)
+}
+
+extension_sql!(
+    r#"
+REVOKE EXECUTE ON FUNCTION lance_insert_from_query(TEXT, TEXT) FROM PUBLIC;
+"#,
+    name = "revoke_lance_insert_from_query",
+    requires = [lance_insert_from_query],
+);
+
+/// Append JSON rows to a Lance table
+///
+/// Each row is an object keyed by column name; missing keys are NULL. Keys
+/// naming no column are rejected unless `allow_schema_evolution` is set, in
+/// which case they are added as new nullable columns, typed from their JSON
+/// values, before the rows are appended. Returns the table version created by
+/// the append.
+#[pg_extern]
+pub fn lance_append_jsonb(
+    table_path: &str,
+    rows: Vec<pgrx::JsonB>,
+    allow_schema_evolution: default!(bool, false),
+) -> i64 {
+    let mut scanner = open_scanner(table_path, None);
+
+    let rows: Vec<Map<String, Value>> = rows
+        .into_iter()
+        .enumerate()
+        .map(|(idx, pgrx::JsonB(row))| match row {
+            Value::Object(object) => object,
+            _ => ereport!(
+                ERROR,
+                PgSqlErrorCode::ERRCODE_DATATYPE_MISMATCH,
+                format!("Row {} must be a JSON object", idx + 1)
+            ),
+        })
+        .collect();
+    if rows.is_empty() {
+        return version_to_bigint(scanner.version());
+    }
+
+    let schema = scanner.schema();
+    let mut new_columns: Vec<&String> = Vec::new();
+    for key in rows.iter().flat_map(|row| row.keys()) {
+        if schema.field_with_name(key).is_err() && !new_columns.contains(&key) {
+            new_columns.push(key);
+        }
+    }
+    if let Some(column) = new_columns.first() {
+        if !allow_schema_evolution {
+            ereport!(
+                ERROR,
+                PgSqlErrorCode::ERRCODE_UNDEFINED_COLUMN,
+                format!(
+                    "Column \"{}\" does not exist in Lance table at {}; pass allow_schema_evolution => true to add it",
+                    column, table_path
+                )
+            );
+        }
+        let added = infer_new_columns(&rows, &new_columns);
+        scanner.add_null_columns(added).unwrap_or_else(|code| {
+            ereport!(
+                ERROR,
+                code,
+                format!("Failed to add columns to Lance table at: {}", table_path)
+            )
+        });
+        scanner = open_scanner(table_path, None);
+    }
+
+    let schema = scanner.schema();
+    let batch = ReaderBuilder::new(Arc::clone(&schema))
+        .build_decoder()
+        .and_then(|mut decoder| {
+            decoder.serialize(&rows)?;
+            decoder.flush()
+        })
+        .unwrap_or_else(|e| {
+            ereport!(
+                ERROR,
+                PgSqlErrorCode::ERRCODE_DATATYPE_MISMATCH,
+                format!("Rows do not match Lance table at {}: {}", table_path, e)
+            )
+        })
+        .unwrap_or_else(|| RecordBatch::new_empty(Arc::clone(&schema)));
+
+    let version = scanner.append(schema, vec![batch]).unwrap_or_else(|code| {
+        ereport!(
+            ERROR,
+            code,
+            format!("Failed to append to Lance table at: {}", table_path)
+        )
+    });
+    version_to_bigint(version)
+}
+
+extension_sql!(
+    r#"
+REVOKE EXECUTE ON FUNCTION lance_append_jsonb(TEXT, JSONB[], BOOLEAN) FROM PUBLIC;
+"#,
+    name = "revoke_lance_append_jsonb",
+    requires = [lance_append_jsonb],
+);
+
+/// Infer Arrow fields for columns that only exist in the JSON rows
+///
+/// Columns whose values are all NULL have no type to infer and become text.
+fn infer_new_columns(rows: &[Map<String, Value>], columns: &[&String]) -> Arc<Schema> {
+    let values = rows.iter().map(|row| {
+        let object = columns
+            .iter()
+            .filter_map(|&column| Some((column.clone(), row.get(column)?.clone())))
+            .collect();
+        Ok(Value::Object(object))
+    });
+    let inferred =
+        arrow::json::reader::infer_json_schema_from_iterator(values).unwrap_or_else(|e| {
+            ereport!(
+                ERROR,
+                PgSqlErrorCode::ERRCODE_DATATYPE_MISMATCH,
+                format!("Cannot infer types of new columns: {}", e)
+            )
+        });
+
+    let fields: Vec<Field> = columns
+        .iter()
+        .map(|&column| {
+            let data_type = match inferred.field_with_name(column).map(|f| f.data_type()) {
+                Ok(DataType::Null) | Err(_) => DataType::Utf8,
+                Ok(data_type) => data_type.clone(),
+            };
+            Field::new(column.clone(), data_type, true)
+        })
+        .collect();
+    Arc::new(Schema::new(fields))
+}
+
+/// Check that JSON rows convert to a Lance table's columns without writing
+///
+/// Returns a JSONB array with one `{row, column, error}` object per problem,
+/// where `row` is the 1-based position in `rows` and `column` is NULL for
+/// problems with the row as a whole. An empty array means every row converts.
+#[pg_extern]
+pub fn lance_append_validate(table_path: &str, rows: Vec<pgrx::JsonB>) -> pgrx::JsonB {
+    let scanner = open_scanner(table_path, None);
+    let schema = scanner.schema();
+
+    let mut errors = Vec::new();
+    for (idx, pgrx::JsonB(row)) in rows.iter().enumerate() {
+        let row_number = idx + 1;
+        let Value::Object(object) = row else {
+            errors.push(json!({
+                "row": row_number,
+                "column": null,
+                "error": "row must be a JSON object",
+            }));
+            continue;
+        };
+        for key in object.keys() {
+            if schema.field_with_name(key).is_err() {
+                errors.push(json!({
+                    "row": row_number,
+                    "column": key,
+                    "error": "column does not exist in Lance table",
+                }));
+            }
+        }
+        for field in schema.fields() {
+            let value = object.get(field.name()).cloned().unwrap_or(Value::Null);
+            if let Err(e) = convert_json_value(field, value) {
+                errors.push(json!({
+                    "row": row_number,
+                    "column": field.name(),
+                    "error": e.to_string(),
+                }));
+            }
+        }
+    }
+    pgrx::JsonB(Value::Array(errors))
+}
+
+/// Convert one JSON value to a single-row array of the field's type
+fn convert_json_value(field: &Field, value: Value) -> Result<ArrayRef, arrow::error::ArrowError> {
+    let schema = Arc::new(Schema::new(vec![field.clone()]));
+    let mut decoder = ReaderBuilder::new(schema).build_decoder()?;
+    decoder.serialize(&[Value::Object(Map::from_iter([(
+        field.name().clone(),
+        value,
+    )]))])?;
+    let batch = decoder
+        .flush()?
+        .ok_or_else(|| arrow::error::ArrowError::JsonError("value produced no row".to_string()))?;
+    Ok(Arc::clone(batch.column(0)))
+}
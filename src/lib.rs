@@ -1,270 +1,697 @@
 use pgrx::prelude::*;
 
 use arrow::array::{
-    Array, BinaryArray, BooleanArray, Date32Array, Date64Array, FixedSizeBinaryArray,
-    FixedSizeListArray, Float16Array, Float32Array, Float64Array, GenericListArray, Int16Array,
-    Int32Array, Int64Array, Int8Array, LargeBinaryArray, LargeStringArray, StringArray,
-    StructArray, TimestampMicrosecondArray, TimestampMillisecondArray, TimestampNanosecondArray,
-    TimestampSecondArray, UInt16Array, UInt32Array, UInt64Array, UInt8Array,
+    Array, ArrayRef, BinaryArray, BooleanArray, Date32Array, Date64Array, Decimal128Array,
+    Decimal256Array, DictionaryArray, DurationMicrosecondArray, DurationMillisecondArray,
+    DurationNanosecondArray, DurationSecondArray, FixedSizeBinaryArray, FixedSizeListArray,
+    Float16Array, Float32Array, Float64Array, GenericListArray, Int16Array, Int32Array, Int64Array,
+    Int8Array, IntervalDayTimeArray, IntervalMonthDayNanoArray, IntervalYearMonthArray,
+    LargeBinaryArray, LargeStringArray, StringArray, StructArray, Time32MillisecondArray,
+    Time32SecondArray, Time64MicrosecondArray, Time64NanosecondArray, TimestampMicrosecondArray,
+    TimestampMillisecondArray, TimestampNanosecondArray, TimestampSecondArray, UInt16Array,
+    UInt32Array, UInt64Array, UInt8Array,
 };
-use arrow::datatypes::{DataType, TimeUnit as ArrowTimeUnit};
+use arrow::datatypes::{
+    ArrowDictionaryKeyType, DataType, Int16Type, Int32Type, Int64Type, Int8Type, IntervalUnit,
+    TimeUnit as ArrowTimeUnit, UInt16Type, UInt32Type, UInt64Type, UInt8Type,
+};
+use arrow::record_batch::RecordBatch;
 use base64::{engine::general_purpose::STANDARD, Engine as _};
-use chrono::NaiveDate;
+use chrono::{Datelike, NaiveDate, NaiveDateTime, Timelike};
 use serde_json::{json, Map, Number, Value};
+use std::sync::Arc;
+use tdigest::TDigest;
+use xxhash_rust::xxh3::xxh3_64;
 
+mod cursor;
+mod guc;
+mod insert;
 mod scanner;
+mod typed;
 mod types;
 
-use scanner::LanceScanner;
-use types::arrow_schema_to_pg_columns;
+use scanner::{LanceScanIterator, LanceScanner, ScanOptions, DELETED_COLUMN};
+use types::{arrow_schema_to_pg_columns, downcast_array, ConversionError};
 
 pgrx::pg_module_magic!();
 
+/// Name of the row address column Lance adds to scans
+const ROW_ADDRESS_COLUMN: &str = "_rowaddr";
+
+/// Appended to the base64 of binary values cut short by `max_binary_bytes`
+const TRUNCATED_BINARY_MARKER: &str = "...truncated";
+
+#[pg_guard]
+pub extern "C-unwind" fn _PG_init() {
+    guc::init();
+}
+
 // extension_sql_file!("./sql/bootstrap.sql", bootstrap);
 
-fn arrow_value_to_serde_json(array: &dyn Array, row_idx: usize) -> Value {
+fn arrow_value_to_serde_json(array: &dyn Array, row_idx: usize) -> Result<Value, ConversionError> {
     if array.is_null(row_idx) {
-        return Value::Null;
+        return Ok(Value::Null);
     }
 
-    match array.data_type() {
-        DataType::Boolean => Value::Bool(
-            array
-                .as_any()
-                .downcast_ref::<BooleanArray>()
-                .unwrap()
-                .value(row_idx),
-        ),
-        DataType::Int8 => json!(array
-            .as_any()
-            .downcast_ref::<Int8Array>()
-            .unwrap()
-            .value(row_idx)),
-        DataType::Int16 => json!(array
-            .as_any()
-            .downcast_ref::<Int16Array>()
-            .unwrap()
-            .value(row_idx)),
-        DataType::Int32 => json!(array
-            .as_any()
-            .downcast_ref::<Int32Array>()
-            .unwrap()
-            .value(row_idx)),
-        DataType::Int64 => json!(array
-            .as_any()
-            .downcast_ref::<Int64Array>()
-            .unwrap()
-            .value(row_idx)),
-        DataType::UInt8 => json!(array
-            .as_any()
-            .downcast_ref::<UInt8Array>()
-            .unwrap()
-            .value(row_idx)),
-        DataType::UInt16 => json!(array
-            .as_any()
-            .downcast_ref::<UInt16Array>()
-            .unwrap()
-            .value(row_idx)),
-        DataType::UInt32 => json!(array
-            .as_any()
-            .downcast_ref::<UInt32Array>()
-            .unwrap()
-            .value(row_idx)),
-        DataType::UInt64 => json!(array
-            .as_any()
-            .downcast_ref::<UInt64Array>()
-            .unwrap()
-            .value(row_idx)),
+    let value = match array.data_type() {
+        DataType::Boolean => {
+            Value::Bool(downcast_array::<BooleanArray>(array, "BooleanArray")?.value(row_idx))
+        }
+        DataType::Int8 => json!(downcast_array::<Int8Array>(array, "Int8Array")?.value(row_idx)),
+        DataType::Int16 => json!(downcast_array::<Int16Array>(array, "Int16Array")?.value(row_idx)),
+        DataType::Int32 => json!(downcast_array::<Int32Array>(array, "Int32Array")?.value(row_idx)),
+        DataType::Int64 => json!(downcast_array::<Int64Array>(array, "Int64Array")?.value(row_idx)),
+        DataType::UInt8 => json!(downcast_array::<UInt8Array>(array, "UInt8Array")?.value(row_idx)),
+        DataType::UInt16 => {
+            json!(downcast_array::<UInt16Array>(array, "UInt16Array")?.value(row_idx))
+        }
+        DataType::UInt32 => {
+            json!(downcast_array::<UInt32Array>(array, "UInt32Array")?.value(row_idx))
+        }
+        DataType::UInt64 => {
+            json!(downcast_array::<UInt64Array>(array, "UInt64Array")?.value(row_idx))
+        }
         DataType::Float16 => {
-            let val = array
-                .as_any()
-                .downcast_ref::<Float16Array>()
-                .unwrap()
-                .value(row_idx);
+            let val = downcast_array::<Float16Array>(array, "Float16Array")?.value(row_idx);
             Number::from_f64(val.to_f32() as f64)
                 .map(Value::Number)
                 .unwrap_or(Value::Null)
         }
         DataType::Float32 => {
-            let val = array
-                .as_any()
-                .downcast_ref::<Float32Array>()
-                .unwrap()
-                .value(row_idx);
+            let val = downcast_array::<Float32Array>(array, "Float32Array")?.value(row_idx);
             Number::from_f64(val as f64)
                 .map(Value::Number)
                 .unwrap_or(Value::Null)
         }
         DataType::Float64 => {
-            let val = array
-                .as_any()
-                .downcast_ref::<Float64Array>()
-                .unwrap()
-                .value(row_idx);
+            let val = downcast_array::<Float64Array>(array, "Float64Array")?.value(row_idx);
             Number::from_f64(val)
                 .map(Value::Number)
                 .unwrap_or(Value::Null)
         }
         DataType::Utf8 => Value::String(
-            array
-                .as_any()
-                .downcast_ref::<StringArray>()
-                .unwrap()
+            downcast_array::<StringArray>(array, "StringArray")?
                 .value(row_idx)
                 .to_string(),
         ),
         DataType::LargeUtf8 => Value::String(
-            array
-                .as_any()
-                .downcast_ref::<LargeStringArray>()
-                .unwrap()
+            downcast_array::<LargeStringArray>(array, "LargeStringArray")?
                 .value(row_idx)
                 .to_string(),
         ),
-        DataType::Date32 => {
-            let days = array
-                .as_any()
-                .downcast_ref::<Date32Array>()
-                .unwrap()
-                .value(row_idx);
-            NaiveDate::from_ymd_opt(1970, 1, 1)
-                .and_then(|d| d.checked_add_signed(chrono::Duration::days(days as i64)))
-                .map(|d| Value::String(d.to_string()))
-                .unwrap_or(Value::Null)
-        }
-        DataType::Date64 => {
-            let millis = array
-                .as_any()
-                .downcast_ref::<Date64Array>()
-                .unwrap()
-                .value(row_idx);
-            chrono::DateTime::from_timestamp_millis(millis)
-                .map(|dt| Value::String(dt.naive_utc().date().to_string()))
-                .unwrap_or(Value::Null)
+        DataType::Date32 | DataType::Date64 => {
+            let date = match arrow_date_value(array, row_idx) {
+                Ok(date) => date,
+                Err(e) => return json_out_of_range(e, Value::Null),
+            };
+            match guc::TIMESTAMP_JSON_FORMAT.get() {
+                guc::TimestampJsonFormat::Iso => Value::String(date.to_string()),
+                guc::TimestampJsonFormat::EpochMicros => {
+                    json!(date
+                        .and_time(chrono::NaiveTime::MIN)
+                        .and_utc()
+                        .timestamp_micros())
+                }
+            }
         }
         DataType::Timestamp(unit, tz_opt) => {
-            let naive_dt_opt = match unit {
-                ArrowTimeUnit::Second => {
-                    let secs = array
-                        .as_any()
-                        .downcast_ref::<TimestampSecondArray>()
-                        .unwrap()
-                        .value(row_idx);
-                    chrono::DateTime::from_timestamp(secs, 0).map(|dt| dt.naive_utc())
+            let dt = match arrow_timestamp_value(array, unit, row_idx) {
+                Ok(dt) => dt,
+                Err(e) => {
+                    let invalid = match tz_opt {
+                        Some(tz) => format!("InvalidTimestamp {}", tz),
+                        None => "InvalidTimestamp".to_string(),
+                    };
+                    return json_out_of_range(e, Value::String(invalid));
                 }
-                ArrowTimeUnit::Millisecond => {
-                    let millis = array
-                        .as_any()
-                        .downcast_ref::<TimestampMillisecondArray>()
-                        .unwrap()
-                        .value(row_idx);
-                    chrono::DateTime::from_timestamp_millis(millis).map(|dt| dt.naive_utc())
+            };
+            let in_session_zone = match guc::TIMESTAMP_JSON_TIMEZONE.get() {
+                guc::TimestampJsonTimezone::Utc => false,
+                guc::TimestampJsonTimezone::Session => tz_opt.is_some(),
+                guc::TimestampJsonTimezone::SessionAssumeUtc => true,
+            };
+            match (guc::TIMESTAMP_JSON_FORMAT.get(), tz_opt) {
+                (guc::TimestampJsonFormat::EpochMicros, _) => {
+                    json!(dt.and_utc().timestamp_micros())
                 }
-                ArrowTimeUnit::Microsecond => {
-                    let micros = array
-                        .as_any()
-                        .downcast_ref::<TimestampMicrosecondArray>()
-                        .unwrap()
-                        .value(row_idx);
-                    chrono::DateTime::from_timestamp_micros(micros).map(|dt| dt.naive_utc())
+                (guc::TimestampJsonFormat::Iso, _) if in_session_zone => {
+                    Value::String(types::session_timestamp_string(dt.and_utc())?)
                 }
-                ArrowTimeUnit::Nanosecond => {
-                    let nanos = array
-                        .as_any()
-                        .downcast_ref::<TimestampNanosecondArray>()
-                        .unwrap()
-                        .value(row_idx);
-                    chrono::DateTime::from_timestamp(
-                        nanos / 1_000_000_000,
-                        (nanos % 1_000_000_000) as u32,
-                    )
-                    .map(|dt| dt.naive_utc())
+                (guc::TimestampJsonFormat::Iso, Some(tz)) => {
+                    Value::String(format!("{} {}", dt, tz))
                 }
-            };
-            let dt_str = naive_dt_opt
-                .map(|dt| dt.to_string())
-                .unwrap_or_else(|| "InvalidTimestamp".to_string());
-            if let Some(tz) = tz_opt {
-                Value::String(format!("{} {}", dt_str, tz))
-            } else {
-                Value::String(dt_str)
+                (guc::TimestampJsonFormat::Iso, None) => Value::String(dt.to_string()),
             }
         }
+        // `value(row_idx)` and the child arrays of a struct already account for
+        // the array's offset, so rows of sliced batches resolve correctly.
         DataType::List(_) | DataType::LargeList(_) | DataType::FixedSizeList(_, _) => {
             fn handle_list<OffsetSize: arrow::array::OffsetSizeTrait>(
                 array: &dyn Array,
                 row_idx: usize,
-            ) -> Value {
-                let list_array = array
-                    .as_any()
-                    .downcast_ref::<GenericListArray<OffsetSize>>()
-                    .unwrap();
+            ) -> Result<Value, ConversionError> {
+                let list_array =
+                    downcast_array::<GenericListArray<OffsetSize>>(array, "ListArray")?;
                 let value_array_for_row = list_array.value(row_idx);
                 let mut json_list = Vec::new();
                 for i in 0..value_array_for_row.len() {
-                    json_list.push(arrow_value_to_serde_json(value_array_for_row.as_ref(), i));
+                    json_list.push(arrow_value_to_serde_json(value_array_for_row.as_ref(), i)?);
                 }
-                Value::Array(json_list)
+                Ok(Value::Array(json_list))
             }
-            fn handle_fixed_size_list(array: &dyn Array, row_idx: usize) -> Value {
-                let list_array = array.as_any().downcast_ref::<FixedSizeListArray>().unwrap();
+            fn handle_fixed_size_list(
+                array: &dyn Array,
+                row_idx: usize,
+            ) -> Result<Value, ConversionError> {
+                let list_array = downcast_array::<FixedSizeListArray>(array, "FixedSizeListArray")?;
                 let value_array_for_row = list_array.value(row_idx);
                 let mut json_list = Vec::new();
                 for i in 0..value_array_for_row.len() {
-                    json_list.push(arrow_value_to_serde_json(value_array_for_row.as_ref(), i));
+                    json_list.push(arrow_value_to_serde_json(value_array_for_row.as_ref(), i)?);
                 }
-                Value::Array(json_list)
+                Ok(Value::Array(json_list))
             }
 
             match array.data_type() {
-                DataType::List(_) => handle_list::<i32>(array, row_idx),
-                DataType::LargeList(_) => handle_list::<i64>(array, row_idx),
-                DataType::FixedSizeList(_, _) => handle_fixed_size_list(array, row_idx),
+                DataType::List(_) => handle_list::<i32>(array, row_idx)?,
+                DataType::LargeList(_) => handle_list::<i64>(array, row_idx)?,
+                DataType::FixedSizeList(_, _) => handle_fixed_size_list(array, row_idx)?,
                 _ => unreachable!(),
             }
         }
         DataType::Struct(fields) => {
-            let struct_array = array.as_any().downcast_ref::<StructArray>().unwrap();
+            let struct_array = downcast_array::<StructArray>(array, "StructArray")?;
             let mut json_map = Map::new();
             for (i, field) in fields.iter().enumerate() {
                 let field_array = struct_array.column(i);
                 json_map.insert(
                     field.name().clone(),
-                    arrow_value_to_serde_json(field_array.as_ref(), row_idx),
+                    arrow_value_to_serde_json(field_array.as_ref(), row_idx)?,
                 );
             }
             Value::Object(json_map)
         }
+        DataType::Duration(unit) => Value::String(types::iso8601_duration(
+            arrow_duration_value(array, unit, row_idx)?,
+            unit,
+        )),
         DataType::Binary => Value::String(
-            STANDARD.encode(
-                array
-                    .as_any()
-                    .downcast_ref::<BinaryArray>()
-                    .unwrap()
-                    .value(row_idx),
-            ),
-        ),
-        DataType::LargeBinary => Value::String(
-            STANDARD.encode(
-                array
-                    .as_any()
-                    .downcast_ref::<LargeBinaryArray>()
-                    .unwrap()
-                    .value(row_idx),
-            ),
-        ),
-        DataType::FixedSizeBinary(_) => Value::String(
-            STANDARD.encode(
-                array
-                    .as_any()
-                    .downcast_ref::<FixedSizeBinaryArray>()
-                    .unwrap()
-                    .value(row_idx),
-            ),
+            STANDARD.encode(downcast_array::<BinaryArray>(array, "BinaryArray")?.value(row_idx)),
         ),
+        DataType::LargeBinary => {
+            Value::String(STANDARD.encode(
+                downcast_array::<LargeBinaryArray>(array, "LargeBinaryArray")?.value(row_idx),
+            ))
+        }
+        DataType::FixedSizeBinary(_) => Value::String(STANDARD.encode(
+            downcast_array::<FixedSizeBinaryArray>(array, "FixedSizeBinaryArray")?.value(row_idx),
+        )),
+        DataType::Dictionary(_, _) => {
+            let (values, key) = arrow_dictionary_entry(array, row_idx)?;
+            arrow_value_to_serde_json(values.as_ref(), key)?
+        }
+        DataType::Decimal128(_, _) | DataType::Decimal256(_, _) => {
+            decimal_to_json(array, row_idx, types::DecimalFormat::Numeric)?
+        }
+        DataType::Interval(unit) => {
+            let (months, days, nanos) = arrow_interval_value(array, unit, row_idx)?;
+            Value::String(types::iso8601_interval(months, days, nanos))
+        }
 
         _ => Value::String(format!("<unsupported_type: {:?}>", array.data_type())),
+    };
+    Ok(value)
+}
+
+/// Handle a date or timestamp that does not fit chrono's range in JSON output
+///
+/// Unless `pglance.on_row_error` is `skip`, which drops the row, such values
+/// keep their long-standing rendering: `null` for dates and
+/// `InvalidTimestamp` for timestamps.
+fn json_out_of_range(e: ConversionError, rendering: Value) -> Result<Value, ConversionError> {
+    match guc::ON_ROW_ERROR.get() {
+        guc::OnRowError::Abort if e.code == PgSqlErrorCode::ERRCODE_DATETIME_VALUE_OUT_OF_RANGE => {
+            Ok(rendering)
+        }
+        _ => Err(e),
+    }
+}
+
+/// Encode a non-NULL decimal value as JSON in the given format
+fn decimal_to_json(
+    array: &dyn Array,
+    row_idx: usize,
+    format: types::DecimalFormat,
+) -> Result<Value, ConversionError> {
+    let (unscaled, text, scale) = match array.data_type() {
+        DataType::Decimal128(_, scale) => {
+            let decimals = downcast_array::<Decimal128Array>(array, "Decimal128Array")?;
+            let unscaled = decimals.value(row_idx).to_string();
+            (unscaled, decimals.value_as_string(row_idx), *scale)
+        }
+        DataType::Decimal256(_, scale) => {
+            let decimals = downcast_array::<Decimal256Array>(array, "Decimal256Array")?;
+            let unscaled = decimals.value(row_idx).to_string();
+            (unscaled, decimals.value_as_string(row_idx), *scale)
+        }
+        other => {
+            return Err(ConversionError::new(
+                PgSqlErrorCode::ERRCODE_DATATYPE_MISMATCH,
+                format!("expected a decimal array, found {:?}", other),
+            ))
+        }
+    };
+
+    match format {
+        types::DecimalFormat::Numeric => serde_json::from_str::<Number>(&text)
+            .map(Value::Number)
+            .map_err(|e| {
+                ConversionError::new(
+                    PgSqlErrorCode::ERRCODE_NUMERIC_VALUE_OUT_OF_RANGE,
+                    format!("decimal {} does not fit a JSON number: {}", text, e),
+                )
+            }),
+        types::DecimalFormat::ScaledInt => Ok(json!({ "unscaled": unscaled, "scale": scale })),
+        types::DecimalFormat::Text => Ok(Value::String(text)),
+    }
+}
+
+/// Resolve a dictionary-encoded value to its values array and key
+///
+/// `row_idx` is local to `array`, so dictionaries nested in lists or structs
+/// are resolved against the child array's own keys.
+fn arrow_dictionary_entry(
+    array: &dyn Array,
+    row_idx: usize,
+) -> Result<(ArrayRef, usize), ConversionError> {
+    fn entry<K: ArrowDictionaryKeyType>(
+        array: &dyn Array,
+        row_idx: usize,
+    ) -> Result<(ArrayRef, usize), ConversionError> {
+        let dictionary = downcast_array::<DictionaryArray<K>>(array, "DictionaryArray")?;
+        let key = dictionary.key(row_idx).ok_or_else(|| {
+            ConversionError::new(
+                PgSqlErrorCode::ERRCODE_DATA_EXCEPTION,
+                format!("dictionary key at row {} is null", row_idx),
+            )
+        })?;
+        Ok((Arc::clone(dictionary.values()), key))
+    }
+
+    match array.data_type() {
+        DataType::Dictionary(key_type, _) => match key_type.as_ref() {
+            DataType::Int8 => entry::<Int8Type>(array, row_idx),
+            DataType::Int16 => entry::<Int16Type>(array, row_idx),
+            DataType::Int32 => entry::<Int32Type>(array, row_idx),
+            DataType::Int64 => entry::<Int64Type>(array, row_idx),
+            DataType::UInt8 => entry::<UInt8Type>(array, row_idx),
+            DataType::UInt16 => entry::<UInt16Type>(array, row_idx),
+            DataType::UInt32 => entry::<UInt32Type>(array, row_idx),
+            DataType::UInt64 => entry::<UInt64Type>(array, row_idx),
+            other => Err(ConversionError::new(
+                PgSqlErrorCode::ERRCODE_DATATYPE_MISMATCH,
+                format!("unsupported dictionary key type {:?}", other),
+            )),
+        },
+        other => Err(ConversionError::new(
+            PgSqlErrorCode::ERRCODE_DATATYPE_MISMATCH,
+            format!("expected a dictionary array, found {:?}", other),
+        )),
+    }
+}
+
+/// Decode a Date32/Date64 value into a calendar date
+fn arrow_date_value(array: &dyn Array, row_idx: usize) -> Result<NaiveDate, ConversionError> {
+    match array.data_type() {
+        DataType::Date32 => {
+            let days = downcast_array::<Date32Array>(array, "Date32Array")?.value(row_idx);
+            NaiveDate::from_ymd_opt(1970, 1, 1)
+                .and_then(|d| d.checked_add_signed(chrono::Duration::days(days as i64)))
+                .ok_or_else(|| {
+                    ConversionError::new(
+                        PgSqlErrorCode::ERRCODE_DATETIME_VALUE_OUT_OF_RANGE,
+                        format!("date value {} days is out of range", days),
+                    )
+                })
+        }
+        DataType::Date64 => {
+            let millis = downcast_array::<Date64Array>(array, "Date64Array")?.value(row_idx);
+            chrono::DateTime::from_timestamp_millis(millis)
+                .map(|dt| dt.naive_utc().date())
+                .ok_or_else(|| {
+                    ConversionError::new(
+                        PgSqlErrorCode::ERRCODE_DATETIME_VALUE_OUT_OF_RANGE,
+                        format!("date value {} ms is out of range", millis),
+                    )
+                })
+        }
+        other => Err(ConversionError::new(
+            PgSqlErrorCode::ERRCODE_DATATYPE_MISMATCH,
+            format!("expected a date array, found {:?}", other),
+        )),
+    }
+}
+
+/// Decode a Timestamp value into a naive UTC datetime
+fn arrow_timestamp_value(
+    array: &dyn Array,
+    unit: &ArrowTimeUnit,
+    row_idx: usize,
+) -> Result<NaiveDateTime, ConversionError> {
+    let naive_dt_opt = match unit {
+        ArrowTimeUnit::Second => {
+            let secs = downcast_array::<TimestampSecondArray>(array, "TimestampSecondArray")?
+                .value(row_idx);
+            chrono::DateTime::from_timestamp(secs, 0).map(|dt| dt.naive_utc())
+        }
+        ArrowTimeUnit::Millisecond => {
+            let millis =
+                downcast_array::<TimestampMillisecondArray>(array, "TimestampMillisecondArray")?
+                    .value(row_idx);
+            chrono::DateTime::from_timestamp_millis(millis).map(|dt| dt.naive_utc())
+        }
+        ArrowTimeUnit::Microsecond => {
+            let micros =
+                downcast_array::<TimestampMicrosecondArray>(array, "TimestampMicrosecondArray")?
+                    .value(row_idx);
+            chrono::DateTime::from_timestamp_micros(micros).map(|dt| dt.naive_utc())
+        }
+        ArrowTimeUnit::Nanosecond => {
+            let nanos =
+                downcast_array::<TimestampNanosecondArray>(array, "TimestampNanosecondArray")?
+                    .value(row_idx);
+            chrono::DateTime::from_timestamp(
+                nanos.div_euclid(1_000_000_000),
+                nanos.rem_euclid(1_000_000_000) as u32,
+            )
+            .map(|dt| dt.naive_utc())
+        }
+    };
+    naive_dt_opt.ok_or_else(|| {
+        ConversionError::new(
+            PgSqlErrorCode::ERRCODE_DATETIME_VALUE_OUT_OF_RANGE,
+            format!("timestamp value is out of range for {:?}", unit),
+        )
+    })
+}
+
+/// Decode a Time32/Time64 value into microseconds since midnight
+fn arrow_time_micros(array: &dyn Array, row_idx: usize) -> Result<i64, ConversionError> {
+    let micros = match array.data_type() {
+        DataType::Time32(ArrowTimeUnit::Second) => {
+            downcast_array::<Time32SecondArray>(array, "Time32SecondArray")?.value(row_idx) as i64
+                * 1_000_000
+        }
+        DataType::Time32(ArrowTimeUnit::Millisecond) => {
+            downcast_array::<Time32MillisecondArray>(array, "Time32MillisecondArray")?
+                .value(row_idx) as i64
+                * 1_000
+        }
+        DataType::Time64(ArrowTimeUnit::Microsecond) => {
+            downcast_array::<Time64MicrosecondArray>(array, "Time64MicrosecondArray")?
+                .value(row_idx)
+        }
+        DataType::Time64(ArrowTimeUnit::Nanosecond) => {
+            downcast_array::<Time64NanosecondArray>(array, "Time64NanosecondArray")?
+                .value(row_idx)
+                .div_euclid(1_000)
+        }
+        other => {
+            return Err(ConversionError::new(
+                PgSqlErrorCode::ERRCODE_DATATYPE_MISMATCH,
+                format!("expected a time array, found {:?}", other),
+            ))
+        }
+    };
+    if !(0..86_400_000_000).contains(&micros) {
+        return Err(ConversionError::new(
+            PgSqlErrorCode::ERRCODE_DATETIME_VALUE_OUT_OF_RANGE,
+            format!("time value {} us is outside of a day", micros),
+        ));
+    }
+    Ok(micros)
+}
+
+/// Read the raw tick count of a Duration value
+fn arrow_duration_value(
+    array: &dyn Array,
+    unit: &ArrowTimeUnit,
+    row_idx: usize,
+) -> Result<i64, ConversionError> {
+    let value = match unit {
+        ArrowTimeUnit::Second => {
+            downcast_array::<DurationSecondArray>(array, "DurationSecondArray")?.value(row_idx)
+        }
+        ArrowTimeUnit::Millisecond => {
+            downcast_array::<DurationMillisecondArray>(array, "DurationMillisecondArray")?
+                .value(row_idx)
+        }
+        ArrowTimeUnit::Microsecond => {
+            downcast_array::<DurationMicrosecondArray>(array, "DurationMicrosecondArray")?
+                .value(row_idx)
+        }
+        ArrowTimeUnit::Nanosecond => {
+            downcast_array::<DurationNanosecondArray>(array, "DurationNanosecondArray")?
+                .value(row_idx)
+        }
+    };
+    Ok(value)
+}
+
+/// Read an interval value as `(months, days, nanoseconds)`
+fn arrow_interval_value(
+    array: &dyn Array,
+    unit: &IntervalUnit,
+    row_idx: usize,
+) -> Result<(i32, i32, i64), ConversionError> {
+    let value = match unit {
+        IntervalUnit::YearMonth => (
+            downcast_array::<IntervalYearMonthArray>(array, "IntervalYearMonthArray")?
+                .value(row_idx),
+            0,
+            0,
+        ),
+        IntervalUnit::DayTime => {
+            let value = downcast_array::<IntervalDayTimeArray>(array, "IntervalDayTimeArray")?
+                .value(row_idx);
+            (0, value.days, i64::from(value.milliseconds) * 1_000_000)
+        }
+        IntervalUnit::MonthDayNano => {
+            let value =
+                downcast_array::<IntervalMonthDayNanoArray>(array, "IntervalMonthDayNanoArray")?
+                    .value(row_idx);
+            (value.months, value.days, value.nanoseconds)
+        }
+    };
+    Ok(value)
+}
+
+/// Convert a single Arrow value into a PostgreSQL datum
+///
+/// The datum type matches `types::arrow_data_type_to_pg_type` for the array's data type.
+/// Complex values without a direct PostgreSQL counterpart go through
+/// `arrow_value_to_serde_json` and become JSONB, where nested booleans stay
+/// JSON booleans. Types without a PostgreSQL mapping fall back to text, or
+/// fail when `pglance.on_unsupported_type` is `error`; a mapped type is never
+/// returned as text. Returns `None` for NULL.
+pub fn arrow_value_to_datum(
+    array: &dyn Array,
+    row_idx: usize,
+) -> Result<Option<pg_sys::Datum>, ConversionError> {
+    if array.is_null(row_idx) {
+        return Ok(None);
+    }
+
+    fn out_of_range(value: impl std::fmt::Display, pg_type: &str) -> ConversionError {
+        ConversionError::new(
+            PgSqlErrorCode::ERRCODE_NUMERIC_VALUE_OUT_OF_RANGE,
+            format!("value {} is out of range for type {}", value, pg_type),
+        )
     }
+
+    let datum = match array.data_type() {
+        DataType::Boolean => downcast_array::<BooleanArray>(array, "BooleanArray")?
+            .value(row_idx)
+            .into_datum(),
+        DataType::Int8 => downcast_array::<Int8Array>(array, "Int8Array")?
+            .value(row_idx)
+            .into_datum(),
+        DataType::Int16 => downcast_array::<Int16Array>(array, "Int16Array")?
+            .value(row_idx)
+            .into_datum(),
+        DataType::Int32 => downcast_array::<Int32Array>(array, "Int32Array")?
+            .value(row_idx)
+            .into_datum(),
+        DataType::Int64 => downcast_array::<Int64Array>(array, "Int64Array")?
+            .value(row_idx)
+            .into_datum(),
+        DataType::UInt8 => {
+            let val = downcast_array::<UInt8Array>(array, "UInt8Array")?.value(row_idx);
+            i8::try_from(val)
+                .map_err(|_| out_of_range(val, "char"))?
+                .into_datum()
+        }
+        DataType::UInt16 => {
+            let val = downcast_array::<UInt16Array>(array, "UInt16Array")?.value(row_idx);
+            i16::try_from(val)
+                .map_err(|_| out_of_range(val, "int2"))?
+                .into_datum()
+        }
+        DataType::UInt32 => {
+            let val = downcast_array::<UInt32Array>(array, "UInt32Array")?.value(row_idx);
+            i32::try_from(val)
+                .map_err(|_| out_of_range(val, "int4"))?
+                .into_datum()
+        }
+        DataType::UInt64 if guc::UINT64_AS_BIGINT.get() => {
+            let val = downcast_array::<UInt64Array>(array, "UInt64Array")?.value(row_idx);
+            i64::try_from(val)
+                .map_err(|_| out_of_range(val, "int8"))?
+                .into_datum()
+        }
+        DataType::UInt64 => pgrx::AnyNumeric::from(
+            downcast_array::<UInt64Array>(array, "UInt64Array")?.value(row_idx),
+        )
+        .into_datum(),
+        DataType::Float16 => downcast_array::<Float16Array>(array, "Float16Array")?
+            .value(row_idx)
+            .to_f32()
+            .into_datum(),
+        DataType::Float32 => downcast_array::<Float32Array>(array, "Float32Array")?
+            .value(row_idx)
+            .into_datum(),
+        DataType::Float64 => downcast_array::<Float64Array>(array, "Float64Array")?
+            .value(row_idx)
+            .into_datum(),
+        DataType::Utf8 => downcast_array::<StringArray>(array, "StringArray")?
+            .value(row_idx)
+            .into_datum(),
+        DataType::LargeUtf8 => downcast_array::<LargeStringArray>(array, "LargeStringArray")?
+            .value(row_idx)
+            .into_datum(),
+        DataType::Binary => downcast_array::<BinaryArray>(array, "BinaryArray")?
+            .value(row_idx)
+            .into_datum(),
+        DataType::LargeBinary => downcast_array::<LargeBinaryArray>(array, "LargeBinaryArray")?
+            .value(row_idx)
+            .into_datum(),
+        DataType::FixedSizeBinary(_) => {
+            downcast_array::<FixedSizeBinaryArray>(array, "FixedSizeBinaryArray")?
+                .value(row_idx)
+                .into_datum()
+        }
+        DataType::Date32 | DataType::Date64 => {
+            let date = arrow_date_value(array, row_idx)?;
+            pgrx::datum::Date::new(date.year(), date.month() as u8, date.day() as u8)
+                .map_err(|_| out_of_range(date, "date"))?
+                .into_datum()
+        }
+        // Timezone-aware values are UTC instants, whatever zone they carry
+        DataType::Timestamp(unit, Some(_)) => {
+            let dt = arrow_timestamp_value(array, unit, row_idx)?;
+            types::chrono_to_pg_timestamptz(dt.and_utc())?.into_datum()
+        }
+        DataType::Timestamp(unit, None) => {
+            let dt = arrow_timestamp_value(array, unit, row_idx)?;
+            let seconds = dt.second() as f64 + dt.nanosecond() as f64 / 1_000_000_000.0;
+            pgrx::datum::Timestamp::new(
+                dt.year(),
+                dt.month() as u8,
+                dt.day() as u8,
+                dt.hour() as u8,
+                dt.minute() as u8,
+                seconds,
+            )
+            .map_err(|_| out_of_range(dt, "timestamp"))?
+            .into_datum()
+        }
+        DataType::Time32(_) | DataType::Time64(_) => {
+            let micros = arrow_time_micros(array, row_idx)?;
+            let seconds = (micros % 60_000_000) as f64 / 1_000_000.0;
+            pgrx::datum::Time::new(
+                (micros / 3_600_000_000) as u8,
+                (micros / 60_000_000 % 60) as u8,
+                seconds,
+            )
+            .map_err(|_| out_of_range(micros, "time"))?
+            .into_datum()
+        }
+        DataType::Decimal128(_, _) | DataType::Decimal256(_, _) => {
+            let text = match array.data_type() {
+                DataType::Decimal128(_, _) => {
+                    downcast_array::<Decimal128Array>(array, "Decimal128Array")?
+                        .value_as_string(row_idx)
+                }
+                _ => downcast_array::<Decimal256Array>(array, "Decimal256Array")?
+                    .value_as_string(row_idx),
+            };
+            text.parse::<pgrx::AnyNumeric>()
+                .map_err(|_| out_of_range(&text, "numeric"))?
+                .into_datum()
+        }
+        DataType::Interval(unit) => {
+            let (months, days, nanos) = arrow_interval_value(array, unit, row_idx)?;
+            pgrx::datum::Interval::new(months, days, nanos.div_euclid(1_000))
+                .map_err(|_| {
+                    out_of_range(types::iso8601_interval(months, days, nanos), "interval")
+                })?
+                .into_datum()
+        }
+        DataType::Duration(unit) => {
+            let value = arrow_duration_value(array, unit, row_idx)?;
+            let micros = types::duration_to_micros(value, unit)
+                .ok_or_else(|| out_of_range(value, "interval"))?;
+            pgrx::datum::Interval::new(0, 0, micros)
+                .map_err(|_| out_of_range(value, "interval"))?
+                .into_datum()
+        }
+        DataType::List(_) | DataType::LargeList(_) | DataType::FixedSizeList(_, _)
+            if types::is_bool_list_type(array.data_type()) =>
+        {
+            types::arrow_list_to_bool_vec(array, row_idx)?.into_datum()
+        }
+        DataType::FixedSizeList(field, _) if field.data_type() == &DataType::Float32 => {
+            types::arrow_list_to_f32_vec(array, row_idx)?.into_datum()
+        }
+        DataType::FixedSizeList(field, _) if field.data_type() == &DataType::Float64 => {
+            let list_array = downcast_array::<FixedSizeListArray>(array, "FixedSizeListArray")?;
+            let values = list_array.value(row_idx);
+            downcast_array::<Float64Array>(values, "Float64Array")?
+                .iter()
+                .collect::<Vec<Option<f64>>>()
+                .into_datum()
+        }
+        DataType::Dictionary(_, _) => {
+            let (values, key) = arrow_dictionary_entry(array, row_idx)?;
+            return arrow_value_to_datum(values.as_ref(), key);
+        }
+        DataType::List(_)
+        | DataType::LargeList(_)
+        | DataType::FixedSizeList(_, _)
+        | DataType::Struct(_)
+        | DataType::Union(_, _)
+        | DataType::Map(_, _) => {
+            pgrx::JsonB(arrow_value_to_serde_json(array, row_idx)?).into_datum()
+        }
+        // A text datum must never stand in for a value declared with another type
+        other if types::mapped_pg_type(other).is_some_and(|oid| oid.value() != pg_sys::TEXTOID) => {
+            return Err(ConversionError::new(
+                PgSqlErrorCode::ERRCODE_FEATURE_NOT_SUPPORTED,
+                format!("Arrow type {:?} has no PostgreSQL conversion", other),
+            ));
+        }
+        other if guc::ON_UNSUPPORTED_TYPE.get() == guc::OnUnsupportedType::Error => {
+            return Err(ConversionError::new(
+                PgSqlErrorCode::ERRCODE_FEATURE_NOT_SUPPORTED,
+                format!("Arrow type {:?} has no PostgreSQL conversion", other),
+            ));
+        }
+        _ => match arrow_value_to_serde_json(array, row_idx)? {
+            Value::String(s) => s.into_datum(),
+            other => other.to_string().into_datum(),
+        },
+    };
+    Ok(datum)
 }
 
 #[pg_extern]
@@ -284,8 +711,7 @@ pub fn lance_table_info(
         name!(nullable, bool),
     ),
 > {
-    let scanner = LanceScanner::new(table_path)
-        .unwrap_or_else(|_| pgrx::error!("Failed to open Lance table at: {}", table_path));
+    let scanner = open_scanner(table_path, None);
 
     let schema = scanner.schema();
     let columns = arrow_schema_to_pg_columns(schema.as_ref());
@@ -301,214 +727,6976 @@ pub fn lance_table_info(
     TableIterator::new(rows)
 }
 
-/// Get Lance table statistics
+/// Describe the columns a scan projecting `columns` would return, in projection order
 #[pg_extern]
-pub fn lance_table_stats(
+pub fn lance_projected_schema(
     table_path: &str,
+    columns: Vec<String>,
 ) -> TableIterator<
     'static,
     (
-        name!(version, i64),
-        name!(num_rows, i64),
-        name!(num_columns, i32),
+        name!(column_name, String),
+        name!(data_type, String),
+        name!(nullable, bool),
     ),
 > {
-    let scanner = LanceScanner::new(table_path)
-        .unwrap_or_else(|_| pgrx::error!("Failed to open Lance table at: {}", table_path));
+    let scanner = open_scanner(table_path, None);
 
-    let stats = scanner
-        .get_stats()
-        .unwrap_or_else(|_| pgrx::error!("Failed to get table statistics"));
+    let schema = scanner.schema();
+    let rows: Vec<_> = columns
+        .into_iter()
+        .map(|column| {
+            let field = schema.field_with_name(&column).unwrap_or_else(|_| {
+                ereport!(
+                    ERROR,
+                    PgSqlErrorCode::ERRCODE_UNDEFINED_COLUMN,
+                    format!("Column \"{}\" does not exist in Lance table", column)
+                )
+            });
+            let pg_type = types::arrow_to_pg_type(field)
+                .unwrap_or(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::TEXTOID));
+            let type_name = types::pg_type_name(pg_type).to_string();
+            (column, type_name, field.is_nullable())
+        })
+        .collect();
 
-    let row = (
-        stats.version as i64,
-        stats.num_rows as i64,
-        stats.num_columns() as i32,
-    );
+    TableIterator::new(rows)
+}
 
-    TableIterator::new(std::iter::once(row))
+/// Generate a `CREATE TABLE` statement matching a Lance table's schema
+///
+/// Column types follow `arrow_to_pg_type`, so complex types become `jsonb`
+/// columns, and non-nullable fields get `NOT NULL`.
+#[pg_extern]
+pub fn lance_ddl(table_path: &str, table_name: &str) -> String {
+    let scanner = open_scanner(table_path, None);
+
+    let schema = scanner.schema();
+    let columns: Vec<String> = arrow_schema_to_pg_columns(schema.as_ref())
+        .into_iter()
+        .map(|(name, pg_type, nullable)| {
+            let type_name = match types::pg_type_name(pg_type) {
+                // Unquoted, `char` means `character(1)` rather than the one-byte type
+                "char" => "\"char\"",
+                "unknown" => "text",
+                other => other,
+            };
+            let constraint = if nullable { "" } else { " NOT NULL" };
+            format!(
+                "    {} {}{}",
+                pgrx::spi::quote_identifier(&name),
+                type_name,
+                constraint
+            )
+        })
+        .collect();
+
+    format!(
+        "CREATE TABLE {} (\n{}\n);",
+        pgrx::spi::quote_identifier(table_name),
+        columns.join(",\n")
+    )
 }
 
-/// Scan Lance table and return data in JSONB format
+/// Open a Lance table ahead of time so the first real query is fast
+///
+/// Primes the per-backend dataset handle cache and loads fragment metadata.
 #[pg_extern]
-pub fn lance_scan_jsonb(
+pub fn lance_warmup(table_path: &str) {
+    let scanner = open_scanner(table_path, None);
+
+    scanner
+        .warmup()
+        .unwrap_or_else(|_| pgrx::error!("Failed to warm up Lance table at: {}", table_path));
+}
+
+/// Get the table-level metadata recorded in a Lance table's schema
+#[pg_extern]
+pub fn lance_table_metadata(
     table_path: &str,
-    limit: default!(Option<i64>, "NULL"),
-) -> TableIterator<'static, (name!(row_data, pgrx::JsonB),)> {
-    let scanner = LanceScanner::new(table_path)
-        .unwrap_or_else(|_| pgrx::error!("Failed to open Lance table at: {}", table_path));
+) -> TableIterator<'static, (name!(key, String), name!(value, String))> {
+    let scanner = open_scanner(table_path, None);
 
-    let scan_iter = scanner
-        .scan_with_filter(None, limit)
-        .unwrap_or_else(|_| pgrx::error!("Failed to create scan iterator"));
+    let mut rows: Vec<(String, String)> = scanner
+        .schema()
+        .metadata()
+        .iter()
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    rows.sort();
 
-    let schema = scanner.schema();
+    TableIterator::new(rows)
+}
 
-    let mut results = Vec::new();
-    let mut rows_outputted_count = 0i64;
+/// Get the dataset configuration stored in a Lance table's manifest
+///
+/// Unlike `lance_table_metadata`, which reads the schema's metadata, this is
+/// the manifest's config map, where Lance keeps dataset-wide settings.
+#[pg_extern]
+pub fn lance_config(
+    table_path: &str,
+) -> TableIterator<'static, (name!(key, String), name!(value, String))> {
+    let scanner = open_scanner(table_path, None);
 
-    'batch_loop: for record_batch in scan_iter.batches {
-        for row_idx_in_batch in 0..record_batch.num_rows() {
-            if let Some(l_pg) = limit {
-                if rows_outputted_count >= l_pg {
-                    break 'batch_loop;
-                }
-            }
+    TableIterator::new(scanner.config())
+}
 
-            let mut json_map = Map::new();
-            for (col_idx, field) in schema.fields().iter().enumerate() {
-                let column_array = record_batch.column(col_idx);
-                let value = arrow_value_to_serde_json(column_array.as_ref(), row_idx_in_batch);
-                json_map.insert(field.name().clone(), value);
-            }
-            results.push((pgrx::JsonB(Value::Object(json_map)),));
-            rows_outputted_count += 1;
-        }
-    }
+/// Describe each index of a Lance table and the parameters it was built with
+///
+/// Returns a JSONB array with one object per index. Parameters that do not
+/// apply to an index's type are left out rather than reported as NULL.
+#[pg_extern]
+pub fn lance_index_details(table_path: &str) -> pgrx::JsonB {
+    let scanner = open_scanner(table_path, None);
 
-    TableIterator::new(results)
+    let indexes = scanner.indexes().unwrap_or_else(|code| {
+        ereport!(
+            ERROR,
+            code,
+            format!("Failed to read indexes of Lance table at: {}", table_path)
+        )
+    });
+    let details = indexes
+        .into_iter()
+        .map(|(name, columns, statistics)| index_details(name, columns, &statistics))
+        .collect();
+    pgrx::JsonB(Value::Array(details))
 }
 
-#[cfg(any(test, feature = "pg_test"))]
-#[pg_schema]
-mod tests {
-    use arrow::array::{BooleanArray, Float32Array, Int32Array, StringArray};
-    use arrow::datatypes::{DataType, Field, Schema};
-    use arrow::record_batch::RecordBatch;
-    use lance::Dataset;
-    use pgrx::prelude::*;
-    use std::sync::Arc;
-    use tempfile::TempDir;
+/// Pick the user-facing parameters out of Lance's index statistics
+///
+/// Vector indexes describe each delta under `indices`, with the quantizer's
+/// settings in `sub_index`; the first delta carries the build parameters.
+fn index_details(name: String, columns: Vec<String>, statistics: &Value) -> Value {
+    let delta = statistics
+        .get("indices")
+        .and_then(|indices| indices.get(0))
+        .unwrap_or(statistics);
+    let sub_index = delta.get("sub_index");
+    let find = |key: &str| {
+        [Some(delta), sub_index, Some(statistics)]
+            .into_iter()
+            .flatten()
+            .find_map(|stats| stats.get(key).filter(|value| !value.is_null()))
+            .cloned()
+    };
 
-    /// Test data generator for Lance tables using synchronous blocking operations
-    struct LanceTestDataGenerator {
-        temp_dir: TempDir,
+    let mut details = Map::new();
+    details.insert("name".to_string(), json!(name));
+    if let Some(index_type) = find("index_type").and_then(|t| t.as_str().map(str::to_uppercase)) {
+        details.insert("type".to_string(), json!(index_type));
     }
-
-    impl LanceTestDataGenerator {
-        fn new() -> Result<Self, Box<dyn std::error::Error>> {
-            let temp_dir = TempDir::new()?;
-            Ok(Self { temp_dir })
+    details.insert("columns".to_string(), json!(columns));
+    for (key, source) in [
+        ("num_partitions", "num_partitions"),
+        ("num_sub_vectors", "num_sub_vectors"),
+        ("metric", "metric_type"),
+    ] {
+        if let Some(value) = find(source) {
+            details.insert(key.to_string(), value);
         }
+    }
+    Value::Object(details)
+}
 
-        fn get_base_path(&self) -> &std::path::Path {
-            self.temp_dir.path()
-        }
+/// Get Lance table schema in Arrow's own JSON schema serialization
+#[pg_extern]
+pub fn lance_arrow_schema(table_path: &str) -> String {
+    let scanner = open_scanner(table_path, None);
 
-        /// Create a simple table with basic data types
-        fn create_simple_table(&self) -> Result<std::path::PathBuf, Box<dyn std::error::Error>> {
-            let table_path = self.get_base_path().join("simple_table");
+    let schema = scanner.schema();
+    serde_json::to_string(schema.as_ref())
+        .unwrap_or_else(|e| pgrx::error!("Failed to serialize Arrow schema: {}", e))
+}
 
-            // Create sample data with various basic types
-            let id_array = Int32Array::from(vec![1, 2, 3, 4, 5]);
-            let name_array = StringArray::from(vec!["Alice", "Bob", "Charlie", "David", "Eve"]);
-            let age_array = Int32Array::from(vec![25, 30, 35, 40, 45]);
+/// Get the exact Arrow data type of each column
+///
+/// Unlike `lance_table_info`, nested types keep their item types and sizes,
+/// so a vector column reports its dimension.
+#[pg_extern]
+pub fn lance_arrow_types(
+    table_path: &str,
+) -> TableIterator<'static, (name!(column_name, String), name!(arrow_type, String))> {
+    let scanner = open_scanner(table_path, None);
+
+    let rows: Vec<_> = scanner
+        .schema()
+        .fields()
+        .iter()
+        .map(|field| {
+            (
+                field.name().clone(),
+                types::arrow_type_name(field.data_type()),
+            )
+        })
+        .collect();
+
+    TableIterator::new(rows)
+}
+
+/// Get Lance table statistics
+///
+/// With `pglance.stats_cache_ttl_seconds` set, statistics are served from a
+/// per-backend cache until they are older than the TTL.
+#[pg_extern]
+pub fn lance_table_stats(
+    table_path: &str,
+) -> TableIterator<
+    'static,
+    (
+        name!(version, i64),
+        name!(num_rows, i64),
+        name!(num_columns, i32),
+    ),
+> {
+    let ttl = std::time::Duration::from_secs(guc::STATS_CACHE_TTL_SECONDS.get() as u64);
+    let stats = match scanner::cached_stats(table_path, ttl) {
+        Some(stats) => stats,
+        None => {
+            let scanner = open_scanner(table_path, None);
+            let stats = scanner
+                .get_stats()
+                .unwrap_or_else(|_| pgrx::error!("Failed to get table statistics"));
+            scanner::cache_stats(table_path, stats.clone());
+            stats
+        }
+    };
+
+    let version = version_to_bigint(stats.version);
+    let row = (version, stats.num_rows as i64, stats.num_columns() as i32);
+
+    TableIterator::new(std::iter::once(row))
+}
+
+/// Get creation and last modification times of a Lance table
+///
+/// Derived from the timestamps of the first and latest version manifests.
+#[pg_extern]
+pub fn lance_table_times(
+    table_path: &str,
+) -> TableIterator<
+    'static,
+    (
+        name!(created_at, pgrx::datum::TimestampWithTimeZone),
+        name!(last_modified, pgrx::datum::TimestampWithTimeZone),
+    ),
+> {
+    let scanner = open_scanner(table_path, None);
+
+    let versions = scanner
+        .versions()
+        .unwrap_or_else(|_| pgrx::error!("Failed to list table versions"));
+
+    let (first, latest) = match (versions.first(), versions.last()) {
+        (Some(first), Some(latest)) => (first.timestamp, latest.timestamp),
+        _ => pgrx::error!("Lance table at {} has no versions", table_path),
+    };
+
+    let to_pg = |ts| {
+        types::chrono_to_pg_timestamptz(ts).unwrap_or_else(|e| ereport!(ERROR, e.code, e.message))
+    };
+
+    TableIterator::once((to_pg(first), to_pg(latest)))
+}
+
+/// List the versions of a Lance table with the operation that wrote each
+///
+/// Operations are read from each version's transaction file; versions without
+/// one report `unknown`.
+#[pg_extern]
+pub fn lance_versions(
+    table_path: &str,
+) -> TableIterator<
+    'static,
+    (
+        name!(version, i64),
+        name!(created_at, pgrx::datum::TimestampWithTimeZone),
+        name!(operation, String),
+    ),
+> {
+    let scanner = open_scanner(table_path, None);
+
+    let versions = scanner
+        .versions()
+        .unwrap_or_else(|_| pgrx::error!("Failed to list table versions"));
+
+    let rows: Vec<_> = versions
+        .into_iter()
+        .map(|version| {
+            let created_at = types::chrono_to_pg_timestamptz(version.timestamp)
+                .unwrap_or_else(|e| ereport!(ERROR, e.code, e.message));
+            let number = version_to_bigint(version.version);
+            let operation = scanner
+                .version_operation(version.version)
+                .unwrap_or_else(|| "unknown".to_string());
+            (number, created_at, operation)
+        })
+        .collect();
+
+    TableIterator::new(rows)
+}
+
+/// Summarize schema and row count changes between two versions of a Lance table
+///
+/// Columns are matched by Lance field id, so a renamed column is reported as
+/// renamed rather than as a removal plus an addition.
+#[pg_extern]
+pub fn lance_version_diff(table_path: &str, from_version: i64, to_version: i64) -> pgrx::JsonB {
+    let open_version = |version: i64| {
+        if version < 1 {
+            ereport!(
+                ERROR,
+                PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+                format!("Version must be at least 1, got {}", version)
+            );
+        }
+        let options = scanner::OpenOptions {
+            consistency: scanner::ReadConsistency::Version(version as u64),
+            ..Default::default()
+        };
+        let scanner = LanceScanner::open(table_path, &options).unwrap_or_else(|code| {
+            ereport!(
+                ERROR,
+                code,
+                format!(
+                    "Failed to open version {} of Lance table at: {}",
+                    version, table_path
+                )
+            )
+        });
+        let stats = scanner
+            .get_stats()
+            .unwrap_or_else(|_| pgrx::error!("Failed to get table statistics"));
+        (scanner.field_ids(), stats.num_rows as i64)
+    };
+
+    let (from_fields, from_rows) = open_version(from_version);
+    let (to_fields, to_rows) = open_version(to_version);
+
+    let from_names: std::collections::HashMap<i32, &String> =
+        from_fields.iter().map(|(id, name)| (*id, name)).collect();
+    let to_ids: std::collections::HashSet<i32> = to_fields.iter().map(|(id, _)| *id).collect();
+
+    let mut added_columns = Vec::new();
+    let mut renamed_columns = Vec::new();
+    for (id, name) in &to_fields {
+        match from_names.get(id) {
+            None => added_columns.push(json!(name)),
+            Some(old_name) if *old_name != name => {
+                renamed_columns.push(json!({ "from": old_name, "to": name }))
+            }
+            Some(_) => {}
+        }
+    }
+    let removed_columns: Vec<Value> = from_fields
+        .iter()
+        .filter(|(id, _)| !to_ids.contains(id))
+        .map(|(_, name)| json!(name))
+        .collect();
+
+    pgrx::JsonB(json!({
+        "from_version": from_version,
+        "to_version": to_version,
+        "added_columns": added_columns,
+        "removed_columns": removed_columns,
+        "renamed_columns": renamed_columns,
+        "from_num_rows": from_rows,
+        "to_num_rows": to_rows,
+        "row_count_delta": to_rows - from_rows,
+    }))
+}
+
+/// Rows added to or deleted from a Lance table since a version
+///
+/// Rows of fragments written after `since_version` are reported as `added`.
+/// Rows deleted from fragments that existed at `since_version`, or whose
+/// fragment was removed altogether, are reported as `deleted` with their
+/// values at that version. When fragments were both removed and written,
+/// e.g. by compaction, moved rows cannot be told apart from new ones, so the
+/// call fails with `ERRCODE_OBJECT_NOT_IN_PREREQUISITE_STATE` rather than
+/// reporting every moved row as `added`.
+#[pg_extern]
+pub fn lance_changes_since(
+    table_path: &str,
+    since_version: i64,
+    limit: default!(Option<i64>, "NULL"),
+) -> TableIterator<'static, (name!(change_type, String), name!(row_data, pgrx::JsonB))> {
+    let latest = open_scanner(table_path, None);
+    if since_version < 1 || since_version as u64 > latest.version() {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            format!(
+                "since_version must be between 1 and the latest version {}, got {}",
+                latest.version(),
+                since_version
+            )
+        );
+    }
+    if latest.schema().field_with_name(ROW_ADDRESS_COLUMN).is_ok() {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_DUPLICATE_COLUMN,
+            format!(
+                "Lance table has a column named \"{}\", which would hide the row address",
+                ROW_ADDRESS_COLUMN
+            )
+        );
+    }
+    let options = scanner::OpenOptions {
+        consistency: scanner::ReadConsistency::Version(since_version as u64),
+        ..Default::default()
+    };
+    let since = LanceScanner::open(table_path, &options).unwrap_or_else(|code| {
+        ereport!(
+            ERROR,
+            code,
+            format!(
+                "Failed to open version {} of Lance table at: {}",
+                since_version, table_path
+            )
+        )
+    });
+
+    let deletion_marker = |fragment: &lance_table::format::Fragment| {
+        fragment
+            .deletion_file
+            .as_ref()
+            .map(|deletion_file| (deletion_file.read_version, deletion_file.id))
+    };
+    let since_fragments: std::collections::HashMap<u64, _> = since
+        .fragments()
+        .iter()
+        .map(|fragment| (fragment.id, deletion_marker(fragment)))
+        .collect();
+    let mut added_ids = Vec::new();
+    let mut changed_ids = Vec::new();
+    for fragment in latest.fragments() {
+        match since_fragments.get(&fragment.id) {
+            None => added_ids.push(fragment.id),
+            Some(marker) if *marker != deletion_marker(&fragment) => changed_ids.push(fragment.id),
+            Some(_) => {}
+        }
+    }
+    let latest_ids: std::collections::HashSet<u64> = latest
+        .fragments()
+        .iter()
+        .map(|fragment| fragment.id)
+        .collect();
+    let removed_ids: Vec<u64> = since_fragments
+        .keys()
+        .copied()
+        .filter(|id| !latest_ids.contains(id))
+        .collect();
+    if !removed_ids.is_empty() && !added_ids.is_empty() {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_OBJECT_NOT_IN_PREREQUISITE_STATE,
+            format!(
+                "Lance table at {} was compacted or rewritten since version {}, so its changes cannot be listed; resync from a full scan",
+                table_path, since_version
+            )
+        );
+    }
+
+    let read = |scanner: &LanceScanner, ids: &[u64]| {
+        scanner
+            .scan_fragments(ids)
+            .unwrap_or_else(|e| raise_scan_error(e))
+            .batches
+    };
+    let remaining: std::collections::HashSet<u64> = read(&latest, &changed_ids)
+        .iter()
+        .flat_map(|batch| row_addresses(batch).values().to_vec())
+        .collect();
+    // Rows of removed fragments are never among the remaining addresses
+    changed_ids.extend(removed_ids);
+
+    let mut changes = Vec::new();
+    let changed_rows = read(&latest, &added_ids)
+        .into_iter()
+        .map(|batch| ("added", batch))
+        .chain(
+            read(&since, &changed_ids)
+                .into_iter()
+                .map(|batch| ("deleted", batch)),
+        );
+    'batches: for (change_type, batch) in changed_rows {
+        let addresses = row_addresses(&batch);
+        let schema = batch.schema();
+        let columns: Vec<&str> = schema.fields()[..batch.num_columns() - 1]
+            .iter()
+            .map(|f| f.name().as_str())
+            .collect();
+        let encoder = BatchJsonEncoder::new(&batch).only(&columns);
+        for row_idx in 0..batch.num_rows() {
+            if limit.is_some_and(|limit| changes.len() as i64 >= limit) {
+                break 'batches;
+            }
+            if change_type == "deleted" && remaining.contains(&addresses.value(row_idx)) {
+                continue;
+            }
+            let row = encoder
+                .encode_row(row_idx)
+                .unwrap_or_else(|e| ereport!(ERROR, e.code, e.message));
+            changes.push((change_type.to_string(), pgrx::JsonB(row)));
+        }
+    }
+
+    TableIterator::new(changes)
+}
+
+/// The trailing `_rowaddr` column of a batch scanned with row addresses
+fn row_addresses(batch: &RecordBatch) -> UInt64Array {
+    downcast_array::<UInt64Array>(
+        batch.column(batch.num_columns() - 1).as_ref(),
+        "UInt64Array",
+    )
+    .unwrap_or_else(|e| ereport!(ERROR, e.code, e.message))
+    .clone()
+}
+
+/// List the fragments of a Lance table
+///
+/// Reports each fragment's row count, data files and deletion file, so
+/// maintenance scripts can find fragments with deleted rows to compact.
+#[pg_extern]
+pub fn lance_fragments(
+    table_path: &str,
+) -> TableIterator<
+    'static,
+    (
+        name!(fragment_id, i64),
+        name!(physical_rows, Option<i64>),
+        name!(num_data_files, i32),
+        name!(num_deleted_rows, Option<i64>),
+        name!(has_deletions, bool),
+        name!(deletion_file, Option<String>),
+    ),
+> {
+    let scanner = open_scanner(table_path, None);
+
+    let rows: Vec<_> = scanner
+        .fragments()
+        .into_iter()
+        .map(|fragment| {
+            let deletion_file = fragment.deletion_file.as_ref().map(|deletion_file| {
+                lance_table::io::deletion::deletion_file_path(
+                    &object_store::path::Path::default(),
+                    fragment.id,
+                    deletion_file,
+                )
+                .to_string()
+            });
+            (
+                fragment.id as i64,
+                fragment.physical_rows.map(|rows| rows as i64),
+                fragment.files.len() as i32,
+                fragment
+                    .deletion_file
+                    .as_ref()
+                    .and_then(|f| f.num_deleted_rows)
+                    .map(|rows| rows as i64),
+                fragment.deletion_file.is_some(),
+                deletion_file,
+            )
+        })
+        .collect();
+
+    TableIterator::new(rows)
+}
+
+/// Summarize how rows are spread over the fragments of a Lance table
+///
+/// Many fragments with few rows each slow scans down and signal that the
+/// table needs compaction. Rows are counted without deleted rows; fragments
+/// written by old Lance versions that do not record their row count are
+/// counted as fragments but left out of the row statistics.
+#[pg_extern]
+pub fn lance_fragment_stats(
+    table_path: &str,
+) -> TableIterator<
+    'static,
+    (
+        name!(num_fragments, i32),
+        name!(avg_rows_per_fragment, Option<f64>),
+        name!(min_rows, Option<i64>),
+        name!(max_rows, Option<i64>),
+    ),
+> {
+    let scanner = open_scanner(table_path, None);
+
+    let fragments = scanner.fragments();
+    let rows: Vec<i64> = fragments
+        .iter()
+        .filter_map(|fragment| fragment.num_rows())
+        .map(|rows| rows as i64)
+        .collect();
+    let avg_rows = (!rows.is_empty()).then(|| rows.iter().sum::<i64>() as f64 / rows.len() as f64);
+
+    TableIterator::once((
+        fragments.len() as i32,
+        avg_rows,
+        rows.iter().min().copied(),
+        rows.iter().max().copied(),
+    ))
+}
+
+/// List the data files of each fragment with their storage format
+///
+/// A fragment may reference several files, e.g. after columns are added, and
+/// files written by other tools need not be Lance files. The format is taken
+/// from the file extension; Lance files also report their file format version.
+/// `uri` is the file's full location, which for datasets spanning several
+/// buckets need not be under the table's own prefix.
+#[pg_extern]
+pub fn lance_data_files(
+    table_path: &str,
+) -> TableIterator<
+    'static,
+    (
+        name!(fragment_id, i64),
+        name!(path, String),
+        name!(format, String),
+        name!(format_version, Option<String>),
+        name!(num_fields, i32),
+        name!(uri, String),
+    ),
+> {
+    let scanner = open_scanner(table_path, None);
+
+    let rows: Vec<_> = scanner
+        .fragments()
+        .into_iter()
+        .flat_map(|fragment| {
+            let scanner = &scanner;
+            fragment.files.into_iter().map(move |file| {
+                let format = data_file_format(&file.path);
+                let format_version = (format == "lance")
+                    .then(|| format!("{}.{}", file.file_major_version, file.file_minor_version));
+                let uri = scanner.data_file_uri(&file.path);
+                (
+                    fragment.id as i64,
+                    file.path,
+                    format,
+                    format_version,
+                    file.fields.len() as i32,
+                    uri,
+                )
+            })
+        })
+        .collect();
+
+    TableIterator::new(rows)
+}
+
+/// Storage format of a data file, from its extension
+fn data_file_format(path: &str) -> String {
+    std::path::Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Scan Lance table and return data in JSONB format
+///
+/// `select` optionally takes a comma-separated list of Lance SQL expressions,
+/// e.g. `age * 2 AS double_age, name`; the JSONB keys use the aliases.
+/// `require_non_null` adds a `column IS NOT NULL` predicate, combined with
+/// `filter` using `AND`. `include_deleted` also returns rows that are deleted
+/// but still stored, adding a `_deleted` key to every row. `max_binary_bytes`
+/// encodes at most that many bytes of each binary value. `timeout_ms` bounds
+/// the time spent reading the table. `decimal_as` is `numeric` for decimals as
+/// JSON numbers, `scaled_int` for exact `{unscaled, scale}` objects or `text`
+/// for exact strings.
+/// `mask_columns` names output columns whose values are replaced by NULL, so
+/// a scan can be exposed without revealing them; `filter` and
+/// `require_non_null` may not read the columns behind them.
+#[pg_extern]
+pub fn lance_scan_jsonb(
+    table_path: &str,
+    limit: default!(Option<i64>, "NULL"),
+    select: default!(Option<&str>, "NULL"),
+    storage_options: default!(Option<pgrx::JsonB>, "NULL"),
+    filter: default!(Option<&str>, "NULL"),
+    require_non_null: default!(Option<&str>, "NULL"),
+    include_deleted: default!(bool, false),
+    max_binary_bytes: default!(Option<i32>, "NULL"),
+    timeout_ms: default!(Option<i32>, "NULL"),
+    decimal_as: default!(&str, "'numeric'"),
+    mask_columns: default!(Option<Vec<String>>, "NULL"),
+) -> TableIterator<'static, (name!(row_data, pgrx::JsonB),)> {
+    let decimal_format = types::DecimalFormat::parse(decimal_as).unwrap_or_else(|code| {
+        ereport!(
+            ERROR,
+            code,
+            format!(
+                "Unknown decimal_as \"{}\", expected numeric, scaled_int or text",
+                decimal_as
+            )
+        )
+    });
+    let timeout = timeout_arg(timeout_ms);
+    let max_binary_bytes = max_binary_bytes.map(|max_bytes| {
+        usize::try_from(max_bytes).unwrap_or_else(|_| {
+            ereport!(
+                ERROR,
+                PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+                format!("max_binary_bytes must not be negative, got {}", max_bytes)
+            )
+        })
+    });
+    let scanner = open_scanner(table_path, storage_options);
+
+    if include_deleted && scanner.schema().field_with_name(DELETED_COLUMN).is_ok() {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_DUPLICATE_COLUMN,
+            format!(
+                "Lance table has a column named \"{}\", which would hide the deletion marker",
+                DELETED_COLUMN
+            )
+        );
+    }
+
+    if let Some(column) = require_non_null {
+        if scanner.schema().field_with_name(column).is_err() {
+            ereport!(
+                ERROR,
+                PgSqlErrorCode::ERRCODE_UNDEFINED_COLUMN,
+                format!("Column \"{}\" does not exist in Lance table", column)
+            );
+        }
+    }
+    let effective_filter = scanner::and_filters(
+        filter
+            .map(str::to_string)
+            .into_iter()
+            .chain(require_non_null.map(scanner::not_null_predicate)),
+    );
+
+    let projection = select.map(|select| {
+        scanner::parse_select_list(select).unwrap_or_else(|code| {
+            ereport!(ERROR, code, format!("Invalid select list: {}", select))
+        })
+    });
+
+    let mask_columns = mask_columns.unwrap_or_default();
+    for column in &mask_columns {
+        let in_output = match &projection {
+            Some(projection) => projection.iter().any(|(alias, _)| alias == column),
+            None => scanner.schema().field_with_name(column).is_ok(),
+        };
+        if !in_output {
+            ereport!(
+                ERROR,
+                PgSqlErrorCode::ERRCODE_UNDEFINED_COLUMN,
+                format!("Masked column \"{}\" is not in the scan output", column)
+            );
+        }
+    }
+
+    // Masking is only meaningful if rows cannot be selected by the hidden
+    // values, so the filters must not read the columns behind masked outputs
+    if !mask_columns.is_empty() {
+        let schema = scanner.schema();
+        let mut masked_sources = std::collections::HashSet::new();
+        for column in &mask_columns {
+            match &projection {
+                Some(projection) => {
+                    for (_, expr) in projection.iter().filter(|(alias, _)| alias == column) {
+                        masked_sources.extend(
+                            scanner::referenced_columns(expr, schema.clone()).unwrap_or_else(
+                                |code| {
+                                    ereport!(ERROR, code, format!("Invalid select list: {}", expr))
+                                },
+                            ),
+                        );
+                    }
+                }
+                None => {
+                    masked_sources.insert(column.clone());
+                }
+            }
+        }
+        if let Some(filter) = &effective_filter {
+            let read = scanner::referenced_columns(filter, schema.clone()).unwrap_or_else(|code| {
+                ereport!(ERROR, code, format!("Invalid filter: {}", filter))
+            });
+            let mut leaked: Vec<&String> = read.intersection(&masked_sources).collect();
+            leaked.sort();
+            if let Some(column) = leaked.first() {
+                ereport!(
+                    ERROR,
+                    PgSqlErrorCode::ERRCODE_INSUFFICIENT_PRIVILEGE,
+                    format!(
+                        "Filter references column \"{}\", which is masked in the scan output",
+                        column
+                    )
+                );
+            }
+        }
+    }
+
+    let options = ScanOptions {
+        filter: effective_filter,
+        projection,
+        limit,
+        include_deleted,
+        timeout,
+        ..Default::default()
+    };
+    let scan_iter = scanner.scan(&options).unwrap_or_else(|e| match e {
+        scanner::ScanError::PredicateParse(_) => ereport!(
+            ERROR,
+            e.code(),
+            format!("Invalid filter: {}: {}", filter.unwrap_or_default(), e)
+        ),
+        scanner::ScanError::ProjectionInvalid(_) => ereport!(
+            ERROR,
+            e.code(),
+            format!(
+                "Invalid select expression: {}: {}",
+                select.unwrap_or_default(),
+                e
+            )
+        ),
+        e => raise_scan_error(e),
+    });
+
+    TableIterator::new(scan_to_jsonb_rows_with(scan_iter, limit, |encoder| {
+        let encoder = encoder
+            .with_masked(&mask_columns)
+            .with_decimal_format(decimal_format);
+        match max_binary_bytes {
+            Some(max_bytes) => encoder.with_max_binary_bytes(max_bytes),
+            None => encoder,
+        }
+    }))
+}
+
+/// Run a read-only SQL query over a Lance table with DataFusion
+///
+/// The table is registered as `t`, so queries can use the full DataFusion
+/// SQL dialect, including aggregates and expressions Lance filters cannot
+/// express. Only queries are allowed; DDL and DML are rejected. `limit` is
+/// applied inside the query. Sorts, joins and results are bounded by
+/// `pglance.scan_mem_budget_bytes`, or `work_mem` when it is unset, and the
+/// query can be cancelled or bounded with `timeout_ms`.
+#[pg_extern]
+pub fn lance_scan_sql(
+    table_path: &str,
+    sql: &str,
+    limit: default!(Option<i64>, "NULL"),
+    timeout_ms: default!(Option<i32>, "NULL"),
+) -> TableIterator<'static, (name!(row_data, pgrx::JsonB),)> {
+    if let Some(limit) = limit {
+        check_row_count_arg(limit);
+    }
+    let timeout = timeout_arg(timeout_ms);
+    let scanner = open_scanner(table_path, None);
+
+    let scan_iter = scanner
+        .sql("t", sql, limit, timeout, || pgrx::check_for_interrupts!())
+        .unwrap_or_else(|e| match e {
+            scanner::ScanError::QueryInvalid(_) => ereport!(
+                ERROR,
+                e.code(),
+                format!("Invalid SQL query: {}: {}", sql, e)
+            ),
+            e => raise_scan_error(e),
+        });
+
+    TableIterator::new(scan_to_jsonb_rows(scan_iter, limit))
+}
+
+/// Describe what a scan would pass to Lance without executing it
+///
+/// Returns the normalized filter, the resolved projection, the effective
+/// limit, whether the plan uses an index, and the physical plan itself.
+#[pg_extern]
+pub fn lance_scan_debug(
+    table_path: &str,
+    filter: default!(Option<&str>, "NULL"),
+    columns: default!(Option<Vec<String>>, "NULL"),
+    limit: default!(Option<i64>, "NULL"),
+) -> pgrx::JsonB {
+    let scanner = open_scanner(table_path, None);
+
+    let schema = scanner.schema();
+    let resolved_columns: Vec<String> = match &columns {
+        Some(columns) => {
+            for column in columns {
+                if schema.field_with_name(column).is_err() {
+                    ereport!(
+                        ERROR,
+                        PgSqlErrorCode::ERRCODE_UNDEFINED_COLUMN,
+                        format!("Column \"{}\" does not exist in Lance table", column)
+                    );
+                }
+            }
+            columns.clone()
+        }
+        None => schema.fields().iter().map(|f| f.name().clone()).collect(),
+    };
+
+    let normalized_filter = filter
+        .map(str::trim)
+        .filter(|f| !f.is_empty())
+        .map(str::to_string);
+
+    let plan = scanner
+        .explain_scan(normalized_filter.clone(), columns, limit)
+        .unwrap_or_else(|e| {
+            ereport!(
+                ERROR,
+                e.code(),
+                format!(
+                    "Failed to plan scan with filter: {}: {}",
+                    filter.unwrap_or_default(),
+                    e
+                )
+            )
+        });
+
+    pgrx::JsonB(json!({
+        "filter": normalized_filter,
+        "columns": resolved_columns,
+        "limit": limit,
+        "uses_index": plan.uses_index,
+        "plan": plan.text,
+    }))
+}
+
+/// Estimate the cost of a scan without running it
+///
+/// Row counts come from fragment metadata and byte counts from the sizes of
+/// the data files holding the needed columns, so nothing is read but metadata.
+/// Lance does not expose statistics for pruning by filter, so with a filter
+/// both numbers are upper bounds: every live row is counted, and every data
+/// file, since any column may appear in the filter.
+#[pg_extern]
+pub fn lance_scan_estimate(
+    table_path: &str,
+    filter: default!(Option<&str>, "NULL"),
+    columns: default!(Option<Vec<String>>, "NULL"),
+) -> pgrx::JsonB {
+    let scanner = open_scanner(table_path, None);
+
+    if let Some(columns) = &columns {
+        let schema = scanner.schema();
+        for column in columns {
+            if schema.field_with_name(column).is_err() {
+                ereport!(
+                    ERROR,
+                    PgSqlErrorCode::ERRCODE_UNDEFINED_COLUMN,
+                    format!("Column \"{}\" does not exist in Lance table", column)
+                );
+            }
+        }
+    }
+    let filter = filter
+        .map(str::trim)
+        .filter(|f| !f.is_empty())
+        .map(str::to_string);
+
+    let plan = scanner
+        .explain_scan(filter.clone(), columns.clone(), None)
+        .unwrap_or_else(|e| raise_scan_error(e));
+
+    let stats = scanner
+        .get_stats()
+        .unwrap_or_else(|code| ereport!(ERROR, code, "Failed to get Lance table stats"));
+
+    let needed_fields: Option<Vec<i32>> = match (&filter, &columns) {
+        (None, Some(columns)) => Some(
+            scanner
+                .field_ids()
+                .into_iter()
+                .filter(|(_, name)| columns.contains(name))
+                .map(|(id, _)| id)
+                .collect(),
+        ),
+        _ => None,
+    };
+    let estimated_bytes: u64 = scanner
+        .data_file_sizes()
+        .unwrap_or_else(|code| ereport!(ERROR, code, "Failed to read Lance data file sizes"))
+        .into_iter()
+        .filter(|(fields, _)| match &needed_fields {
+            Some(needed) => fields.iter().any(|id| needed.contains(id)),
+            None => true,
+        })
+        .map(|(_, size)| size)
+        .sum();
+
+    pgrx::JsonB(json!({
+        "estimated_rows": stats.num_rows,
+        "estimated_bytes": estimated_bytes,
+        "uses_index": plan.uses_index,
+        "upper_bound": filter.is_some(),
+    }))
+}
+
+/// Rows sampled by `lance_logical_size` to size variable-width columns
+const LOGICAL_SIZE_SAMPLE_ROWS: i64 = 1024;
+
+/// Estimate the uncompressed in-memory size of a Lance table in bytes
+///
+/// Multiplies the row count by an estimated row width: the byte width of
+/// fixed-width columns, plus the average in-memory size of the other columns
+/// over the first rows of the table. Validity bitmaps are not counted.
+#[pg_extern]
+pub fn lance_logical_size(table_path: &str) -> i64 {
+    let scanner = open_scanner(table_path, None);
+    let stats = scanner
+        .get_stats()
+        .unwrap_or_else(|code| ereport!(ERROR, code, "Failed to get Lance table stats"));
+    let schema = scanner.schema();
+
+    let mut row_width = 0.0f64;
+    let mut sampled_columns = Vec::new();
+    for (idx, field) in schema.fields().iter().enumerate() {
+        match field.data_type().primitive_width() {
+            Some(width) => row_width += width as f64,
+            None => sampled_columns.push(idx),
+        }
+    }
+
+    if !sampled_columns.is_empty() && stats.num_rows > 0 {
+        let sample = scanner
+            .scan_with_filter(None, Some(LOGICAL_SIZE_SAMPLE_ROWS))
+            .unwrap_or_else(|e| raise_scan_error(e));
+        let mut sampled_rows = 0usize;
+        let mut sampled_bytes = 0usize;
+        for batch in &sample.batches {
+            sampled_rows += batch.num_rows();
+            for &idx in &sampled_columns {
+                sampled_bytes += batch
+                    .column(idx)
+                    .to_data()
+                    .get_slice_memory_size()
+                    .unwrap_or_else(|_| batch.column(idx).get_buffer_memory_size());
+            }
+        }
+        if sampled_rows > 0 {
+            row_width += sampled_bytes as f64 / sampled_rows as f64;
+        }
+    }
+
+    (stats.num_rows as f64 * row_width).round() as i64
+}
+
+/// Scan Lance table with a parameterized filter and return data in JSONB format
+///
+/// `filter_template` uses `$1`, `$2`, ... placeholders which are replaced by
+/// the corresponding `params` element rendered as a quoted literal.
+/// `timeout_ms` bounds the time spent reading the table.
+#[pg_extern]
+pub fn lance_scan_jsonb_params(
+    table_path: &str,
+    filter_template: &str,
+    params: Vec<pgrx::JsonB>,
+    limit: default!(Option<i64>, "NULL"),
+    timeout_ms: default!(Option<i32>, "NULL"),
+) -> TableIterator<'static, (name!(row_data, pgrx::JsonB),)> {
+    let timeout = timeout_arg(timeout_ms);
+    let scanner = open_scanner(table_path, None);
+
+    let params: Vec<Value> = params.into_iter().map(|p| p.0).collect();
+    let filter = scanner::substitute_params(filter_template, &params).unwrap_or_else(|code| {
+        ereport!(
+            ERROR,
+            code,
+            format!("Invalid filter template or parameters: {}", filter_template)
+        )
+    });
+
+    let options = ScanOptions {
+        filter: Some(filter),
+        limit,
+        timeout,
+        ..Default::default()
+    };
+    let scan_iter = scanner.scan(&options).unwrap_or_else(|e| match e {
+        scanner::ScanError::PredicateParse(_) => ereport!(
+            ERROR,
+            e.code(),
+            format!("Invalid filter: {}: {}", filter_template, e)
+        ),
+        e => raise_scan_error(e),
+    });
+
+    TableIterator::new(scan_to_jsonb_rows(scan_iter, limit))
+}
+
+/// Scan a Lance table and return one row per scanned record batch
+///
+/// Each row carries the batch's position, its row count and its rows as a JSONB
+/// array, so clients can process the table in the chunks Lance produced.
+/// `timeout_ms` bounds the time spent reading the table.
+#[pg_extern]
+pub fn lance_scan_batches(
+    table_path: &str,
+    filter: default!(Option<&str>, "NULL"),
+    limit: default!(Option<i64>, "NULL"),
+    timeout_ms: default!(Option<i32>, "NULL"),
+) -> TableIterator<
+    'static,
+    (
+        name!(batch_index, i32),
+        name!(batch_num_rows, i32),
+        name!(batch_data, pgrx::JsonB),
+    ),
+> {
+    let timeout = timeout_arg(timeout_ms);
+    let scanner = open_scanner(table_path, None);
+
+    let options = ScanOptions {
+        filter: filter.map(str::to_string),
+        limit,
+        timeout,
+        ..Default::default()
+    };
+    let scan_iter = scanner.scan(&options).unwrap_or_else(|e| match e {
+        scanner::ScanError::PredicateParse(_) => ereport!(
+            ERROR,
+            e.code(),
+            format!("Invalid filter: {}: {}", filter.unwrap_or_default(), e)
+        ),
+        e => raise_scan_error(e),
+    });
+
+    TableIterator::new(batches_to_jsonb_batches(scan_iter.batches, limit))
+}
+
+/// Scan Lance table and return each row as an array of text values
+///
+/// Values follow column order and are rendered as in `lance_scan_jsonb`, with
+/// strings unquoted and complex values as JSON text. NULLs become array NULLs.
+/// `timeout_ms` bounds the time spent reading the table.
+#[pg_extern]
+pub fn lance_scan_text(
+    table_path: &str,
+    limit: default!(Option<i64>, "NULL"),
+    timeout_ms: default!(Option<i32>, "NULL"),
+) -> TableIterator<'static, (name!(values, Vec<Option<String>>),)> {
+    let timeout = timeout_arg(timeout_ms);
+    let scanner = open_scanner(table_path, None);
+
+    let options = ScanOptions {
+        limit,
+        timeout,
+        ..Default::default()
+    };
+    let scan_iter = scanner
+        .scan(&options)
+        .unwrap_or_else(|e| raise_scan_error(e));
+
+    let mut results = Vec::new();
+    for record_batch in scan_iter.batches {
+        let columns: Vec<JsonColumn> = record_batch.columns().iter().map(JsonColumn::new).collect();
+        for row_idx in 0..record_batch.num_rows() {
+            let values = columns
+                .iter()
+                .map(|column| {
+                    match column
+                        .value(row_idx)
+                        .unwrap_or_else(|e| ereport!(ERROR, e.code, e.message))
+                    {
+                        Value::Null => None,
+                        Value::String(s) => Some(s),
+                        other => Some(other.to_string()),
+                    }
+                })
+                .collect();
+            results.push((values,));
+        }
+    }
+    if let Some(limit) = limit {
+        results.truncate(limit.max(0) as usize);
+    }
+
+    TableIterator::new(results)
+}
+
+/// Scan Lance table and include each row's address as `_rowaddr`
+///
+/// The address is `fragment_id << 32 | offset_in_fragment`. It locates the row
+/// physically, so it changes when compaction rewrites the fragment.
+/// `timeout_ms` bounds the time spent reading the table.
+#[pg_extern]
+pub fn lance_scan_with_addr(
+    table_path: &str,
+    limit: default!(Option<i64>, "NULL"),
+    timeout_ms: default!(Option<i32>, "NULL"),
+) -> TableIterator<'static, (name!(row_data, pgrx::JsonB),)> {
+    let timeout = timeout_arg(timeout_ms);
+    let scanner = open_scanner(table_path, None);
+
+    if scanner.schema().field_with_name(ROW_ADDRESS_COLUMN).is_ok() {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_DUPLICATE_COLUMN,
+            format!(
+                "Lance table has a column named \"{}\", which would hide the row address",
+                ROW_ADDRESS_COLUMN
+            )
+        );
+    }
+
+    let options = ScanOptions {
+        limit,
+        timeout,
+        ..Default::default()
+    };
+    let scan_iter = scanner
+        .scan_with_row_address(&options)
+        .unwrap_or_else(|e| raise_scan_error(e));
+
+    TableIterator::new(scan_to_jsonb_rows(scan_iter, limit))
+}
+
+/// Append the batches of an Arrow IPC stream to a Lance table
+///
+/// The stream's schema must match the table's column names and types exactly.
+/// Returns the table version created by the append.
+#[pg_extern]
+pub fn lance_append_ipc(table_path: &str, ipc: &[u8]) -> i64 {
+    let scanner = open_scanner(table_path, None);
+
+    let reader = arrow::ipc::reader::StreamReader::try_new(std::io::Cursor::new(ipc), None)
+        .unwrap_or_else(|e| {
+            ereport!(
+                ERROR,
+                PgSqlErrorCode::ERRCODE_INVALID_BINARY_REPRESENTATION,
+                format!("Invalid Arrow IPC stream: {}", e)
+            )
+        });
+    let schema = reader.schema();
+    let batches: Vec<RecordBatch> = reader.collect::<Result<_, _>>().unwrap_or_else(|e| {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_INVALID_BINARY_REPRESENTATION,
+            format!("Invalid Arrow IPC stream: {}", e)
+        )
+    });
+
+    let version = scanner
+        .append(schema.clone(), batches)
+        .unwrap_or_else(|code| match code {
+            PgSqlErrorCode::ERRCODE_DATATYPE_MISMATCH => ereport!(
+                ERROR,
+                code,
+                format!(
+                    "Arrow IPC schema does not match Lance table at {}: expected {:?}, got {:?}",
+                    table_path,
+                    scanner.schema().fields(),
+                    schema.fields()
+                )
+            ),
+            _ => ereport!(
+                ERROR,
+                code,
+                format!("Failed to append to Lance table at: {}", table_path)
+            ),
+        });
+    version_to_bigint(version)
+}
+
+extension_sql!(
+    r#"
+REVOKE EXECUTE ON FUNCTION lance_append_ipc(TEXT, BYTEA) FROM PUBLIC;
+"#,
+    name = "revoke_lance_append_ipc",
+    requires = [lance_append_ipc],
+);
+
+/// Delete a Lance table and all of its versions
+///
+/// This cannot be undone, so `force` must be true to confirm the deletion.
+/// EXECUTE is revoked from PUBLIC; superusers grant it to the roles that may
+/// drop tables.
+#[pg_extern]
+pub fn lance_drop_table(table_path: &str, force: default!(bool, false)) {
+    if !force {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            format!(
+                "Refusing to drop Lance table at {} without force => true",
+                table_path
+            )
+        );
+    }
+
+    let scanner = open_scanner(table_path, None);
+    scanner
+        .remove_table(table_path)
+        .unwrap_or_else(|code| match code {
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE => ereport!(
+                ERROR,
+                code,
+                format!(
+                    "Refusing to drop Lance table at {}, which is the root of its bucket",
+                    table_path
+                )
+            ),
+            _ => ereport!(
+                ERROR,
+                code,
+                format!("Failed to drop Lance table at: {}", table_path)
+            ),
+        });
+}
+
+// Dropping deletes arbitrary paths the server can reach, so only roles the
+// administrator grants it to may call it
+extension_sql!(
+    r#"
+REVOKE EXECUTE ON FUNCTION lance_drop_table(TEXT, BOOLEAN) FROM PUBLIC;
+"#,
+    name = "revoke_lance_drop_table",
+    requires = [lance_drop_table],
+);
+
+/// Copy a Lance table, with all of its versions, to a new path
+///
+/// The copy is independent of the source: later writes to either table do not
+/// show in the other. Returns the version of the copy, which is the source's
+/// latest version. Shallow copies, sharing the source's data files, need
+/// manifests that reference files outside the table and are not supported by
+/// this Lance format, so `deep` must be true. EXECUTE is revoked from
+/// PUBLIC, as for `lance_drop_table`.
+#[pg_extern]
+pub fn lance_clone(source_path: &str, dest_path: &str, deep: default!(bool, true)) -> i64 {
+    if !deep {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_FEATURE_NOT_SUPPORTED,
+            "Shallow clones are not supported; Lance tables can only reference data files under their own path"
+        );
+    }
+
+    let scanner = open_scanner(source_path, None);
+    scanner
+        .copy_table(dest_path)
+        .unwrap_or_else(|code| match code {
+            PgSqlErrorCode::ERRCODE_DUPLICATE_OBJECT => ereport!(
+                ERROR,
+                code,
+                format!("Destination {} already holds files", dest_path)
+            ),
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE => ereport!(
+                ERROR,
+                code,
+                format!(
+                    "Cannot clone Lance table at {} to {}: the source must not be a bucket root and the destination must be a valid location",
+                    source_path, dest_path
+                )
+            ),
+            _ => ereport!(
+                ERROR,
+                code,
+                format!(
+                    "Failed to clone Lance table at {} to {}",
+                    source_path, dest_path
+                )
+            ),
+        });
+    version_to_bigint(open_scanner(dest_path, None).version())
+}
+
+// Cloning writes to arbitrary paths the server can reach
+extension_sql!(
+    r#"
+REVOKE EXECUTE ON FUNCTION lance_clone(TEXT, TEXT, BOOLEAN) FROM PUBLIC;
+"#,
+    name = "revoke_lance_clone",
+    requires = [lance_clone],
+);
+
+/// Return the first `n` rows of a Lance table
+#[pg_extern]
+pub fn lance_head(
+    table_path: &str,
+    n: default!(i64, 10),
+) -> TableIterator<'static, (name!(row_data, pgrx::JsonB),)> {
+    check_row_count_arg(n);
+    let scanner = open_scanner(table_path, None);
+
+    let scan_iter = scanner
+        .scan_with_filter(None, Some(n))
+        .unwrap_or_else(|e| raise_scan_error(e));
+
+    TableIterator::new(scan_to_jsonb_rows(scan_iter, Some(n)))
+}
+
+/// Return the last `n` rows of a Lance table
+///
+/// Counts the rows first and scans from offset `count - n`; asking for more
+/// rows than the table has returns the whole table.
+#[pg_extern]
+pub fn lance_tail(
+    table_path: &str,
+    n: default!(i64, 10),
+) -> TableIterator<'static, (name!(row_data, pgrx::JsonB),)> {
+    check_row_count_arg(n);
+    let scanner = open_scanner(table_path, None);
+
+    let num_rows = scanner
+        .get_stats()
+        .unwrap_or_else(|_| pgrx::error!("Failed to get table statistics"))
+        .num_rows as i64;
+    let offset = (num_rows - n).max(0);
+
+    let scan_iter = scanner
+        .scan_with_offset(None, None, Some(n), Some(offset))
+        .unwrap_or_else(|e| raise_scan_error(e));
+
+    TableIterator::new(scan_to_jsonb_rows(scan_iter, Some(n)))
+}
+
+/// Validate a `timeout_ms` argument, which must be positive when given
+fn timeout_arg(timeout_ms: Option<i32>) -> Option<std::time::Duration> {
+    timeout_ms.map(|timeout_ms| {
+        if timeout_ms <= 0 {
+            ereport!(
+                ERROR,
+                PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+                format!("timeout_ms must be positive, got {}", timeout_ms)
+            );
+        }
+        std::time::Duration::from_millis(timeout_ms as u64)
+    })
+}
+
+fn check_row_count_arg(n: i64) {
+    if n < 0 {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            format!("n must not be negative, got {}", n)
+        );
+    }
+}
+
+/// Return a random sample of approximately `fraction` of the rows
+///
+/// Passing `seed` makes the sample reproducible across calls.
+#[pg_extern]
+pub fn lance_sample(
+    table_path: &str,
+    fraction: f64,
+    limit: default!(Option<i64>, "NULL"),
+    seed: default!(Option<i64>, "NULL"),
+) -> TableIterator<'static, (name!(row_data, pgrx::JsonB),)> {
+    if !(fraction > 0.0 && fraction <= 1.0) {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            format!("fraction must be in (0, 1], got {}", fraction)
+        );
+    }
+
+    let scanner = open_scanner(table_path, None);
+
+    let scan_iter = scanner
+        .sample(fraction, seed.map(|s| s as u64))
+        .unwrap_or_else(|_| pgrx::error!("Failed to sample Lance table"));
+
+    TableIterator::new(scan_to_jsonb_rows(scan_iter, limit))
+}
+
+/// Return the rows whose hashed row id leaves `remainder` modulo `modulus`
+///
+/// The subset depends only on the row ids, so it is the same on every call
+/// without a seed, and the subsets for `0..modulus` partition the table.
+#[pg_extern]
+pub fn lance_hash_sample(
+    table_path: &str,
+    modulus: i32,
+    remainder: i32,
+    limit: default!(Option<i64>, "NULL"),
+) -> TableIterator<'static, (name!(row_data, pgrx::JsonB),)> {
+    if modulus < 1 || !(0..modulus).contains(&remainder) {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            format!(
+                "modulus must be positive and remainder in [0, modulus), got {} and {}",
+                modulus, remainder
+            )
+        );
+    }
+
+    let scanner = open_scanner(table_path, None);
+
+    let scan_iter = scanner
+        .hash_sample(modulus as u64, remainder as u64, limit)
+        .unwrap_or_else(|e| raise_scan_error(e));
+
+    TableIterator::new(scan_to_jsonb_rows(scan_iter, limit))
+}
+
+/// Scan a vector column and return each value as a `float4[]`
+///
+/// Accepts `List`, `LargeList` and `FixedSizeList` columns of floats.
+#[pg_extern]
+pub fn lance_scan_vectors(
+    table_path: &str,
+    column: &str,
+    limit: default!(Option<i64>, "NULL"),
+    null_element_policy: default!(&str, "'null'"),
+) -> TableIterator<'static, (name!(vector, Option<Vec<Option<f32>>>),)> {
+    let policy = types::NullElementPolicy::parse(null_element_policy).unwrap_or_else(|code| {
+        ereport!(
+            ERROR,
+            code,
+            format!(
+                "Unknown null_element_policy \"{}\", expected null, zero or error",
+                null_element_policy
+            )
+        )
+    });
+    let scanner = open_scanner(table_path, None);
+    vector_column_index(&scanner.schema(), column);
+
+    let projection = vec![(column.to_string(), scanner::quote_identifier(column))];
+    let scan_iter = scanner
+        .scan_with_projection(None, Some(projection), limit)
+        .unwrap_or_else(|e| raise_scan_error(e));
+
+    let mut results = Vec::new();
+    for record_batch in scan_iter.batches {
+        let vectors = record_batch.column(0);
+        for row_idx in 0..record_batch.num_rows() {
+            let vector = types::arrow_list_to_f32_vec(vectors.as_ref(), row_idx)
+                .and_then(|vector| vector.map(|v| policy.apply(v)).transpose())
+                .unwrap_or_else(|e| ereport!(ERROR, e.code, e.message));
+            results.push((vector,));
+        }
+    }
+    if let Some(limit) = limit {
+        results.truncate(limit.max(0) as usize);
+    }
+
+    TableIterator::new(results)
+}
+
+/// Find the index of a float vector column, raising an error if there is none
+fn vector_column_index(schema: &arrow::datatypes::Schema, column: &str) -> usize {
+    let (idx, field) = schema.column_with_name(column).unwrap_or_else(|| {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_UNDEFINED_COLUMN,
+            format!("Column \"{}\" does not exist in Lance table", column)
+        )
+    });
+    if !types::is_float_vector_type(field.data_type()) {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_DATATYPE_MISMATCH,
+            format!(
+                "Column \"{}\" of type {:?} is not a float vector column",
+                column,
+                field.data_type()
+            )
+        );
+    }
+    idx
+}
+
+/// Alias of the projected postfilter outcome in `nearest_rows` scans
+const NEAREST_MATCHES_COLUMN: &str = "_pglance_matches";
+
+/// A row ranked by `nearest_rows`, ordered by distance and then scan order
+struct NearestCandidate {
+    distance: f64,
+    seq: u64,
+    row_id: u64,
+    matches: bool,
+}
+
+impl PartialEq for NearestCandidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == std::cmp::Ordering::Equal
+    }
+}
+
+impl Eq for NearestCandidate {}
+
+impl PartialOrd for NearestCandidate {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for NearestCandidate {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.distance
+            .total_cmp(&other.distance)
+            .then(self.seq.cmp(&other.seq))
+    }
+}
+
+/// Exact nearest neighbor search over one or more vector columns
+///
+/// Each search is `(column, query, weight)`; a row's distance is the weighted
+/// sum of the Euclidean distances of its vectors to the queries. Rows with a
+/// NULL vector or NULL elements are skipped. Returns the `k` nearest rows,
+/// closest first, with ties kept in scan order.
+///
+/// Only the vector columns are scanned, and a postfilter is evaluated in the
+/// same scan. The `k` best rows are kept in a bounded heap, and only those
+/// are then read in full.
+fn nearest_rows(
+    table_path: &str,
+    searches: &[(&str, &[f32], f64)],
+    k: i32,
+    filter: Option<&str>,
+    prefilter: bool,
+) -> Vec<(pgrx::JsonB, f64)> {
+    if k < 1 {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            format!("k must be positive, got {}", k)
+        );
+    }
+    let scanner = open_scanner(table_path, None);
+    let schema = scanner.schema();
+    for (column, _, _) in searches {
+        vector_column_index(&schema, column);
+    }
+
+    let mut projection: Vec<(String, String)> = Vec::new();
+    for (column, _, _) in searches {
+        if !projection.iter().any(|(alias, _)| alias == column) {
+            projection.push((column.to_string(), scanner::quote_identifier(column)));
+        }
+    }
+    // A postfilter ranks every row and then drops the nearest rows that do
+    // not match, so whether a row matches is read alongside its vectors
+    let postfilter = filter.filter(|_| !prefilter);
+    if let Some(filter) = postfilter {
+        projection.push((NEAREST_MATCHES_COLUMN.to_string(), format!("({})", filter)));
+    }
+    let mut options = ScanOptions::new().with_projection(projection);
+    if prefilter {
+        options.filter = filter.map(str::to_string);
+    }
+
+    let k = k as usize;
+    let mut heap = std::collections::BinaryHeap::with_capacity(k + 1);
+    let mut seq = 0u64;
+    let mut conversion_error = None;
+    scanner
+        .for_each_batch_with_row_id(options, |batch| {
+            match rank_nearest_batch(
+                &batch,
+                searches,
+                postfilter.is_some(),
+                &mut seq,
+                &mut heap,
+                k,
+            ) {
+                Ok(()) => std::ops::ControlFlow::Continue(()),
+                Err(e) => {
+                    conversion_error = Some(e);
+                    std::ops::ControlFlow::Break(())
+                }
+            }
+        })
+        .unwrap_or_else(|e| raise_scan_error(e));
+    if let Some(e) = conversion_error {
+        ereport!(ERROR, e.code, e.message);
+    }
+
+    let winners: Vec<NearestCandidate> = heap
+        .into_sorted_vec()
+        .into_iter()
+        .filter(|candidate| candidate.matches)
+        .collect();
+    if winners.is_empty() {
+        return Vec::new();
+    }
+    let row_ids: Vec<u64> = winners.iter().map(|candidate| candidate.row_id).collect();
+    let names: Vec<&str> = schema.fields().iter().map(|f| f.name().as_str()).collect();
+    let rows = scanner.take_rows(&row_ids, &names).unwrap_or_else(|code| {
+        ereport!(
+            ERROR,
+            code,
+            format!(
+                "Failed to read nearest rows of Lance table at: {}",
+                table_path
+            )
+        )
+    });
+    let encoder = BatchJsonEncoder::new(&rows).only(&names);
+    winners
+        .iter()
+        .enumerate()
+        .map(|(row_idx, candidate)| {
+            let row = encoder
+                .encode_row(row_idx)
+                .unwrap_or_else(|e| ereport!(ERROR, e.code, e.message));
+            (pgrx::JsonB(row), candidate.distance)
+        })
+        .collect()
+}
+
+/// Rank the rows of one `nearest_rows` batch into a heap of the `k` nearest
+///
+/// The batch holds the searched columns, then the postfilter outcome when
+/// `has_matches`, then `_rowid`.
+fn rank_nearest_batch(
+    batch: &RecordBatch,
+    searches: &[(&str, &[f32], f64)],
+    has_matches: bool,
+    seq: &mut u64,
+    heap: &mut std::collections::BinaryHeap<NearestCandidate>,
+    k: usize,
+) -> Result<(), ConversionError> {
+    let schema = batch.schema();
+    let columns: Vec<&ArrayRef> = searches
+        .iter()
+        .map(|(column, _, _)| {
+            let (idx, _) = schema
+                .column_with_name(column)
+                .expect("searched columns are projected");
+            batch.column(idx)
+        })
+        .collect();
+    let row_ids = downcast_array::<UInt64Array>(
+        batch.column(batch.num_columns() - 1).as_ref(),
+        "UInt64Array",
+    )?;
+    let matches = if has_matches {
+        Some(downcast_array::<BooleanArray>(
+            batch.column(batch.num_columns() - 2).as_ref(),
+            "BooleanArray",
+        )?)
+    } else {
+        None
+    };
+
+    'row_loop: for row_idx in 0..batch.num_rows() {
+        *seq += 1;
+        let mut distance = 0.0;
+        for (column, (name, query, weight)) in columns.iter().zip(searches) {
+            let Some(vector) = types::arrow_list_to_f32_vec(column.as_ref(), row_idx)? else {
+                continue 'row_loop;
+            };
+            if vector.len() != query.len() {
+                return Err(ConversionError::new(
+                    PgSqlErrorCode::ERRCODE_DATATYPE_MISMATCH,
+                    format!(
+                        "Query vector has {} dimensions, but column \"{}\" has a vector of {}",
+                        query.len(),
+                        name,
+                        vector.len()
+                    ),
+                ));
+            }
+            match types::euclidean_distance(&vector, query) {
+                Some(d) => distance += weight * d,
+                None => continue 'row_loop,
+            }
+        }
+        heap.push(NearestCandidate {
+            distance,
+            seq: *seq,
+            row_id: row_ids.value(row_idx),
+            matches: matches
+                .is_none_or(|matches| matches.is_valid(row_idx) && matches.value(row_idx)),
+        });
+        if heap.len() > k {
+            heap.pop();
+        }
+    }
+    Ok(())
+}
+
+/// Find the `k` rows whose vectors in `column` are nearest to `query`
+///
+/// Performs an exact search by Euclidean distance, so any float vector column
+/// can be searched by name. Returns rows closest first. With `prefilter`, the
+/// `k` nearest rows matching `filter` are returned; otherwise the `k` nearest
+/// rows are found first and those not matching `filter` are dropped.
+#[pg_extern]
+pub fn lance_knn(
+    table_path: &str,
+    column: &str,
+    query: Vec<f32>,
+    k: default!(i32, 10),
+    filter: default!(Option<&str>, "NULL"),
+    prefilter: default!(bool, true),
+) -> TableIterator<'static, (name!(row_data, pgrx::JsonB), name!(distance, f64))> {
+    TableIterator::new(nearest_rows(
+        table_path,
+        &[(column, &query, 1.0)],
+        k,
+        filter,
+        prefilter,
+    ))
+}
+
+/// Find the `k` nearest rows by a weighted combination of two vector columns
+///
+/// The distance is `weight * distance_a + (1 - weight) * distance_b`, so a
+/// weight of 1 searches only `column_a` and 0 only `column_b`.
+#[pg_extern]
+pub fn lance_knn_fused(
+    table_path: &str,
+    column_a: &str,
+    query_a: Vec<f32>,
+    column_b: &str,
+    query_b: Vec<f32>,
+    weight: default!(f64, 0.5),
+    k: default!(i32, 10),
+) -> TableIterator<'static, (name!(row_data, pgrx::JsonB), name!(distance, f64))> {
+    if !(0.0..=1.0).contains(&weight) {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            format!("weight must be between 0 and 1, got {}", weight)
+        );
+    }
+    let searches = [
+        (column_a, query_a.as_slice(), weight),
+        (column_b, query_b.as_slice(), 1.0 - weight),
+    ];
+    TableIterator::new(nearest_rows(table_path, &searches, k, None, true))
+}
+
+/// Distance between the vectors of two rows, identified by Lance row id
+///
+/// `metric` is `l2`, `cosine` or `dot` (the negated dot product). Returns
+/// NULL when either vector is NULL or has NULL elements.
+#[pg_extern]
+pub fn lance_vector_distance(
+    table_path: &str,
+    column: &str,
+    rowid_a: i64,
+    rowid_b: i64,
+    metric: default!(&str, "'l2'"),
+) -> Option<f32> {
+    let metric = types::DistanceMetric::parse(metric).unwrap_or_else(|code| {
+        ereport!(
+            ERROR,
+            code,
+            format!(
+                "Unknown distance metric \"{}\", expected l2, cosine or dot",
+                metric
+            )
+        )
+    });
+    let mut row_ids: Vec<u64> = [rowid_a, rowid_b]
+        .iter()
+        .map(|&row_id| {
+            u64::try_from(row_id).unwrap_or_else(|_| {
+                ereport!(
+                    ERROR,
+                    PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+                    format!("Row id must not be negative, got {}", row_id)
+                )
+            })
+        })
+        .collect();
+    row_ids.dedup();
+
+    let scanner = open_scanner(table_path, None);
+    vector_column_index(&scanner.schema(), column);
+    let batch = scanner
+        .take_rows(&row_ids, &[column])
+        .unwrap_or_else(|code| {
+            ereport!(
+                ERROR,
+                code,
+                format!(
+                    "Failed to read rows {} and {} of Lance table at: {}",
+                    rowid_a, rowid_b, table_path
+                )
+            )
+        });
+
+    let vector = |row_idx: usize| {
+        types::arrow_list_to_f32_vec(batch.column(0).as_ref(), row_idx)
+            .unwrap_or_else(|e| ereport!(ERROR, e.code, e.message))
+    };
+    let (a, b) = (vector(0)?, vector(row_ids.len() - 1)?);
+    if a.len() != b.len() {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_DATATYPE_MISMATCH,
+            format!(
+                "Vectors of rows {} and {} have different dimensions: {} and {}",
+                rowid_a,
+                rowid_b,
+                a.len(),
+                b.len()
+            )
+        );
+    }
+    types::vector_distance(&a, &b, metric).map(|distance| distance as f32)
+}
+
+/// Check whether at least `threshold` rows match `filter`
+///
+/// Streams the matching row ids and stops as soon as the threshold is reached,
+/// which is much cheaper than a full count on large tables.
+#[pg_extern]
+pub fn lance_count_at_least(table_path: &str, filter: Option<&str>, threshold: i64) -> bool {
+    if threshold <= 0 {
+        return true;
+    }
+    let scanner = open_scanner(table_path, None);
+
+    scanner
+        .count_at_least(
+            filter.map(str::to_string),
+            threshold as u64,
+            || pgrx::check_for_interrupts!(),
+        )
+        .unwrap_or_else(|e| raise_scan_error(e))
+}
+
+/// Count rows grouped by the values of a column
+///
+/// Groups are built from the Arrow values while streaming the column, without
+/// materializing JSONB rows. Returns groups by descending count (ties ordered
+/// by value), keeping only the top `limit` groups when given. NULL values form
+/// their own group.
+#[pg_extern]
+pub fn lance_group_count(
+    table_path: &str,
+    column: &str,
+    limit: default!(Option<i64>, "NULL"),
+) -> TableIterator<'static, (name!(group_value, pgrx::JsonB), name!(count, i64))> {
+    let scanner = open_scanner(table_path, None);
+
+    if scanner.schema().field_with_name(column).is_err() {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_UNDEFINED_COLUMN,
+            format!("Column \"{}\" does not exist in Lance table", column)
+        );
+    }
+
+    let projection = vec![(column.to_string(), scanner::quote_identifier(column))];
+    let scan_iter = scanner
+        .scan_with_projection(None, Some(projection), None)
+        .unwrap_or_else(|e| raise_scan_error(e));
+
+    let mut groups: std::collections::HashMap<String, (Value, i64)> =
+        std::collections::HashMap::new();
+    for record_batch in scan_iter.batches {
+        let values = JsonColumn::new(record_batch.column(0));
+        for row_idx in 0..record_batch.num_rows() {
+            let value = values
+                .value(row_idx)
+                .unwrap_or_else(|e| ereport!(ERROR, e.code, e.message));
+            groups
+                .entry(value.to_string())
+                .or_insert_with(|| (value, 0))
+                .1 += 1;
+        }
+    }
+
+    let mut groups: Vec<(String, (Value, i64))> = groups.into_iter().collect();
+    groups.sort_by(|(a_key, (_, a_count)), (b_key, (_, b_count))| {
+        b_count.cmp(a_count).then_with(|| a_key.cmp(b_key))
+    });
+    if let Some(limit) = limit {
+        groups.truncate(limit.max(0) as usize);
+    }
+
+    TableIterator::new(
+        groups
+            .into_iter()
+            .map(|(_, (value, count))| (pgrx::JsonB(value), count)),
+    )
+}
+
+/// Estimate quantiles of a numeric column
+///
+/// The column is streamed batch by batch into a t-digest, so memory use stays
+/// bounded regardless of table size. Returns a JSON object mapping each
+/// requested probability to its estimated value (`null` for an empty column).
+#[pg_extern]
+pub fn lance_quantiles(table_path: &str, column: &str, q: Vec<f64>) -> pgrx::JsonB {
+    if let Some(p) = q.iter().find(|p| !(0.0..=1.0).contains(*p)) {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            format!("quantile probabilities must be in [0, 1], got {}", p)
+        );
+    }
+
+    let scanner = open_scanner(table_path, None);
+
+    let schema = scanner.schema();
+    let field = schema.field_with_name(column).unwrap_or_else(|_| {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_UNDEFINED_COLUMN,
+            format!("Column \"{}\" does not exist in Lance table", column)
+        )
+    });
+    if !field.data_type().is_numeric() {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_DATATYPE_MISMATCH,
+            format!(
+                "Column \"{}\" of type {:?} is not numeric",
+                column,
+                field.data_type()
+            )
+        );
+    }
+
+    let projection = vec![(column.to_string(), scanner::quote_identifier(column))];
+    let scan_iter = scanner
+        .scan_with_projection(None, Some(projection), None)
+        .unwrap_or_else(|e| raise_scan_error(e));
+
+    let mut digest = TDigest::new_with_size(100);
+    let mut count = 0usize;
+    for record_batch in scan_iter.batches {
+        let values = arrow::compute::cast(record_batch.column(0), &DataType::Float64)
+            .unwrap_or_else(|e| pgrx::error!("Failed to read column \"{}\": {}", column, e));
+        let values: Vec<f64> = downcast_array::<Float64Array>(values.as_ref(), "Float64Array")
+            .unwrap_or_else(|e| ereport!(ERROR, e.code, e.message))
+            .iter()
+            .flatten()
+            .filter(|v| !v.is_nan())
+            .collect();
+        count += values.len();
+        digest = digest.merge_unsorted(values);
+    }
+
+    let quantiles: Map<String, Value> = q
+        .iter()
+        .map(|p| {
+            let value = if count == 0 {
+                Value::Null
+            } else {
+                Number::from_f64(digest.estimate_quantile(*p))
+                    .map(Value::Number)
+                    .unwrap_or(Value::Null)
+            };
+            (p.to_string(), value)
+        })
+        .collect();
+
+    pgrx::JsonB(Value::Object(quantiles))
+}
+
+/// Summarize a column in a single scan
+///
+/// Returns `count` (non-null values), `null_count`, `min` and `max`, plus
+/// `mean` and sample `stddev` for numeric columns. Per-batch moments are
+/// merged with Chan's parallel algorithm, and min/max use Arrow's sort kernel
+/// so any orderable type is supported.
+#[pg_extern]
+pub fn lance_column_summary(table_path: &str, column: &str) -> pgrx::JsonB {
+    let scanner = open_scanner(table_path, None);
+
+    let schema = scanner.schema();
+    let field = schema.field_with_name(column).unwrap_or_else(|_| {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_UNDEFINED_COLUMN,
+            format!("Column \"{}\" does not exist in Lance table", column)
+        )
+    });
+    let numeric = field.data_type().is_numeric();
+
+    let projection = vec![(column.to_string(), scanner::quote_identifier(column))];
+    let scan_iter = scanner
+        .scan_with_projection(None, Some(projection), None)
+        .unwrap_or_else(|e| raise_scan_error(e));
+
+    let read_error = |e: arrow::error::ArrowError| -> ! {
+        pgrx::error!("Failed to summarize column \"{}\": {}", column, e)
+    };
+    let mut count = 0u64;
+    let mut null_count = 0u64;
+    let mut min: Option<ArrayRef> = None;
+    let mut max: Option<ArrayRef> = None;
+    let mut moments = Moments::default();
+    for record_batch in scan_iter.batches {
+        let values = record_batch.column(0);
+        null_count += values.null_count() as u64;
+        count += (values.len() - values.null_count()) as u64;
+
+        min = merge_extreme(min, values, false).unwrap_or_else(|e| read_error(e));
+        max = merge_extreme(max, values, true).unwrap_or_else(|e| read_error(e));
+
+        if numeric {
+            let floats =
+                arrow::compute::cast(values, &DataType::Float64).unwrap_or_else(|e| read_error(e));
+            let floats = downcast_array::<Float64Array>(floats.as_ref(), "Float64Array")
+                .unwrap_or_else(|e| ereport!(ERROR, e.code, e.message));
+            moments = moments.merge(Moments::of(floats));
+        }
+    }
+
+    let extreme_json = |extreme: Option<ArrayRef>| match extreme {
+        Some(array) => arrow_value_to_serde_json(array.as_ref(), 0)
+            .unwrap_or_else(|e| ereport!(ERROR, e.code, e.message)),
+        None => Value::Null,
+    };
+    let mut summary = Map::new();
+    summary.insert("count".to_string(), json!(count));
+    summary.insert("null_count".to_string(), json!(null_count));
+    summary.insert("min".to_string(), extreme_json(min));
+    summary.insert("max".to_string(), extreme_json(max));
+    if numeric {
+        let float_json = |v: Option<f64>| {
+            v.and_then(Number::from_f64)
+                .map(Value::Number)
+                .unwrap_or(Value::Null)
+        };
+        summary.insert("mean".to_string(), float_json(moments.mean()));
+        summary.insert("stddev".to_string(), float_json(moments.sample_stddev()));
+    }
+
+    pgrx::JsonB(Value::Object(summary))
+}
+
+/// Get the minimum and maximum values of a column
+///
+/// Returns `min`, `max`, `source` and `exact`. Bounds would come from zone-map
+/// statistics when Lance records them, but the Lance version this extension
+/// builds against exposes none, so the bounds always come from a single scan
+/// of the column (`source` is `scan`) and are exact. Only the one column is
+/// read, which is still cheaper than a summary.
+#[pg_extern]
+pub fn lance_column_bounds(table_path: &str, column: &str) -> pgrx::JsonB {
+    let scanner = open_scanner(table_path, None);
+
+    if scanner.schema().field_with_name(column).is_err() {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_UNDEFINED_COLUMN,
+            format!("Column \"{}\" does not exist in Lance table", column)
+        );
+    }
+
+    let projection = vec![(column.to_string(), scanner::quote_identifier(column))];
+    let scan_iter = scanner
+        .scan_with_projection(None, Some(projection), None)
+        .unwrap_or_else(|e| raise_scan_error(e));
+
+    let read_error = |e: arrow::error::ArrowError| -> ! {
+        pgrx::error!("Failed to compute bounds of column \"{}\": {}", column, e)
+    };
+    let mut min: Option<ArrayRef> = None;
+    let mut max: Option<ArrayRef> = None;
+    for record_batch in scan_iter.batches {
+        let values = record_batch.column(0);
+        min = merge_extreme(min, values, false).unwrap_or_else(|e| read_error(e));
+        max = merge_extreme(max, values, true).unwrap_or_else(|e| read_error(e));
+    }
+
+    let extreme_json = |extreme: Option<ArrayRef>| match extreme {
+        Some(array) => arrow_value_to_serde_json(array.as_ref(), 0)
+            .unwrap_or_else(|e| ereport!(ERROR, e.code, e.message)),
+        None => Value::Null,
+    };
+    pgrx::JsonB(json!({
+        "min": extreme_json(min),
+        "max": extreme_json(max),
+        "source": "scan",
+        "exact": true,
+    }))
+}
+
+/// Count, mean and sum of squared deviations of a set of values
+#[derive(Debug, Default, Clone, Copy)]
+struct Moments {
+    count: u64,
+    mean: f64,
+    m2: f64,
+}
+
+impl Moments {
+    fn of(values: &Float64Array) -> Self {
+        let count = (values.len() - values.null_count()) as u64;
+        if count == 0 {
+            return Self::default();
+        }
+        let mean = values.iter().flatten().sum::<f64>() / count as f64;
+        let m2 = values
+            .iter()
+            .flatten()
+            .map(|v| (v - mean) * (v - mean))
+            .sum();
+        Self { count, mean, m2 }
+    }
+
+    /// Combine the moments of two disjoint sets of values
+    fn merge(self, other: Self) -> Self {
+        if self.count == 0 {
+            return other;
+        }
+        if other.count == 0 {
+            return self;
+        }
+        let count = self.count + other.count;
+        let delta = other.mean - self.mean;
+        let mean = self.mean + delta * other.count as f64 / count as f64;
+        let m2 = self.m2
+            + other.m2
+            + delta * delta * (self.count as f64 * other.count as f64) / count as f64;
+        Self { count, mean, m2 }
+    }
+
+    fn mean(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.mean)
+    }
+
+    fn sample_stddev(&self) -> Option<f64> {
+        (self.count > 1).then(|| (self.m2 / (self.count - 1) as f64).sqrt())
+    }
+}
+
+/// Fold a batch into a running minimum or maximum kept as a one-row array
+fn merge_extreme(
+    current: Option<ArrayRef>,
+    values: &ArrayRef,
+    descending: bool,
+) -> Result<Option<ArrayRef>, arrow::error::ArrowError> {
+    let candidates = match &current {
+        Some(current) => arrow::compute::concat(&[current.as_ref(), values.as_ref()])?,
+        None => Arc::clone(values),
+    };
+    if candidates.null_count() == candidates.len() {
+        return Ok(current);
+    }
+    let options = arrow::compute::SortOptions {
+        descending,
+        nulls_first: false,
+    };
+    let indices = arrow::compute::sort_to_indices(candidates.as_ref(), Some(options), Some(1))?;
+    Ok(Some(arrow::compute::take(
+        candidates.as_ref(),
+        &indices,
+        None,
+    )?))
+}
+
+/// Compute an order-independent checksum of a Lance table's data
+///
+/// Each row is rendered to canonical JSON and hashed with XXH3; row hashes are
+/// summed so the result does not depend on fragment or scan order, then mixed
+/// with the row count. Pass `column` to checksum a single column.
+///
+/// The checksum identifies data, not physical layout: compaction or rewrites
+/// leave it unchanged, while duplicated rows, value changes and schema changes
+/// (including column renames) alter it. Floating point values are hashed by
+/// their JSON rendering, so NaN and infinities all collapse to `null`.
+#[pg_extern]
+pub fn lance_checksum(table_path: &str, column: default!(Option<&str>, "NULL")) -> String {
+    let scanner = open_scanner(table_path, None);
+
+    let projection = column.map(|column| {
+        if scanner.schema().field_with_name(column).is_err() {
+            ereport!(
+                ERROR,
+                PgSqlErrorCode::ERRCODE_UNDEFINED_COLUMN,
+                format!("Column \"{}\" does not exist in Lance table", column)
+            );
+        }
+        vec![(column.to_string(), scanner::quote_identifier(column))]
+    });
+    let scan_iter = scanner
+        .scan_with_projection(None, projection, None)
+        .unwrap_or_else(|e| raise_scan_error(e));
+
+    let mut sum = 0u64;
+    let mut num_rows = 0u64;
+    for record_batch in scan_iter.batches {
+        for row_idx in 0..record_batch.num_rows() {
+            let row = record_batch_row_to_json(&record_batch, row_idx)
+                .unwrap_or_else(|e| ereport!(ERROR, e.code, e.message));
+            sum = sum.wrapping_add(xxh3_64(row.to_string().as_bytes()));
+            num_rows += 1;
+        }
+    }
+
+    let mut digest = [0u8; 16];
+    digest[..8].copy_from_slice(&sum.to_le_bytes());
+    digest[8..].copy_from_slice(&num_rows.to_le_bytes());
+    format!("{:016x}", xxh3_64(&digest))
+}
+
+/// Look up rows by equality on a single column
+///
+/// The value is rendered as a typed, escaped literal based on the column's
+/// Arrow type, so Lance can answer the lookup from a scalar index when one exists.
+#[pg_extern]
+pub fn lance_lookup(
+    table_path: &str,
+    column: &str,
+    value: pgrx::JsonB,
+    limit: default!(Option<i64>, "NULL"),
+) -> TableIterator<'static, (name!(row_data, pgrx::JsonB),)> {
+    let scanner = open_scanner(table_path, None);
+
+    let schema = scanner.schema();
+    let field = schema.field_with_name(column).unwrap_or_else(|_| {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_UNDEFINED_COLUMN,
+            format!("Column \"{}\" does not exist in Lance table", column)
+        )
+    });
+
+    let predicate = scanner::equality_predicate(column, &value.0, field.data_type())
+        .unwrap_or_else(|code| {
+            ereport!(
+                ERROR,
+                code,
+                format!(
+                    "Value {} is not compatible with column \"{}\" of type {:?}",
+                    value.0,
+                    column,
+                    field.data_type()
+                )
+            )
+        });
+
+    let scan_iter = scanner
+        .scan_with_filter(Some(predicate), limit)
+        .unwrap_or_else(|e| raise_scan_error(e));
+
+    TableIterator::new(scan_to_jsonb_rows(scan_iter, limit))
+}
+
+/// Fetch a single value of a Lance table by row id and column
+///
+/// Reads exactly one cell with a one-row, one-column take, the cheapest read
+/// Lance offers. Returns the value as JSONB, with SQL NULLs as JSON `null`.
+#[pg_extern]
+pub fn lance_get_cell(table_path: &str, rowid: i64, column: &str) -> pgrx::JsonB {
+    let row_id = u64::try_from(rowid).unwrap_or_else(|_| {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            format!("Row id must not be negative, got {}", rowid)
+        )
+    });
+    let scanner = open_scanner(table_path, None);
+    if scanner.schema().field_with_name(column).is_err() {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_UNDEFINED_COLUMN,
+            format!("Column \"{}\" does not exist in Lance table", column)
+        );
+    }
+
+    let batch = scanner
+        .take_rows(&[row_id], &[column])
+        .unwrap_or_else(|code| {
+            ereport!(
+                ERROR,
+                code,
+                format!(
+                    "Failed to read row {} of Lance table at: {}",
+                    rowid, table_path
+                )
+            )
+        });
+    let value = arrow_value_to_serde_json(batch.column(0).as_ref(), 0)
+        .unwrap_or_else(|e| ereport!(ERROR, e.code, e.message));
+    pgrx::JsonB(value)
+}
+
+/// Return rows where a column matches any of the given values
+///
+/// Values are rendered as typed, escaped literals into an `IN` predicate, so
+/// Lance can answer the lookup from a scalar index when one exists.
+/// `timeout_ms` bounds the time spent reading the table.
+#[pg_extern]
+pub fn lance_scan_in(
+    table_path: &str,
+    column: &str,
+    values: Vec<pgrx::JsonB>,
+    limit: default!(Option<i64>, "NULL"),
+    timeout_ms: default!(Option<i32>, "NULL"),
+) -> TableIterator<'static, (name!(row_data, pgrx::JsonB),)> {
+    let timeout = timeout_arg(timeout_ms);
+    let scanner = open_scanner(table_path, None);
+
+    let schema = scanner.schema();
+    let field = schema.field_with_name(column).unwrap_or_else(|_| {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_UNDEFINED_COLUMN,
+            format!("Column \"{}\" does not exist in Lance table", column)
+        )
+    });
+
+    let values: Vec<Value> = values.into_iter().map(|v| v.0).collect();
+    let predicate =
+        scanner::in_list_predicate(column, &values, field.data_type()).unwrap_or_else(|code| {
+            ereport!(
+                ERROR,
+                code,
+                format!(
+                    "Values are not compatible with column \"{}\" of type {:?}",
+                    column,
+                    field.data_type()
+                )
+            )
+        });
+    let Some(predicate) = predicate else {
+        return TableIterator::new(Vec::new());
+    };
+
+    let options = ScanOptions {
+        filter: Some(predicate),
+        limit,
+        timeout,
+        ..Default::default()
+    };
+    let scan_iter = scanner
+        .scan(&options)
+        .unwrap_or_else(|e| raise_scan_error(e));
+
+    TableIterator::new(scan_to_jsonb_rows(scan_iter, limit))
+}
+
+/// Return a Lance table version as `bigint`, raising an error past `i64::MAX`
+fn version_to_bigint(version: u64) -> i64 {
+    scanner::version_to_i64(version).unwrap_or_else(|e| ereport!(ERROR, e.code, e.message))
+}
+
+/// Raise a scan failure with the SQLSTATE of its kind
+fn raise_scan_error(e: scanner::ScanError) -> ! {
+    ereport!(
+        ERROR,
+        e.code(),
+        format!("Failed to create scan iterator: {}", e)
+    )
+}
+
+/// Open a Lance table, applying optional storage options given as a JSON object
+fn open_scanner(table_path: &str, storage_options: Option<pgrx::JsonB>) -> LanceScanner {
+    let options = match storage_options {
+        Some(pgrx::JsonB(value)) => {
+            scanner::OpenOptions::from_json(&value).unwrap_or_else(|code| {
+                ereport!(
+                    ERROR,
+                    code,
+                    format!(
+                        "Invalid storage options: {}",
+                        scanner::redact_storage_options(&value)
+                    )
+                )
+            })
+        }
+        None => scanner::OpenOptions::default(),
+    };
+
+    LanceScanner::open(table_path, &options).unwrap_or_else(|code| {
+        ereport!(
+            ERROR,
+            code,
+            format!("Failed to open Lance table at: {}", table_path)
+        )
+    })
+}
+
+/// Convert a single row of a record batch into a JSON object keyed by column name
+fn record_batch_row_to_json(
+    record_batch: &RecordBatch,
+    row_idx: usize,
+) -> Result<Value, ConversionError> {
+    let mut json_map = Map::new();
+    for (col_idx, field) in record_batch.schema().fields().iter().enumerate() {
+        let column_array = record_batch.column(col_idx);
+        let value = arrow_value_to_serde_json(column_array.as_ref(), row_idx).map_err(|e| {
+            ConversionError::new(
+                e.code,
+                format!("column \"{}\": {}", field.name(), e.message),
+            )
+        })?;
+        json_map.insert(field.name().clone(), value);
+    }
+    Ok(Value::Object(json_map))
+}
+
+/// A column of a record batch, downcast once for row-by-row JSON encoding
+///
+/// Scalar types are read directly from the typed array; everything else goes
+/// through `arrow_value_to_serde_json`, producing identical output.
+enum JsonColumn {
+    Boolean(BooleanArray),
+    Int8(Int8Array),
+    Int16(Int16Array),
+    Int32(Int32Array),
+    Int64(Int64Array),
+    UInt8(UInt8Array),
+    UInt16(UInt16Array),
+    UInt32(UInt32Array),
+    UInt64(UInt64Array),
+    Float32(Float32Array),
+    Float64(Float64Array),
+    Utf8(StringArray),
+    LargeUtf8(LargeStringArray),
+    /// Binary values encoded with at most this many bytes each
+    TruncatedBinary(ArrayRef, usize),
+    /// Decimal values encoded in a format other than the default
+    Decimal(ArrayRef, types::DecimalFormat),
+    /// A column hidden from the output, encoded as NULL in every row
+    Masked,
+    Other(ArrayRef),
+}
+
+impl JsonColumn {
+    fn new(array: &ArrayRef) -> Self {
+        fn typed<T: Array + Clone + 'static>(array: &ArrayRef) -> Option<T> {
+            array.as_any().downcast_ref::<T>().cloned()
+        }
+
+        let column = match array.data_type() {
+            DataType::Boolean => typed(array).map(Self::Boolean),
+            DataType::Int8 => typed(array).map(Self::Int8),
+            DataType::Int16 => typed(array).map(Self::Int16),
+            DataType::Int32 => typed(array).map(Self::Int32),
+            DataType::Int64 => typed(array).map(Self::Int64),
+            DataType::UInt8 => typed(array).map(Self::UInt8),
+            DataType::UInt16 => typed(array).map(Self::UInt16),
+            DataType::UInt32 => typed(array).map(Self::UInt32),
+            DataType::UInt64 => typed(array).map(Self::UInt64),
+            DataType::Float32 => typed(array).map(Self::Float32),
+            DataType::Float64 => typed(array).map(Self::Float64),
+            DataType::Utf8 => typed(array).map(Self::Utf8),
+            DataType::LargeUtf8 => typed(array).map(Self::LargeUtf8),
+            _ => None,
+        };
+        // Arrays that fail to downcast report the mismatch when a value is read
+        column.unwrap_or_else(|| Self::Other(Arc::clone(array)))
+    }
+
+    fn value(&self, row_idx: usize) -> Result<Value, ConversionError> {
+        fn float(val: f64) -> Value {
+            Number::from_f64(val)
+                .map(Value::Number)
+                .unwrap_or(Value::Null)
+        }
+
+        let value = match self {
+            Self::Other(array) => return arrow_value_to_serde_json(array.as_ref(), row_idx),
+            Self::Masked => Value::Null,
+            Self::TruncatedBinary(array, max_bytes) if array.is_valid(row_idx) => {
+                let array = array.as_ref();
+                let bytes = match array.data_type() {
+                    DataType::Binary => {
+                        downcast_array::<BinaryArray>(array, "BinaryArray")?.value(row_idx)
+                    }
+                    DataType::LargeBinary => {
+                        downcast_array::<LargeBinaryArray>(array, "LargeBinaryArray")?
+                            .value(row_idx)
+                    }
+                    _ => downcast_array::<FixedSizeBinaryArray>(array, "FixedSizeBinaryArray")?
+                        .value(row_idx),
+                };
+                if bytes.len() > *max_bytes {
+                    let prefix = STANDARD.encode(&bytes[..*max_bytes]);
+                    Value::String(format!("{}{}", prefix, TRUNCATED_BINARY_MARKER))
+                } else {
+                    Value::String(STANDARD.encode(bytes))
+                }
+            }
+            Self::Decimal(array, format) if array.is_valid(row_idx) => {
+                decimal_to_json(array.as_ref(), row_idx, *format)?
+            }
+            Self::Boolean(a) if a.is_valid(row_idx) => Value::Bool(a.value(row_idx)),
+            Self::Int8(a) if a.is_valid(row_idx) => json!(a.value(row_idx)),
+            Self::Int16(a) if a.is_valid(row_idx) => json!(a.value(row_idx)),
+            Self::Int32(a) if a.is_valid(row_idx) => json!(a.value(row_idx)),
+            Self::Int64(a) if a.is_valid(row_idx) => json!(a.value(row_idx)),
+            Self::UInt8(a) if a.is_valid(row_idx) => json!(a.value(row_idx)),
+            Self::UInt16(a) if a.is_valid(row_idx) => json!(a.value(row_idx)),
+            Self::UInt32(a) if a.is_valid(row_idx) => json!(a.value(row_idx)),
+            Self::UInt64(a) if a.is_valid(row_idx) => json!(a.value(row_idx)),
+            Self::Float32(a) if a.is_valid(row_idx) => float(a.value(row_idx) as f64),
+            Self::Float64(a) if a.is_valid(row_idx) => float(a.value(row_idx)),
+            Self::Utf8(a) if a.is_valid(row_idx) => Value::String(a.value(row_idx).to_string()),
+            Self::LargeUtf8(a) if a.is_valid(row_idx) => {
+                Value::String(a.value(row_idx).to_string())
+            }
+            _ => Value::Null,
+        };
+        Ok(value)
+    }
+}
+
+/// Encodes the rows of one record batch as JSON objects
+///
+/// Column arrays are downcast once per batch instead of once per cell, which
+/// matters for wide tables. Produces the same output as `record_batch_row_to_json`.
+///
+/// Every encoded column becomes a key, with SQL NULLs as `null`; columns left
+/// out with `only` are absent from the objects altogether.
+struct BatchJsonEncoder {
+    names: Vec<String>,
+    columns: Vec<JsonColumn>,
+}
+
+impl BatchJsonEncoder {
+    fn new(record_batch: &RecordBatch) -> Self {
+        Self {
+            names: record_batch
+                .schema()
+                .fields()
+                .iter()
+                .map(|f| f.name().clone())
+                .collect(),
+            columns: record_batch.columns().iter().map(JsonColumn::new).collect(),
+        }
+    }
+
+    /// Encode only the named columns, in batch order
+    fn only(mut self, columns: &[&str]) -> Self {
+        let (names, columns) = self
+            .names
+            .into_iter()
+            .zip(self.columns)
+            .filter(|(name, _)| columns.contains(&name.as_str()))
+            .unzip();
+        self.names = names;
+        self.columns = columns;
+        self
+    }
+
+    /// Encode at most `max_bytes` of each binary value, marking cut values
+    ///
+    /// Applies to top-level binary columns; binary values nested in lists or
+    /// structs are encoded in full.
+    fn with_max_binary_bytes(mut self, max_bytes: usize) -> Self {
+        for column in &mut self.columns {
+            if let JsonColumn::Other(array) = column {
+                if matches!(
+                    array.data_type(),
+                    DataType::Binary | DataType::LargeBinary | DataType::FixedSizeBinary(_)
+                ) {
+                    *column = JsonColumn::TruncatedBinary(Arc::clone(array), max_bytes);
+                }
+            }
+        }
+        self
+    }
+
+    /// Encode the named columns as NULL, keeping their keys
+    fn with_masked(mut self, masked: &[String]) -> Self {
+        for (name, column) in self.names.iter().zip(&mut self.columns) {
+            if masked.contains(name) {
+                *column = JsonColumn::Masked;
+            }
+        }
+        self
+    }
+
+    /// Encode decimal values in `format`
+    ///
+    /// Like `with_max_binary_bytes`, applies to top-level decimal columns only.
+    fn with_decimal_format(mut self, format: types::DecimalFormat) -> Self {
+        if format == types::DecimalFormat::Numeric {
+            return self;
+        }
+        for column in &mut self.columns {
+            if let JsonColumn::Other(array) = column {
+                if matches!(
+                    array.data_type(),
+                    DataType::Decimal128(_, _) | DataType::Decimal256(_, _)
+                ) {
+                    *column = JsonColumn::Decimal(Arc::clone(array), format);
+                }
+            }
+        }
+        self
+    }
+
+    fn encode_row(&self, row_idx: usize) -> Result<Value, ConversionError> {
+        let mut json_map = Map::new();
+        for (name, column) in self.names.iter().zip(&self.columns) {
+            let value = column.value(row_idx).map_err(|e| {
+                ConversionError::new(e.code, format!("column \"{}\": {}", name, e.message))
+            })?;
+            json_map.insert(name.clone(), value);
+        }
+        Ok(Value::Object(json_map))
+    }
+}
+
+/// Convert the rows of a scan into JSONB rows, honoring an optional row limit
+fn scan_to_jsonb_rows(scan_iter: LanceScanIterator, limit: Option<i64>) -> Vec<(pgrx::JsonB,)> {
+    scan_to_jsonb_rows_with(scan_iter, limit, |encoder| encoder)
+}
+
+/// Like `scan_to_jsonb_rows`, passing each batch's encoder through `configure`
+fn scan_to_jsonb_rows_with(
+    scan_iter: LanceScanIterator,
+    limit: Option<i64>,
+    configure: impl Fn(BatchJsonEncoder) -> BatchJsonEncoder,
+) -> Vec<(pgrx::JsonB,)> {
+    let mut results = Vec::new();
+    let mut rows_outputted_count = 0i64;
+    let progress_interval = i64::from(guc::PROGRESS_INTERVAL.get());
+    let mut encoder: Option<BatchJsonEncoder> = None;
+
+    for row in scan_iter.rows() {
+        if let Some(l_pg) = limit {
+            if rows_outputted_count >= l_pg {
+                break;
+            }
+        }
+
+        let row = row.unwrap_or_else(|code| ereport!(ERROR, code, "Failed to read scanned row"));
+        // Every batch starts at row 0, so that is where the encoder is rebuilt
+        if row.row_idx() == 0 || encoder.is_none() {
+            encoder = Some(configure(BatchJsonEncoder::new(row.batch())));
+        }
+        let encoded = encoder.as_ref().map(|e| e.encode_row(row.row_idx()));
+        let value = match encoded.expect("encoder is initialized above") {
+            Ok(value) => value,
+            Err(e) => match guc::ON_ROW_ERROR.get() {
+                guc::OnRowError::Abort => ereport!(ERROR, e.code, e.message),
+                guc::OnRowError::Skip => {
+                    pgrx::warning!("Skipping row that could not be converted: {}", e.message);
+                    continue;
+                }
+            },
+        };
+        results.push((pgrx::JsonB(value),));
+        rows_outputted_count += 1;
+
+        if progress_interval > 0 && rows_outputted_count % progress_interval == 0 {
+            pgrx::notice!("pglance: {} rows processed", rows_outputted_count);
+        }
+    }
+
+    results
+}
+
+/// Convert scanned record batches into one JSONB array per batch, honoring an
+/// optional row limit across all batches
+fn batches_to_jsonb_batches(
+    batches: Vec<RecordBatch>,
+    limit: Option<i64>,
+) -> Vec<(i32, i32, pgrx::JsonB)> {
+    let mut results = Vec::new();
+    let mut remaining = limit;
+
+    for record_batch in batches {
+        if remaining.is_some_and(|r| r <= 0) {
+            break;
+        }
+        let rows: Vec<Value> =
+            scan_to_jsonb_rows(LanceScanIterator::new(vec![record_batch]), remaining)
+                .into_iter()
+                .map(|(row,)| row.0)
+                .collect();
+        if let Some(r) = remaining.as_mut() {
+            *r -= rows.len() as i64;
+        }
+        results.push((
+            results.len() as i32,
+            rows.len() as i32,
+            pgrx::JsonB(Value::Array(rows)),
+        ));
+    }
+
+    results
+}
+
+#[cfg(any(test, feature = "pg_test"))]
+#[pg_schema]
+mod tests {
+    use arrow::array::{BooleanArray, Float32Array, Int32Array, StringArray};
+    use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+    use arrow::record_batch::RecordBatch;
+    use lance::Dataset;
+    use pgrx::prelude::*;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    /// Test data generator for Lance tables using synchronous blocking operations
+    struct LanceTestDataGenerator {
+        temp_dir: TempDir,
+    }
+
+    impl LanceTestDataGenerator {
+        fn new() -> Result<Self, Box<dyn std::error::Error>> {
+            let temp_dir = TempDir::new()?;
+            Ok(Self { temp_dir })
+        }
+
+        fn get_base_path(&self) -> &std::path::Path {
+            self.temp_dir.path()
+        }
+
+        /// Create a simple table with basic data types
+        fn create_simple_table(&self) -> Result<std::path::PathBuf, Box<dyn std::error::Error>> {
+            let table_path = self.get_base_path().join("simple_table");
+
+            // Create sample data with various basic types
+            let id_array = Int32Array::from(vec![1, 2, 3, 4, 5]);
+            let name_array = StringArray::from(vec!["Alice", "Bob", "Charlie", "David", "Eve"]);
+            let age_array = Int32Array::from(vec![25, 30, 35, 40, 45]);
             let salary_array =
                 Float32Array::from(vec![50000.5, 65000.0, 80000.25, 95000.75, 120000.0]);
             let is_active_array = BooleanArray::from(vec![true, true, false, true, false]);
 
-            let schema = Arc::new(Schema::new(vec![
-                Field::new("id", DataType::Int32, false),
-                Field::new("name", DataType::Utf8, false),
-                Field::new("age", DataType::Int32, false),
-                Field::new("salary", DataType::Float32, false),
-                Field::new("is_active", DataType::Boolean, false),
-            ]));
+            let schema = Arc::new(Schema::new(vec![
+                Field::new("id", DataType::Int32, false),
+                Field::new("name", DataType::Utf8, false),
+                Field::new("age", DataType::Int32, false),
+                Field::new("salary", DataType::Float32, false),
+                Field::new("is_active", DataType::Boolean, false),
+            ]));
+
+            let batch = RecordBatch::try_new(
+                schema.clone(),
+                vec![
+                    Arc::new(id_array),
+                    Arc::new(name_array),
+                    Arc::new(age_array),
+                    Arc::new(salary_array),
+                    Arc::new(is_active_array),
+                ],
+            )?;
+
+            // Use RecordBatchIterator for lance
+            let reader = arrow::record_batch::RecordBatchIterator::new(vec![Ok(batch)], schema);
+
+            // Use a new runtime for async operation
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(async {
+                Dataset::write(reader, table_path.to_str().unwrap(), None).await
+            })?;
+
+            Ok(table_path)
+        }
+
+        /// Create a table with vector embeddings
+        fn create_vector_table(&self) -> Result<std::path::PathBuf, Box<dyn std::error::Error>> {
+            let table_path = self.get_base_path().join("vector_table");
+
+            let id_array = Int32Array::from(vec![1, 2, 3]);
+            let document_array = StringArray::from(vec!["doc1", "doc2", "doc3"]);
+
+            // Create vector embeddings as List array
+            let mut list_builder =
+                arrow::array::ListBuilder::new(arrow::array::Float32Builder::new());
+
+            // Add each embedding vector
+            for embedding in [
+                vec![0.1, 0.2, 0.3, 0.4],
+                vec![0.5, 0.6, 0.7, 0.8],
+                vec![0.9, 1.0, 1.1, 1.2],
+            ] {
+                for value in embedding {
+                    list_builder.values().append_value(value);
+                }
+                list_builder.append(true);
+            }
+            let list_array = list_builder.finish();
+
+            let schema = Arc::new(Schema::new(vec![
+                Field::new("id", DataType::Int32, false),
+                Field::new("document", DataType::Utf8, false),
+                Field::new(
+                    "embedding",
+                    DataType::List(Arc::new(Field::new("item", DataType::Float32, true))),
+                    false,
+                ),
+            ]));
+
+            let batch = RecordBatch::try_new(
+                schema.clone(),
+                vec![
+                    Arc::new(id_array),
+                    Arc::new(document_array),
+                    Arc::new(list_array),
+                ],
+            )?;
+
+            let reader = arrow::record_batch::RecordBatchIterator::new(vec![Ok(batch)], schema);
+
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(async {
+                Dataset::write(reader, table_path.to_str().unwrap(), None).await
+            })?;
+
+            Ok(table_path)
+        }
+
+        /// Write a single record batch as a new Lance table
+        fn write_table(
+            &self,
+            name: &str,
+            batch: RecordBatch,
+        ) -> Result<std::path::PathBuf, Box<dyn std::error::Error>> {
+            let table_path = self.get_base_path().join(name);
+            let schema = batch.schema();
+            let reader = arrow::record_batch::RecordBatchIterator::new(vec![Ok(batch)], schema);
+
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(async {
+                Dataset::write(reader, table_path.to_str().unwrap(), None).await
+            })?;
+
+            Ok(table_path)
+        }
+
+        /// Create a table with embeddings stored as a LargeList of floats
+        fn create_large_list_vector_table(
+            &self,
+        ) -> Result<std::path::PathBuf, Box<dyn std::error::Error>> {
+            let mut list_builder =
+                arrow::array::LargeListBuilder::new(arrow::array::Float32Builder::new());
+            for embedding in [vec![0.1, 0.2, 0.3], vec![0.4, 0.5, 0.6]] {
+                for value in embedding {
+                    list_builder.values().append_value(value);
+                }
+                list_builder.append(true);
+            }
+
+            let schema = Arc::new(Schema::new(vec![
+                Field::new("id", DataType::Int32, false),
+                Field::new(
+                    "embedding",
+                    DataType::LargeList(Arc::new(Field::new("item", DataType::Float32, true))),
+                    false,
+                ),
+            ]));
+            let batch = RecordBatch::try_new(
+                schema,
+                vec![
+                    Arc::new(Int32Array::from(vec![1, 2])),
+                    Arc::new(list_builder.finish()),
+                ],
+            )?;
+            self.write_table("large_list_vector_table", batch)
+        }
+
+        /// Create a table with a nullable float column
+        fn create_nullable_table(&self) -> Result<std::path::PathBuf, Box<dyn std::error::Error>> {
+            let schema = Arc::new(Schema::new(vec![
+                Field::new("id", DataType::Int32, false),
+                Field::new("score", DataType::Float64, true),
+            ]));
+            let batch = RecordBatch::try_new(
+                schema,
+                vec![
+                    Arc::new(Int32Array::from(vec![1, 2, 3, 4])),
+                    Arc::new(arrow::array::Float64Array::from(vec![
+                        Some(0.5),
+                        None,
+                        Some(1.5),
+                        None,
+                    ])),
+                ],
+            )?;
+            self.write_table("nullable_table", batch)
+        }
+
+        /// Create a table with a Duration(Microsecond) column
+        fn create_duration_table(&self) -> Result<std::path::PathBuf, Box<dyn std::error::Error>> {
+            let schema = Arc::new(Schema::new(vec![
+                Field::new("id", DataType::Int32, false),
+                Field::new(
+                    "elapsed",
+                    DataType::Duration(arrow::datatypes::TimeUnit::Microsecond),
+                    false,
+                ),
+            ]));
+            let batch = RecordBatch::try_new(
+                schema,
+                vec![
+                    Arc::new(Int32Array::from(vec![1, 2])),
+                    Arc::new(arrow::array::DurationMicrosecondArray::from(vec![
+                        1_500_000, 90_000_000,
+                    ])),
+                ],
+            )?;
+            self.write_table("duration_table", batch)
+        }
+
+        /// Create a table whose second row holds a timestamp chrono cannot represent
+        fn create_invalid_timestamp_table(
+            &self,
+        ) -> Result<std::path::PathBuf, Box<dyn std::error::Error>> {
+            let schema = Arc::new(Schema::new(vec![
+                Field::new("id", DataType::Int32, false),
+                Field::new(
+                    "created_at",
+                    DataType::Timestamp(arrow::datatypes::TimeUnit::Second, None),
+                    false,
+                ),
+            ]));
+            let batch = RecordBatch::try_new(
+                schema,
+                vec![
+                    Arc::new(Int32Array::from(vec![1, 2])),
+                    Arc::new(arrow::array::TimestampSecondArray::from(vec![0, i64::MAX])),
+                ],
+            )?;
+            self.write_table("invalid_timestamp_table", batch)
+        }
+
+        /// Create a single-row table with `num_columns` Int32 columns
+        fn create_wide_table(
+            &self,
+            num_columns: usize,
+        ) -> Result<std::path::PathBuf, Box<dyn std::error::Error>> {
+            let schema = Arc::new(Schema::new(
+                (0..num_columns)
+                    .map(|i| Field::new(format!("c{}", i), DataType::Int32, false))
+                    .collect::<Vec<_>>(),
+            ));
+            let columns = (0..num_columns)
+                .map(|i| Arc::new(Int32Array::from(vec![i as i32])) as arrow::array::ArrayRef)
+                .collect();
+            let batch = RecordBatch::try_new(schema, columns)?;
+            self.write_table("wide_table", batch)
+        }
+    }
+
+    /// Run `f` and return the error report it raises, if any
+    fn caught_error(f: impl FnOnce()) -> Option<pg_sys::panic::ErrorReportWithLevel> {
+        pgrx::PgTryBuilder::new(std::panic::AssertUnwindSafe(|| {
+            f();
+            None
+        }))
+        .catch_others(|e| match e {
+            pg_sys::panic::CaughtError::PostgresError(report)
+            | pg_sys::panic::CaughtError::ErrorReport(report)
+            | pg_sys::panic::CaughtError::RustPanic {
+                ereport: report, ..
+            } => Some(report),
+        })
+        .execute()
+    }
+
+    /// Run `f` and return the SQLSTATE of the error it raises, if any
+    fn caught_sqlstate(f: impl FnOnce()) -> Option<PgSqlErrorCode> {
+        caught_error(f).map(|report| report.sql_error_code())
+    }
+
+    #[pg_test]
+    fn test_hello_pglance() {
+        assert_eq!("Hello, pglance", crate::hello_pglance());
+    }
+
+    #[pg_test]
+    fn test_error_handling() {
+        // Test with invalid path
+        let result = std::panic::catch_unwind(|| {
+            let _: Vec<(String, String, bool)> =
+                crate::lance_table_info("/invalid/path/does/not/exist").collect::<Vec<_>>();
+        });
+        assert!(result.is_err());
+    }
+
+    #[pg_test]
+    fn test_simple_table_integration() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        // Test table info
+        let table_info: Vec<(String, String, bool)> =
+            crate::lance_table_info(table_path_str).collect::<Vec<_>>();
+
+        assert_eq!(table_info.len(), 5);
+
+        // Check specific columns
+        let id_column = table_info.iter().find(|(name, _, _)| name == "id").unwrap();
+        assert_eq!(id_column.1, "int4");
+        assert!(!id_column.2); // not nullable
+
+        let name_column = table_info
+            .iter()
+            .find(|(name, _, _)| name == "name")
+            .unwrap();
+        assert_eq!(name_column.1, "text");
+
+        let salary_column = table_info
+            .iter()
+            .find(|(name, _, _)| name == "salary")
+            .unwrap();
+        assert_eq!(salary_column.1, "float4");
+
+        // Test table stats
+        let stats: Vec<(i64, i64, i32)> =
+            crate::lance_table_stats(table_path_str).collect::<Vec<_>>();
+
+        assert_eq!(stats.len(), 1);
+        let (version, num_rows, num_columns) = stats[0];
+        assert!(version >= 1);
+        assert_eq!(num_rows, 5);
+        assert_eq!(num_columns, 5);
+
+        // Test data scanning
+        let data: Vec<(pgrx::JsonB,)> = crate::lance_scan_jsonb(
+            table_path_str,
+            Some(3),
+            None,
+            None,
+            None,
+            None,
+            false,
+            None,
+            None,
+            "numeric",
+            None,
+        )
+        .collect::<Vec<_>>();
+
+        assert_eq!(data.len(), 3);
+
+        // Verify first row data
+        let first_row = &data[0].0;
+        let json_value = &first_row.0;
+        assert_eq!(json_value["id"], 1);
+        assert_eq!(json_value["name"], "Alice");
+        assert_eq!(json_value["age"], 25);
+        // Use approximate comparison for floating point
+        let salary = json_value["salary"].as_f64().unwrap();
+        assert!((salary - 50000.5).abs() < 0.1);
+        assert_eq!(json_value["is_active"], true);
+    }
+
+    #[pg_test]
+    fn test_vector_table_integration() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_vector_table()
+            .expect("Failed to create vector table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        // Test table info
+        let table_info: Vec<(String, String, bool)> =
+            crate::lance_table_info(table_path_str).collect::<Vec<_>>();
+
+        assert_eq!(table_info.len(), 3);
+
+        // Check embedding column (should be a list type)
+        let embedding_column = table_info
+            .iter()
+            .find(|(name, _, _)| name == "embedding")
+            .unwrap();
+        assert!(embedding_column.1.contains("json")); // Lists are converted to JSON in PostgreSQL
+
+        // Test data scanning with limit
+        let data: Vec<(pgrx::JsonB,)> = crate::lance_scan_jsonb(
+            table_path_str,
+            Some(2),
+            None,
+            None,
+            None,
+            None,
+            false,
+            None,
+            None,
+            "numeric",
+            None,
+        )
+        .collect::<Vec<_>>();
+
+        assert_eq!(data.len(), 2);
+
+        // Verify first row has vector data
+        let first_row = &data[0].0;
+        let json_value = &first_row.0;
+        assert_eq!(json_value["id"], 1);
+        assert_eq!(json_value["document"], "doc1");
+
+        // Check that embedding is an array
+        assert!(json_value["embedding"].is_array());
+        let embedding = json_value["embedding"].as_array().unwrap();
+        assert_eq!(embedding.len(), 4);
+        // Use approximate comparison for floating point values
+        let val0 = embedding[0].as_f64().unwrap();
+        let val1 = embedding[1].as_f64().unwrap();
+        assert!((val0 - 0.1).abs() < 0.01);
+        assert!((val1 - 0.2).abs() < 0.01);
+    }
+
+    #[pg_test]
+    fn test_lance_lookup() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let rows: Vec<(pgrx::JsonB,)> = crate::lance_lookup(
+            table_path_str,
+            "id",
+            pgrx::JsonB(serde_json::json!(3)),
+            None,
+        )
+        .collect::<Vec<_>>();
+
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].0 .0["id"], 3);
+        assert_eq!(rows[0].0 .0["name"], "Charlie");
+
+        // Quotes in string values must not escape the literal
+        let rows: Vec<(pgrx::JsonB,)> = crate::lance_lookup(
+            table_path_str,
+            "name",
+            pgrx::JsonB(serde_json::json!("Bob' OR '1'='1")),
+            None,
+        )
+        .collect::<Vec<_>>();
+        assert!(rows.is_empty());
+    }
+
+    #[pg_test]
+    fn test_scan_jsonb_select_expressions() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let data: Vec<(pgrx::JsonB,)> = crate::lance_scan_jsonb(
+            table_path_str,
+            Some(2),
+            Some("age + 1 AS next_age, name"),
+            None,
+            None,
+            None,
+            false,
+            None,
+            None,
+            "numeric",
+            None,
+        )
+        .collect::<Vec<_>>();
+
+        assert_eq!(data.len(), 2);
+        let first_row = &data[0].0 .0;
+        assert_eq!(first_row["next_age"], 26);
+        assert_eq!(first_row["name"], "Alice");
+        assert!(first_row.get("age").is_none());
+
+        let result = std::panic::catch_unwind(|| {
+            crate::lance_scan_jsonb(
+                table_path_str,
+                None,
+                Some("age +* AS broken"),
+                None,
+                None,
+                None,
+                false,
+                None,
+                None,
+                "numeric",
+                None,
+            )
+            .collect::<Vec<_>>()
+        });
+        assert!(result.is_err());
+    }
+
+    #[pg_test]
+    fn test_on_row_error_skip() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_invalid_timestamp_table()
+            .expect("Failed to create invalid timestamp table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        // The default 'abort' mode keeps the JSON rendering of invalid timestamps
+        let data: Vec<(pgrx::JsonB,)> = crate::lance_scan_jsonb(
+            table_path_str,
+            None,
+            None,
+            None,
+            None,
+            None,
+            false,
+            None,
+            None,
+            "numeric",
+            None,
+        )
+        .collect::<Vec<_>>();
+        assert_eq!(data.len(), 2);
+        assert_eq!(data[1].0 .0["created_at"], "InvalidTimestamp");
+
+        Spi::run("SET pglance.on_row_error = 'skip'").unwrap();
+        let data: Vec<(pgrx::JsonB,)> = crate::lance_scan_jsonb(
+            table_path_str,
+            None,
+            None,
+            None,
+            None,
+            None,
+            false,
+            None,
+            None,
+            "numeric",
+            None,
+        )
+        .collect::<Vec<_>>();
+        Spi::run("RESET pglance.on_row_error").unwrap();
+
+        assert_eq!(data.len(), 1);
+        assert_eq!(data[0].0 .0["id"], 1);
+    }
+
+    #[pg_test]
+    fn test_storage_options_commit_store() {
+        let options = crate::scanner::OpenOptions::from_json(&serde_json::json!({
+            "ddb_table_name": "lance-commits",
+            "region": "us-east-1",
+            "allow_http": true,
+        }))
+        .expect("Failed to parse storage options");
+
+        assert_eq!(options.ddb_table_name.as_deref(), Some("lance-commits"));
+        assert!(!options.storage_options.contains_key("ddb_table_name"));
+        assert_eq!(options.storage_options["region"], "us-east-1");
+        assert_eq!(options.storage_options["allow_http"], "true");
+        assert_eq!(
+            options.resolve_uri("s3://bucket/table.lance").unwrap(),
+            "s3+ddb://bucket/table.lance?ddbTableName=lance-commits"
+        );
+        assert!(options.resolve_uri("/local/table.lance").is_err());
+
+        // The table name is a query parameter and must not break out of it
+        let options = crate::scanner::OpenOptions::from_json(&serde_json::json!({
+            "ddb_table_name": "commits&region=x y",
+        }))
+        .expect("Failed to parse storage options");
+        assert_eq!(
+            options.resolve_uri("s3://bucket/table.lance").unwrap(),
+            "s3+ddb://bucket/table.lance?ddbTableName=commits%26region%3Dx+y"
+        );
+
+        // Plain storage options pass through when opening a local table
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let data: Vec<(pgrx::JsonB,)> = crate::lance_scan_jsonb(
+            table_path.to_str().unwrap(),
+            Some(1),
+            None,
+            Some(pgrx::JsonB(serde_json::json!({ "region": "us-east-1" }))),
+            None,
+            None,
+            false,
+            None,
+            None,
+            "numeric",
+            None,
+        )
+        .collect::<Vec<_>>();
+        assert_eq!(data.len(), 1);
+    }
+
+    #[pg_test]
+    fn test_lance_arrow_schema_round_trip() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_vector_table()
+            .expect("Failed to create vector table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let schema_json = crate::lance_arrow_schema(table_path_str);
+        let parsed: Schema =
+            serde_json::from_str(&schema_json).expect("Failed to parse Arrow schema JSON");
+
+        let scanner = crate::scanner::LanceScanner::new(table_path_str).unwrap();
+        assert_eq!(&parsed, scanner.schema().as_ref());
+        assert_eq!(
+            parsed.field_with_name("embedding").unwrap().data_type(),
+            &DataType::List(Arc::new(Field::new("item", DataType::Float32, true)))
+        );
+    }
+
+    #[pg_test]
+    fn test_scanner_schema_is_cached() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+
+        let scanner = crate::scanner::LanceScanner::new(table_path.to_str().unwrap()).unwrap();
+        let first = scanner.schema();
+        let second = scanner.schema();
+        assert!(Arc::ptr_eq(&first, &second));
+
+        let stats = scanner.get_stats().unwrap();
+        assert!(Arc::ptr_eq(&first, &stats.schema));
+    }
+
+    #[pg_test]
+    fn test_scan_jsonb_params() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let rows: Vec<(pgrx::JsonB,)> = crate::lance_scan_jsonb_params(
+            table_path_str,
+            "name = $1",
+            vec![pgrx::JsonB(serde_json::json!("Bob"))],
+            None,
+            None,
+        )
+        .collect::<Vec<_>>();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].0 .0["id"], 2);
+
+        // A quoted payload stays a single literal
+        let rows: Vec<(pgrx::JsonB,)> = crate::lance_scan_jsonb_params(
+            table_path_str,
+            "name = $1 AND age > $2",
+            vec![
+                pgrx::JsonB(serde_json::json!("x' OR '1'='1")),
+                pgrx::JsonB(serde_json::json!(0)),
+            ],
+            None,
+            None,
+        )
+        .collect::<Vec<_>>();
+        assert!(rows.is_empty());
+
+        assert_eq!(
+            crate::scanner::substitute_params("name = '$1' AND id = $1", &[serde_json::json!(7)])
+                .unwrap(),
+            "name = '$1' AND id = 7"
+        );
+        assert!(crate::scanner::substitute_params("id = $2", &[serde_json::json!(1)]).is_err());
+    }
+
+    #[pg_test]
+    fn test_lance_table_times() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+
+        let query = format!(
+            "SELECT last_modified >= created_at \
+                AND created_at > now() - interval '1 hour' \
+                AND last_modified <= clock_timestamp() \
+             FROM lance_table_times('{}')",
+            table_path.to_str().unwrap()
+        );
+        let fresh = Spi::get_one::<bool>(&query).unwrap();
+        assert_eq!(fresh, Some(true));
+    }
+
+    #[pg_test]
+    fn test_scan_vectors_large_list() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_large_list_vector_table()
+            .expect("Failed to create large list vector table");
+
+        let vectors: Vec<(Option<Vec<Option<f32>>>,)> =
+            crate::lance_scan_vectors(table_path.to_str().unwrap(), "embedding", None, "null")
+                .collect::<Vec<_>>();
+
+        assert_eq!(vectors.len(), 2);
+        let first = vectors[0].0.as_ref().unwrap();
+        assert_eq!(first.len(), 3);
+        assert!((first[0].unwrap() - 0.1).abs() < 1e-6);
+        let second = vectors[1].0.as_ref().unwrap();
+        assert!((second[2].unwrap() - 0.6).abs() < 1e-6);
+    }
+
+    #[pg_test]
+    fn test_scan_jsonb_require_non_null() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_nullable_table()
+            .expect("Failed to create nullable table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let data: Vec<(pgrx::JsonB,)> = crate::lance_scan_jsonb(
+            table_path_str,
+            None,
+            None,
+            None,
+            None,
+            Some("score"),
+            false,
+            None,
+            None,
+            "numeric",
+            None,
+        )
+        .collect::<Vec<_>>();
+        assert_eq!(data.len(), 2);
+        assert!(data.iter().all(|(row,)| !row.0["score"].is_null()));
+
+        // Combined with a user filter using AND
+        let data: Vec<(pgrx::JsonB,)> = crate::lance_scan_jsonb(
+            table_path_str,
+            None,
+            None,
+            None,
+            Some("id > 1"),
+            Some("score"),
+            false,
+            None,
+            None,
+            "numeric",
+            None,
+        )
+        .collect::<Vec<_>>();
+        assert_eq!(data.len(), 1);
+        assert_eq!(data[0].0 .0["id"], 3);
+    }
+
+    #[pg_test]
+    fn test_lance_scan_debug() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+
+        let debug = crate::lance_scan_debug(
+            table_path.to_str().unwrap(),
+            Some("  age > 30 "),
+            Some(vec!["name".to_string(), "age".to_string()]),
+            Some(2),
+        )
+        .0;
+
+        assert_eq!(debug["filter"], "age > 30");
+        assert_eq!(debug["columns"], serde_json::json!(["name", "age"]));
+        assert_eq!(debug["limit"], 2);
+        assert!(debug.get("offset").is_none());
+        assert_eq!(debug["uses_index"], false);
+        assert!(!debug["plan"].as_str().unwrap().is_empty());
+
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            use lance_index::{scalar::ScalarIndexParams, DatasetIndexExt, IndexType};
+
+            let mut dataset = Dataset::open(table_path.to_str().unwrap()).await.unwrap();
+            dataset
+                .create_index(
+                    &["age"],
+                    IndexType::BTree,
+                    None,
+                    &ScalarIndexParams::default(),
+                    true,
+                )
+                .await
+                .unwrap();
+        });
+        let debug =
+            crate::lance_scan_debug(table_path.to_str().unwrap(), Some("age > 30"), None, None).0;
+        assert_eq!(debug["uses_index"], true);
+    }
+
+    #[pg_test]
+    fn test_datum_list_of_struct_is_jsonb() {
+        use arrow::array::{ArrayRef, ListArray, StructArray};
+        use arrow::buffer::OffsetBuffer;
+        use arrow::datatypes::Fields;
+
+        let struct_fields = Fields::from(vec![
+            Field::new("name", DataType::Utf8, false),
+            Field::new("score", DataType::Int32, false),
+        ]);
+        let structs = StructArray::new(
+            struct_fields.clone(),
+            vec![
+                Arc::new(StringArray::from(vec!["a", "b", "c"])) as ArrayRef,
+                Arc::new(Int32Array::from(vec![1, 2, 3])) as ArrayRef,
+            ],
+            None,
+        );
+        let list = ListArray::new(
+            Arc::new(Field::new("item", DataType::Struct(struct_fields), false)),
+            OffsetBuffer::new(vec![0, 2, 3].into()),
+            Arc::new(structs),
+            None,
+        );
+
+        assert_eq!(
+            crate::types::arrow_data_type_to_pg_type(list.data_type()).unwrap(),
+            pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::JSONBOID)
+        );
+
+        let datum = crate::arrow_value_to_datum(&list, 0)
+            .unwrap()
+            .expect("list value should not be NULL");
+        let json = unsafe { pgrx::JsonB::from_datum(datum, false) }.unwrap();
+        assert_eq!(
+            json.0,
+            serde_json::json!([{ "name": "a", "score": 1 }, { "name": "b", "score": 2 }])
+        );
+    }
+
+    #[pg_test]
+    fn test_lance_sample() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let sample: Vec<(pgrx::JsonB,)> =
+            crate::lance_sample(table_path_str, 0.4, None, Some(42)).collect::<Vec<_>>();
+        assert_eq!(sample.len(), 2);
+
+        // The same seed yields the same rows
+        let again: Vec<(pgrx::JsonB,)> =
+            crate::lance_sample(table_path_str, 0.4, None, Some(42)).collect::<Vec<_>>();
+        let ids = |rows: &[(pgrx::JsonB,)]| {
+            rows.iter()
+                .map(|(row,)| row.0["id"].clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(&sample), ids(&again));
+
+        let result = std::panic::catch_unwind(|| {
+            crate::lance_sample(table_path_str, 1.5, None, None).collect::<Vec<_>>()
+        });
+        assert!(result.is_err());
+    }
+
+    #[pg_test]
+    fn test_duration_type() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_duration_table()
+            .expect("Failed to create duration table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let table_info: Vec<(String, String, bool)> =
+            crate::lance_table_info(table_path_str).collect::<Vec<_>>();
+        let elapsed = table_info
+            .iter()
+            .find(|(name, _, _)| name == "elapsed")
+            .unwrap();
+        assert_eq!(elapsed.1, "interval");
+
+        let data: Vec<(pgrx::JsonB,)> = crate::lance_scan_jsonb(
+            table_path_str,
+            None,
+            None,
+            None,
+            None,
+            None,
+            false,
+            None,
+            None,
+            "numeric",
+            None,
+        )
+        .collect::<Vec<_>>();
+        assert_eq!(data[0].0 .0["elapsed"], "PT1.5S");
+        assert_eq!(data[1].0 .0["elapsed"], "PT90S");
+
+        let durations = arrow::array::DurationMicrosecondArray::from(vec![1_500_000]);
+        let datum = crate::arrow_value_to_datum(&durations, 0).unwrap().unwrap();
+        let interval = unsafe { pgrx::datum::Interval::from_datum(datum, false) }.unwrap();
+        assert_eq!(
+            interval,
+            pgrx::datum::Interval::new(0, 0, 1_500_000).unwrap()
+        );
+    }
+
+    #[pg_test]
+    fn test_lance_warmup() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        assert!(!crate::scanner::is_dataset_cached(table_path_str));
+        crate::lance_warmup(table_path_str);
+        assert!(crate::scanner::is_dataset_cached(table_path_str));
+    }
+
+    #[pg_test]
+    fn test_extension_type_mapping() {
+        let tensor_field = Field::new(
+            "embedding",
+            DataType::FixedSizeList(Arc::new(Field::new("item", DataType::Float32, true)), 4),
+            true,
+        )
+        .with_metadata(std::collections::HashMap::from([(
+            crate::types::EXTENSION_NAME_KEY.to_string(),
+            crate::types::FIXED_SHAPE_TENSOR_EXTENSION.to_string(),
+        )]));
+        let unknown_field = Field::new("label", DataType::Utf8, true).with_metadata(
+            std::collections::HashMap::from([(
+                crate::types::EXTENSION_NAME_KEY.to_string(),
+                "example.custom".to_string(),
+            )]),
+        );
+
+        assert_eq!(
+            crate::types::extension_name(&tensor_field),
+            Some(crate::types::FIXED_SHAPE_TENSOR_EXTENSION)
+        );
+
+        let schema = Schema::new(vec![tensor_field, unknown_field]);
+        let columns = arrow_schema_to_pg_columns(&schema);
+        assert_eq!(
+            columns[0].1,
+            pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::FLOAT4ARRAYOID)
+        );
+        assert_eq!(
+            columns[1].1,
+            pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::TEXTOID)
+        );
+    }
+
+    #[pg_test]
+    fn test_progress_interval() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        Spi::run("SET pglance.progress_interval = 2").unwrap();
+        let results: Vec<_> = crate::lance_scan_jsonb(
+            table_path_str,
+            None,
+            None,
+            None,
+            None,
+            None,
+            false,
+            None,
+            None,
+            "numeric",
+            None,
+        )
+        .collect();
+        Spi::run("RESET pglance.progress_interval").unwrap();
+
+        assert_eq!(results.len(), 5);
+        assert_eq!(results[0].0 .0["id"], 1);
+        assert_eq!(results[4].0 .0["id"], 5);
+    }
+
+    #[pg_test]
+    fn test_bool_list_is_bool_array() {
+        let mut list_builder = arrow::array::ListBuilder::new(arrow::array::BooleanBuilder::new());
+        list_builder.values().append_value(true);
+        list_builder.values().append_null();
+        list_builder.values().append_value(false);
+        list_builder.append(true);
+        list_builder.append(false);
+        let flags = list_builder.finish();
+
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "flags",
+            flags.data_type().clone(),
+            true,
+        )]));
+        let batch = RecordBatch::try_new(schema, vec![Arc::new(flags)]).unwrap();
+
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .write_table("bool_lists", batch)
+            .expect("Failed to create bool list table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let info: Vec<_> = crate::lance_table_info(table_path_str).collect();
+        assert_eq!(info[0].1, "boolean[]");
+
+        let scanner = crate::scanner::LanceScanner::new(table_path_str).unwrap();
+        let batches = scanner.scan_with_filter(None, None).unwrap().batches;
+        let column = batches[0].column(0);
+
+        let datum = crate::arrow_value_to_datum(column.as_ref(), 0)
+            .unwrap()
+            .expect("list value should not be NULL");
+        let values = unsafe { Vec::<Option<bool>>::from_datum(datum, false) }.unwrap();
+        assert_eq!(values, vec![Some(true), None, Some(false)]);
+
+        assert!(crate::arrow_value_to_datum(column.as_ref(), 1)
+            .unwrap()
+            .is_none());
+    }
+
+    #[pg_test]
+    fn test_lance_checksum() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let first = crate::lance_checksum(table_path_str, None);
+        let second = crate::lance_checksum(table_path_str, None);
+        assert_eq!(first, second);
+        assert_eq!(first.len(), 16);
+
+        let name_checksum = crate::lance_checksum(table_path_str, Some("name"));
+        assert_eq!(
+            name_checksum,
+            crate::lance_checksum(table_path_str, Some("name"))
+        );
+        assert_ne!(name_checksum, first);
+
+        let result = std::panic::catch_unwind(|| {
+            crate::lance_checksum(table_path_str, Some("missing"));
+        });
+        assert!(result.is_err());
+    }
+
+    #[pg_test]
+    fn test_storage_options_cache_sizes() {
+        let options = crate::scanner::OpenOptions::from_json(&serde_json::json!({
+            "metadata_cache_size": 1024,
+            "index_cache_size": "256",
+            "region": "us-east-1",
+        }))
+        .expect("Failed to parse storage options");
+
+        assert_eq!(options.metadata_cache_size, Some(1024));
+        assert_eq!(options.index_cache_size, Some(256));
+        assert!(!options.storage_options.contains_key("index_cache_size"));
+        assert_eq!(options.storage_options["region"], "us-east-1");
+
+        let result = crate::scanner::OpenOptions::from_json(&serde_json::json!({
+            "index_cache_size": "lots",
+        }));
+        assert!(matches!(
+            result,
+            Err(PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE)
+        ));
+
+        // Cache sizes are applied when the dataset is opened
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let data: Vec<(pgrx::JsonB,)> = crate::lance_scan_jsonb(
+            table_path.to_str().unwrap(),
+            None,
+            None,
+            Some(pgrx::JsonB(serde_json::json!({
+                "metadata_cache_size": 64,
+                "index_cache_size": 64,
+            }))),
+            None,
+            None,
+            false,
+            None,
+            None,
+            "numeric",
+            None,
+        )
+        .collect();
+        assert_eq!(data.len(), 5);
+    }
+
+    #[pg_test]
+    fn test_lance_scan_batches() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let batches: Vec<_> = crate::lance_scan_batches(table_path_str, None, None, None).collect();
+        let total: i32 = batches.iter().map(|(_, num_rows, _)| num_rows).sum();
+        assert_eq!(total, 5);
+        for (idx, (batch_index, num_rows, data)) in batches.iter().enumerate() {
+            assert_eq!(*batch_index, idx as i32);
+            assert_eq!(data.0.as_array().unwrap().len(), *num_rows as usize);
+        }
+
+        let limited: Vec<_> =
+            crate::lance_scan_batches(table_path_str, Some("age > 25"), Some(3), None).collect();
+        let total: i32 = limited.iter().map(|(_, num_rows, _)| num_rows).sum();
+        assert_eq!(total, 3);
+
+        // Batches never exceed the scanner's batch size
+        let scanner = crate::scanner::LanceScanner::new(table_path_str)
+            .unwrap()
+            .with_batch_size(2);
+        let batches = scanner.scan_with_filter(None, None).unwrap().batches;
+        assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 5);
+        assert!(batches.iter().all(|b| b.num_rows() <= 2));
+    }
+
+    #[pg_test]
+    fn test_lance_table_metadata() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+
+        let simple_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let metadata: Vec<_> = crate::lance_table_metadata(simple_path.to_str().unwrap()).collect();
+        assert!(metadata.is_empty());
+
+        let schema = Arc::new(
+            Schema::new(vec![Field::new("id", DataType::Int32, false)]).with_metadata(
+                std::collections::HashMap::from([
+                    ("owner".to_string(), "analytics".to_string()),
+                    ("source".to_string(), "events".to_string()),
+                ]),
+            ),
+        );
+        let batch =
+            RecordBatch::try_new(schema, vec![Arc::new(Int32Array::from(vec![1, 2]))]).unwrap();
+        let table_path = generator
+            .write_table("with_metadata", batch)
+            .expect("Failed to create table with metadata");
+
+        let metadata: Vec<_> = crate::lance_table_metadata(table_path.to_str().unwrap()).collect();
+        assert_eq!(
+            metadata,
+            vec![
+                ("owner".to_string(), "analytics".to_string()),
+                ("source".to_string(), "events".to_string()),
+            ]
+        );
+    }
+
+    #[pg_test]
+    fn test_scan_iterator_rows() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+
+        let scanner = crate::scanner::LanceScanner::new(table_path.to_str().unwrap())
+            .unwrap()
+            .with_batch_size(2);
+        let scan_iter = scanner.scan_with_filter(None, None).unwrap();
+        assert!(scan_iter.batches.len() > 1);
+
+        let ids: Vec<i32> = scan_iter
+            .rows()
+            .map(|row| {
+                let row = row.expect("Failed to read row");
+                row.batch()
+                    .column_by_name("id")
+                    .unwrap()
+                    .as_any()
+                    .downcast_ref::<Int32Array>()
+                    .unwrap()
+                    .value(row.row_idx())
+            })
+            .collect();
+        assert_eq!(ids, vec![1, 2, 3, 4, 5]);
+
+        let mut scan_iter = scanner
+            .scan_with_filter(Some("id > 4".into()), None)
+            .unwrap();
+        assert!(scan_iter.next_row().unwrap().is_ok());
+        assert!(scan_iter.next_row().is_none());
+    }
+
+    #[pg_test]
+    fn test_mismatched_array_type_is_error() {
+        let timestamps = arrow::array::TimestampMillisecondArray::from(vec![1_000i64]);
+
+        // Reading with a unit that disagrees with the physical array must not panic
+        let err = crate::arrow_timestamp_value(&timestamps, &TimeUnit::Second, 0).unwrap_err();
+        assert_eq!(err.code, PgSqlErrorCode::ERRCODE_DATATYPE_MISMATCH);
+        assert_eq!(
+            err.message,
+            "expected TimestampSecondArray for data type Timestamp(Millisecond, None)"
+        );
+
+        let err =
+            crate::types::downcast_array::<StringArray>(&Int32Array::from(vec![1]), "StringArray")
+                .unwrap_err();
+        assert_eq!(err.code, PgSqlErrorCode::ERRCODE_DATATYPE_MISMATCH);
+        assert_eq!(err.message, "expected StringArray for data type Int32");
+    }
+
+    #[pg_test]
+    fn test_lance_quantiles() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let quantiles = crate::lance_quantiles(table_path_str, "age", vec![0.0, 0.5, 1.0]);
+        let median = quantiles.0["0.5"].as_f64().unwrap();
+        assert!((median - 35.0).abs() <= 5.0);
+        assert_eq!(quantiles.0["0"].as_f64().unwrap(), 25.0);
+        assert_eq!(quantiles.0["1"].as_f64().unwrap(), 45.0);
+
+        let result = std::panic::catch_unwind(|| {
+            crate::lance_quantiles(table_path_str, "name", vec![0.5]);
+        });
+        assert!(result.is_err());
+
+        let result = std::panic::catch_unwind(|| {
+            crate::lance_quantiles(table_path_str, "age", vec![1.5]);
+        });
+        assert!(result.is_err());
+    }
+
+    #[pg_test]
+    fn test_lance_scan_in() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let rows: Vec<_> = crate::lance_scan_in(
+            table_path_str,
+            "id",
+            vec![
+                pgrx::JsonB(serde_json::json!(1)),
+                pgrx::JsonB(serde_json::json!(4)),
+            ],
+            None,
+            None,
+        )
+        .collect();
+        let ids: Vec<i64> = rows
+            .iter()
+            .map(|(row,)| row.0["id"].as_i64().unwrap())
+            .collect();
+        assert_eq!(ids, vec![1, 4]);
+
+        let rows: Vec<_> = crate::lance_scan_in(table_path_str, "id", vec![], None, None).collect();
+        assert!(rows.is_empty());
+
+        let predicate = crate::scanner::in_list_predicate(
+            "id",
+            &(0..2500).map(|i| serde_json::json!(i)).collect::<Vec<_>>(),
+            &DataType::Int32,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(predicate.matches(" IN (").count(), 3);
+    }
+
+    #[pg_test]
+    fn test_timestamp_json_format_epoch_micros() {
+        let timestamps =
+            arrow::array::TimestampMicrosecondArray::from(vec![1_700_000_000_123_456i64]);
+        let dates = arrow::array::Date32Array::from(vec![1]);
+
+        assert_eq!(
+            crate::arrow_value_to_serde_json(&timestamps, 0).unwrap(),
+            serde_json::json!("2023-11-14 22:13:20.123456")
+        );
+
+        Spi::run("SET pglance.timestamp_json_format = 'epoch_micros'").unwrap();
+        let timestamp = crate::arrow_value_to_serde_json(&timestamps, 0).unwrap();
+        let date = crate::arrow_value_to_serde_json(&dates, 0).unwrap();
+        Spi::run("RESET pglance.timestamp_json_format").unwrap();
+
+        assert_eq!(timestamp, serde_json::json!(1_700_000_000_123_456i64));
+        assert_eq!(date, serde_json::json!(86_400_000_000i64));
+    }
+
+    #[pg_test]
+    fn test_storage_options_http() {
+        let options = crate::scanner::OpenOptions::from_json(&serde_json::json!({
+            "http_header.Authorization": "Bearer secret",
+        }))
+        .expect("Failed to parse storage options");
+        assert_eq!(options.http_headers["Authorization"], "Bearer secret");
+        assert!(options.storage_options.is_empty());
+
+        let location = crate::scanner::http_location("https://data.example.com/tables/t.lance")
+            .unwrap()
+            .expect("https URIs should be recognized");
+        assert_eq!(location.host_str(), Some("data.example.com"));
+        assert!(crate::scanner::http_location("/tmp/table.lance")
+            .unwrap()
+            .is_none());
+        assert!(matches!(
+            crate::scanner::http_location("http://"),
+            Err(PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE)
+        ));
+    }
+
+    /// Serve the files under `root` over HTTP on a local port, returning its base URL
+    ///
+    /// Supports what the object store's HTTP client needs to read a table:
+    /// `HEAD`, ranged `GET` and WebDAV `PROPFIND` listings. Anything else,
+    /// and every missing path, is answered with a 404.
+    fn serve_over_http(root: std::path::PathBuf) -> String {
+        use std::io::{BufRead, BufReader, Read, Write};
+
+        const LAST_MODIFIED: &str = "Mon, 01 Jan 2024 00:00:00 GMT";
+
+        fn propfind_entry(href: &str, path: &std::path::Path) -> String {
+            let (resource_type, length) = if path.is_dir() {
+                ("<D:collection/>", 0)
+            } else {
+                ("", std::fs::metadata(path).map(|m| m.len()).unwrap_or(0))
+            };
+            format!(
+                "<D:response><D:href>{}</D:href><D:propstat><D:prop>\
+                 <D:getlastmodified>{}</D:getlastmodified>\
+                 <D:getcontentlength>{}</D:getcontentlength>\
+                 <D:resourcetype>{}</D:resourcetype>\
+                 </D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>",
+                href, LAST_MODIFIED, length, resource_type
+            )
+        }
+
+        fn list(href: &str, path: &std::path::Path, recursive: bool, out: &mut String) {
+            let Ok(entries) = std::fs::read_dir(path) else {
+                return;
+            };
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                let child = entry.path();
+                if child.is_dir() {
+                    let child_href = format!("{}{}/", href, name);
+                    out.push_str(&propfind_entry(&child_href, &child));
+                    if recursive {
+                        list(&child_href, &child, recursive, out);
+                    }
+                } else {
+                    out.push_str(&propfind_entry(&format!("{}{}", href, name), &child));
+                }
+            }
+        }
+
+        fn respond(
+            stream: &mut std::net::TcpStream,
+            root: &std::path::Path,
+        ) -> std::io::Result<()> {
+            let mut reader = BufReader::new(stream.try_clone()?);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line)?;
+            let mut parts = request_line.split_whitespace();
+            let method = parts.next().unwrap_or_default().to_string();
+            let target = parts.next().unwrap_or("/").to_string();
+            let mut headers = std::collections::HashMap::new();
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line)? == 0 || line == "\r\n" {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+                }
+            }
+            let body_len: usize = headers
+                .get("content-length")
+                .and_then(|len| len.parse().ok())
+                .unwrap_or(0);
+            reader.read_exact(&mut vec![0; body_len])?;
+
+            let path = root.join(target.trim_start_matches('/').trim_end_matches('/'));
+            let (status, extra_headers, body) = match method.as_str() {
+                "PROPFIND" if path.exists() => {
+                    let href = if path.is_dir() && !target.ends_with('/') {
+                        format!("{}/", target)
+                    } else {
+                        target.clone()
+                    };
+                    let mut body = String::from(
+                        "<?xml version=\"1.0\" encoding=\"utf-8\"?><D:multistatus xmlns:D=\"DAV:\">",
+                    );
+                    body.push_str(&propfind_entry(&href, &path));
+                    if path.is_dir() {
+                        let recursive = headers.get("depth").map(String::as_str) != Some("1");
+                        list(&href, &path, recursive, &mut body);
+                    }
+                    body.push_str("</D:multistatus>");
+                    ("207 Multi-Status", String::new(), body.into_bytes())
+                }
+                "GET" | "HEAD" if path.is_file() => {
+                    let data = std::fs::read(&path)?;
+                    let total = data.len();
+                    let range = headers
+                        .get("range")
+                        .and_then(|range| range.strip_prefix("bytes="))
+                        .and_then(|range| range.split_once('-'))
+                        .map(
+                            |(start, end)| match (start.parse::<usize>(), end.parse::<usize>()) {
+                                (Ok(start), Ok(end)) => (start, (end + 1).min(total)),
+                                (Ok(start), Err(_)) => (start, total),
+                                (Err(_), Ok(suffix)) => (total.saturating_sub(suffix), total),
+                                _ => (0, total),
+                            },
+                        );
+                    let meta = format!(
+                        "Last-Modified: {}\r\nETag: \"{}\"\r\n",
+                        LAST_MODIFIED, total
+                    );
+                    match range {
+                        Some((start, end)) => (
+                            "206 Partial Content",
+                            format!(
+                                "{}Content-Range: bytes {}-{}/{}\r\n",
+                                meta,
+                                start,
+                                end - 1,
+                                total
+                            ),
+                            data[start..end].to_vec(),
+                        ),
+                        None => ("200 OK", meta, data),
+                    }
+                }
+                _ => ("404 Not Found", String::new(), Vec::new()),
+            };
+            write!(
+                stream,
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n",
+                status,
+                body.len(),
+                extra_headers
+            )?;
+            if method != "HEAD" {
+                stream.write_all(&body)?;
+            }
+            stream.flush()
+        }
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let root = root.clone();
+                std::thread::spawn(move || {
+                    let mut stream = stream;
+                    let _ = respond(&mut stream, &root);
+                });
+            }
+        });
+        format!("http://{}", address)
+    }
+
+    #[pg_test]
+    fn test_open_table_over_http() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let table_name = table_path.file_name().unwrap().to_str().unwrap();
+        let base_url = serve_over_http(generator.get_base_path().to_path_buf());
+        let url = format!("{}/{}", base_url, table_name);
+
+        let rows: Vec<_> = crate::lance_scan_jsonb(
+            &url, None, None, None, None, None, false, None, None, "numeric", None,
+        )
+        .map(|(pgrx::JsonB(row),)| row)
+        .collect();
+        assert_eq!(rows.len(), 5);
+        assert_eq!(rows[0]["name"], "Alice");
+
+        let missing = crate::scanner::LanceScanner::new(&format!("{}/missing", base_url));
+        assert!(matches!(
+            missing,
+            Err(PgSqlErrorCode::ERRCODE_UNDEFINED_FILE)
+        ));
+
+        // A cached handle looks for newer versions, and finds the table gone
+        std::fs::remove_dir_all(&table_path).unwrap();
+        assert!(matches!(
+            crate::scanner::LanceScanner::new(&url),
+            Err(PgSqlErrorCode::ERRCODE_UNDEFINED_FILE)
+        ));
+    }
+
+    #[pg_test]
+    fn test_batch_json_encoder_matches_row_conversion() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("flag", DataType::Boolean, true),
+            Field::new("small", DataType::UInt8, true),
+            Field::new("id", DataType::Int64, true),
+            Field::new("score", DataType::Float32, true),
+            Field::new("ratio", DataType::Float64, true),
+            Field::new("name", DataType::Utf8, true),
+            Field::new("day", DataType::Date32, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(BooleanArray::from(vec![Some(true), None, Some(false)])),
+                Arc::new(arrow::array::UInt8Array::from(vec![
+                    Some(1),
+                    Some(255),
+                    None,
+                ])),
+                Arc::new(arrow::array::Int64Array::from(vec![
+                    None,
+                    Some(-2),
+                    Some(3),
+                ])),
+                Arc::new(Float32Array::from(vec![Some(0.5), Some(f32::NAN), None])),
+                Arc::new(arrow::array::Float64Array::from(vec![
+                    Some(1.25),
+                    None,
+                    Some(-3.5),
+                ])),
+                Arc::new(StringArray::from(vec![Some("a"), None, Some("c")])),
+                Arc::new(arrow::array::Date32Array::from(vec![
+                    Some(0),
+                    Some(1),
+                    None,
+                ])),
+            ],
+        )
+        .unwrap();
+
+        let encoder = crate::BatchJsonEncoder::new(&batch);
+        for row_idx in 0..batch.num_rows() {
+            assert_eq!(
+                encoder.encode_row(row_idx).unwrap(),
+                crate::record_batch_row_to_json(&batch, row_idx).unwrap()
+            );
+        }
+    }
+
+    #[pg_test]
+    fn test_lance_group_count() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let groups: Vec<(serde_json::Value, i64)> =
+            crate::lance_group_count(table_path_str, "is_active", None)
+                .map(|(value, count)| (value.0, count))
+                .collect();
+        assert_eq!(
+            groups,
+            vec![(serde_json::json!(true), 3), (serde_json::json!(false), 2)]
+        );
+
+        let top: Vec<_> = crate::lance_group_count(table_path_str, "is_active", Some(1)).collect();
+        assert_eq!(top.len(), 1);
+        assert_eq!(top[0].1, 3);
+    }
+
+    #[pg_test]
+    fn test_consistency_latest_sees_appends() {
+        use crate::scanner::{LanceScanner, OpenOptions, ReadConsistency};
+
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        // Prime the handle cache with version 1
+        let scanner = LanceScanner::new(table_path_str).unwrap();
+        assert_eq!(scanner.get_stats().unwrap().num_rows, 5);
+
+        let batch = scanner.scan_with_filter(None, None).unwrap().batches[0].clone();
+        let reader =
+            arrow::record_batch::RecordBatchIterator::new(vec![Ok(batch.clone())], batch.schema());
+        let params = lance::dataset::WriteParams {
+            mode: lance::dataset::WriteMode::Append,
+            ..Default::default()
+        };
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(Dataset::write(reader, table_path_str, Some(params)))
+            .expect("Failed to append rows");
+
+        let latest = OpenOptions::from_json(&serde_json::json!({ "consistency": "latest" }))
+            .expect("Failed to parse storage options");
+        assert_eq!(latest.consistency, ReadConsistency::Latest);
+        let stats = LanceScanner::open(table_path_str, &latest)
+            .unwrap()
+            .get_stats()
+            .unwrap();
+        assert_eq!(stats.version, 2);
+        assert_eq!(stats.num_rows, 10);
+
+        let pinned = OpenOptions::from_json(&serde_json::json!({ "consistency": "version-1" }))
+            .expect("Failed to parse storage options");
+        assert_eq!(pinned.consistency, ReadConsistency::Version(1));
+        let stats = LanceScanner::open(table_path_str, &pinned)
+            .unwrap()
+            .get_stats()
+            .unwrap();
+        assert_eq!(stats.version, 1);
+        assert_eq!(stats.num_rows, 5);
+
+        assert!(OpenOptions::from_json(&serde_json::json!({ "consistency": "eventual" })).is_err());
+    }
+
+    #[pg_test]
+    fn test_lance_ddl() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_vector_table()
+            .expect("Failed to create vector table");
+
+        let ddl = crate::lance_ddl(table_path.to_str().unwrap(), "documents");
+        assert!(ddl.starts_with("CREATE TABLE documents ("));
+        assert!(ddl.contains("id int4 NOT NULL"));
+        assert!(ddl.contains("embedding jsonb"));
+
+        // The generated statement is valid PostgreSQL
+        Spi::run(&ddl).expect("Generated DDL should execute");
+    }
+
+    #[pg_test]
+    fn test_batch_size_larger_than_table() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+
+        let scanner = crate::scanner::LanceScanner::new(table_path.to_str().unwrap())
+            .unwrap()
+            .with_batch_size(100_000);
+        assert_eq!(scanner.effective_batch_size(), 5);
+
+        let batches = scanner.scan_with_filter(None, None).unwrap().batches;
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].num_rows(), 5);
+    }
+
+    #[pg_test]
+    fn test_storage_options_session_token() {
+        let options = crate::scanner::OpenOptions::from_json(&serde_json::json!({
+            "aws_access_key_id": "ASIAEXAMPLE",
+            "aws_secret_access_key": "example-secret",
+            "aws_session_token": "example-session-token",
+            "aws_session_token_expiry": "2999-01-01T00:00:00Z",
+        }))
+        .expect("Failed to parse storage options");
+
+        assert_eq!(
+            options.storage_options["aws_session_token"],
+            "example-session-token"
+        );
+        assert!(!options
+            .storage_options
+            .contains_key("aws_session_token_expiry"));
+        assert!(options.check_credentials().is_ok());
+
+        let debug = format!("{:?}", options);
+        assert!(!debug.contains("example-session-token"));
+        assert!(!debug.contains("example-secret"));
+
+        let expired = crate::scanner::OpenOptions::from_json(&serde_json::json!({
+            "aws_session_token": "example-session-token",
+            "aws_session_token_expiry": "2000-01-01T00:00:00Z",
+        }))
+        .expect("Failed to parse storage options");
+        assert!(matches!(
+            crate::scanner::LanceScanner::open("s3://bucket/table.lance", &expired),
+            Err(PgSqlErrorCode::ERRCODE_INVALID_AUTHORIZATION_SPECIFICATION)
+        ));
+
+        let redacted = crate::scanner::redact_storage_options(&serde_json::json!({
+            "aws_session_token": "example-session-token",
+            "region": "us-east-1",
+        }));
+        assert_eq!(redacted["aws_session_token"], "***");
+        assert_eq!(redacted["region"], "us-east-1");
+    }
+
+    #[pg_test]
+    fn test_lance_head_and_tail() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let ids = |rows: Vec<(pgrx::JsonB,)>| -> Vec<i64> {
+            rows.iter()
+                .map(|(row,)| row.0["id"].as_i64().unwrap())
+                .collect()
+        };
+
+        assert_eq!(
+            ids(crate::lance_head(table_path_str, 2).collect()),
+            vec![1, 2]
+        );
+        assert_eq!(
+            ids(crate::lance_tail(table_path_str, 2).collect()),
+            vec![4, 5]
+        );
+
+        // Asking for more rows than the table has returns all of them
+        assert_eq!(
+            ids(crate::lance_head(table_path_str, 10).collect()).len(),
+            5
+        );
+        assert_eq!(
+            ids(crate::lance_tail(table_path_str, 10).collect()),
+            vec![1, 2, 3, 4, 5]
+        );
+        assert!(crate::lance_tail(table_path_str, 0).next().is_none());
+    }
+
+    #[pg_test]
+    fn test_lance_fragments_deletions() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let fragments: Vec<_> = crate::lance_fragments(table_path_str).collect();
+        assert!(!fragments.is_empty());
+        assert!(fragments.iter().all(|f| !f.4 && f.5.is_none()));
+
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let mut dataset = Dataset::open(table_path_str).await.unwrap();
+            dataset.delete("id = 1").await.unwrap();
+        });
+
+        let fragments: Vec<_> = crate::lance_fragments(table_path_str).collect();
+        let deleted: Vec<_> = fragments.iter().filter(|f| f.4).collect();
+        assert_eq!(deleted.len(), 1);
+        assert_eq!(deleted[0].3, Some(1));
+        assert!(deleted[0].5.as_ref().unwrap().starts_with("_deletions/"));
+    }
+
+    #[pg_test]
+    fn test_time_type() {
+        let times = arrow::array::Time32SecondArray::from(vec![Some(3_661), None, Some(90_000)]);
+
+        let datum = crate::arrow_value_to_datum(&times, 0)
+            .unwrap()
+            .expect("time value should not be NULL");
+        let time = unsafe { pgrx::datum::Time::from_datum(datum, false) }.unwrap();
+        assert_eq!(time, pgrx::datum::Time::new(1, 1, 1.0).unwrap());
+
+        assert!(crate::arrow_value_to_datum(&times, 1).unwrap().is_none());
+
+        let err = crate::arrow_value_to_datum(&times, 2).unwrap_err();
+        assert_eq!(
+            err.code,
+            PgSqlErrorCode::ERRCODE_DATETIME_VALUE_OUT_OF_RANGE
+        );
+
+        let nanos = arrow::array::Time64NanosecondArray::from(vec![45_296_789_000_000]);
+        let datum = crate::arrow_value_to_datum(&nanos, 0).unwrap().unwrap();
+        let time = unsafe { pgrx::datum::Time::from_datum(datum, false) }.unwrap();
+        assert_eq!(time, pgrx::datum::Time::new(12, 34, 56.789).unwrap());
+
+        // Less than a microsecond before midnight is still outside the day
+        let negative = arrow::array::Time64NanosecondArray::from(vec![-999]);
+        let err = crate::arrow_value_to_datum(&negative, 0).unwrap_err();
+        assert_eq!(
+            err.code,
+            PgSqlErrorCode::ERRCODE_DATETIME_VALUE_OUT_OF_RANGE
+        );
+    }
+
+    #[pg_test]
+    fn test_lance_scan_text() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+
+        let rows: Vec<_> =
+            crate::lance_scan_text(table_path.to_str().unwrap(), Some(2), None).collect();
+        assert_eq!(rows.len(), 2);
+        for (values,) in &rows {
+            assert_eq!(values.len(), 5);
+        }
+        assert_eq!(rows[0].0[0].as_deref(), Some("1"));
+        assert_eq!(rows[0].0[1].as_deref(), Some("Alice"));
+        assert_eq!(rows[0].0[4].as_deref(), Some("true"));
+
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_nullable_table()
+            .expect("Failed to create nullable table");
+        let rows: Vec<_> =
+            crate::lance_scan_text(table_path.to_str().unwrap(), None, None).collect();
+        assert_eq!(rows[1].0[1], None);
+    }
+
+    #[pg_test]
+    fn test_lance_scan_typed() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+
+        let count = Spi::get_one::<i64>(&format!(
+            "SELECT count(*) FROM lance_scan_typed('{}') \
+             AS t(id int4, name text, age int4, salary float4, is_active bool)",
+            table_path.display()
+        ))
+        .expect("typed scan failed");
+        assert_eq!(count, Some(5));
+
+        let name = Spi::get_one::<String>(&format!(
+            "SELECT name FROM lance_scan_typed('{}', 1) \
+             AS t(id int4, name text, age int4, salary float4, is_active bool)",
+            table_path.display()
+        ))
+        .expect("typed scan failed");
+        assert_eq!(name.as_deref(), Some("Alice"));
+    }
+
+    #[pg_test]
+    fn test_wide_schema() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_wide_table(2000)
+            .expect("Failed to create wide table");
+
+        let columns: Vec<_> = crate::lance_table_info(table_path.to_str().unwrap()).collect();
+        assert_eq!(columns.len(), 2000);
+
+        let rows: Vec<_> = crate::lance_scan_jsonb(
+            table_path.to_str().unwrap(),
+            None,
+            None,
+            None,
+            None,
+            None,
+            false,
+            None,
+            None,
+            "numeric",
+            None,
+        )
+        .collect();
+        assert_eq!(rows.len(), 1);
+
+        let scanner = crate::scanner::LanceScanner::new(table_path.to_str().unwrap())
+            .expect("Failed to open wide table");
+        let err = crate::typed::check_typed_column_count(&scanner.schema())
+            .expect_err("wide schema should not fit a typed row");
+        assert_eq!(err.code, PgSqlErrorCode::ERRCODE_TOO_MANY_COLUMNS);
+        assert!(err.message.contains("2000"));
+
+        // The typed scan itself refuses the table before building any tuple
+        let caught = caught_sqlstate(|| {
+            Spi::run(&format!(
+                "SELECT count(*) FROM lance_scan_typed('{}') AS t(c0 int4)",
+                table_path.display()
+            ))
+            .ok();
+        });
+        assert_eq!(caught, Some(PgSqlErrorCode::ERRCODE_TOO_MANY_COLUMNS));
+    }
+
+    #[pg_test]
+    fn test_stats_cache_ttl() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let path = table_path.to_str().unwrap();
+
+        Spi::run("SET pglance.stats_cache_ttl_seconds = 1").unwrap();
+        let before = crate::scanner::stats_refresh_count();
+        let first: Vec<_> = crate::lance_table_stats(path).collect();
+        let second: Vec<_> = crate::lance_table_stats(path).collect();
+        assert_eq!(first, second);
+        assert_eq!(crate::scanner::stats_refresh_count(), before + 1);
+
+        std::thread::sleep(std::time::Duration::from_millis(1100));
+        let third: Vec<_> = crate::lance_table_stats(path).collect();
+        assert_eq!(third, first);
+        assert_eq!(crate::scanner::stats_refresh_count(), before + 2);
+
+        Spi::run("SET pglance.stats_cache_ttl_seconds = 0").unwrap();
+        let _: Vec<_> = crate::lance_table_stats(path).collect();
+        assert_eq!(crate::scanner::stats_refresh_count(), before + 3);
+    }
+
+    #[pg_test]
+    fn test_nested_dictionary_to_json() {
+        use arrow::array::{Array, ArrayRef, DictionaryArray, ListArray, StructArray};
+        use arrow::buffer::OffsetBuffer;
+        use arrow::datatypes::Int32Type;
+
+        let dictionary: DictionaryArray<Int32Type> = vec!["a", "b", "a", "c"].into_iter().collect();
+        let item = Arc::new(Field::new("item", dictionary.data_type().clone(), true));
+        let list = ListArray::new(
+            item,
+            OffsetBuffer::new(vec![0, 1, 4].into()),
+            Arc::new(dictionary),
+            None,
+        );
+        assert_eq!(
+            crate::arrow_value_to_serde_json(&list, 0).unwrap(),
+            serde_json::json!(["a"])
+        );
+        assert_eq!(
+            crate::arrow_value_to_serde_json(&list, 1).unwrap(),
+            serde_json::json!(["b", "a", "c"])
+        );
+
+        let dictionary: DictionaryArray<Int32Type> =
+            vec![Some("x"), None, Some("y")].into_iter().collect();
+        let structs = StructArray::from(vec![(
+            Arc::new(Field::new("d", dictionary.data_type().clone(), true)),
+            Arc::new(dictionary) as ArrayRef,
+        )]);
+        assert_eq!(
+            crate::arrow_value_to_serde_json(&structs, 1).unwrap(),
+            serde_json::json!({"d": null})
+        );
+        assert_eq!(
+            crate::arrow_value_to_serde_json(&structs, 2).unwrap(),
+            serde_json::json!({"d": "y"})
+        );
+    }
+
+    #[pg_test]
+    fn test_lance_projected_schema() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+
+        let columns: Vec<_> = crate::lance_projected_schema(
+            table_path.to_str().unwrap(),
+            vec!["salary".to_string(), "id".to_string()],
+        )
+        .collect();
+        assert_eq!(
+            columns,
+            vec![
+                ("salary".to_string(), "float4".to_string(), false),
+                ("id".to_string(), "int4".to_string(), false),
+            ]
+        );
+
+        let result = std::panic::catch_unwind(|| {
+            crate::lance_projected_schema(table_path.to_str().unwrap(), vec!["missing".to_string()])
+                .count()
+        });
+        assert!(result.is_err());
+    }
+
+    #[pg_test]
+    fn test_io_threads() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let path = table_path.to_str().unwrap();
+
+        let scanner = crate::scanner::LanceScanner::new(path).unwrap();
+        assert_eq!(scanner.io_threads(), None);
+
+        assert!(!scanner.object_store_description().contains("LimitStore"));
+
+        Spi::run("SET pglance.io_threads = 2").unwrap();
+        let scanner = crate::scanner::LanceScanner::new(path).unwrap();
+        assert_eq!(scanner.io_threads(), Some(2));
+        // The object store the table is read through is bounded too
+        assert!(scanner
+            .object_store_description()
+            .starts_with("LimitStore(2,"));
+        let rows: Vec<_> = crate::lance_scan_jsonb(
+            path, None, None, None, None, None, false, None, None, "numeric", None,
+        )
+        .collect();
+        assert_eq!(rows.len(), 5);
+
+        Spi::run("RESET pglance.io_threads").unwrap();
+    }
+
+    #[pg_test]
+    fn test_lance_version_diff() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(async {
+                let mut dataset = Dataset::open(table_path_str).await?;
+                dataset
+                    .add_columns(
+                        lance::dataset::NewColumnTransform::SqlExpressions(vec![(
+                            "bonus".to_string(),
+                            "salary * 0.1".to_string(),
+                        )]),
+                        None,
+                        None,
+                    )
+                    .await?;
+                dataset.delete("id = 5").await
+            })
+            .expect("Failed to change table");
+
+        let pgrx::JsonB(diff) = crate::lance_version_diff(table_path_str, 1, 3);
+        assert_eq!(diff["added_columns"], serde_json::json!(["bonus"]));
+        assert_eq!(diff["removed_columns"], serde_json::json!([]));
+        assert_eq!(diff["renamed_columns"], serde_json::json!([]));
+        assert_eq!(diff["row_count_delta"], serde_json::json!(-1));
+
+        let pgrx::JsonB(diff) = crate::lance_version_diff(table_path_str, 2, 1);
+        assert_eq!(diff["removed_columns"], serde_json::json!(["bonus"]));
+        assert_eq!(diff["row_count_delta"], serde_json::json!(0));
+    }
+
+    #[pg_test]
+    fn test_fixed_size_binary_is_bytea() {
+        let uuids: Vec<[u8; 16]> = vec![[0x11; 16], [0xab; 16]];
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("uuid", DataType::FixedSizeBinary(16), false),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2])),
+                Arc::new(
+                    arrow::array::FixedSizeBinaryArray::try_from_iter(uuids.into_iter()).unwrap(),
+                ),
+            ],
+        )
+        .unwrap();
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .write_table("uuid_table", batch)
+            .expect("Failed to create uuid table");
+
+        let columns: Vec<_> = crate::lance_table_info(table_path.to_str().unwrap()).collect();
+        assert_eq!(columns[1].1, "bytea");
+
+        let uuid = Spi::get_one::<Vec<u8>>(&format!(
+            "SELECT uuid FROM lance_scan_typed('{}') AS t(id int4, uuid bytea) WHERE id = 2",
+            table_path.display()
+        ))
+        .expect("typed scan failed")
+        .expect("uuid is not null");
+        assert_eq!(uuid, vec![0xab; 16]);
+    }
+
+    #[pg_test]
+    fn test_lance_scan_with_addr() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        // Append a second fragment
+        let scanner = crate::scanner::LanceScanner::new(table_path_str).unwrap();
+        let batch = scanner.scan_with_filter(None, None).unwrap().batches[0].clone();
+        let reader =
+            arrow::record_batch::RecordBatchIterator::new(vec![Ok(batch.clone())], batch.schema());
+        let params = lance::dataset::WriteParams {
+            mode: lance::dataset::WriteMode::Append,
+            ..Default::default()
+        };
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(Dataset::write(reader, table_path_str, Some(params)))
+            .expect("Failed to append rows");
+
+        let fragment_ids: std::collections::HashSet<i64> = crate::lance_fragments(table_path_str)
+            .map(|f| f.0)
+            .collect();
+        assert_eq!(fragment_ids.len(), 2);
+
+        let rows: Vec<_> = crate::lance_scan_with_addr(table_path_str, None, None).collect();
+        assert_eq!(rows.len(), 10);
+        let mut seen_fragments = std::collections::HashSet::new();
+        for (pgrx::JsonB(row),) in &rows {
+            assert!(row.get("name").is_some());
+            let addr = row["_rowaddr"].as_u64().expect("_rowaddr is an integer");
+            let fragment_id = (addr >> 32) as i64;
+            assert!(fragment_ids.contains(&fragment_id));
+            assert!((addr & 0xffff_ffff) < 5);
+            seen_fragments.insert(fragment_id);
+        }
+        assert_eq!(seen_fragments, fragment_ids);
+    }
+
+    #[pg_test]
+    fn test_scan_error_codes() {
+        use crate::scanner::{LanceScanner, ScanError};
+
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let scanner = LanceScanner::new(table_path.to_str().unwrap()).unwrap();
+
+        let bad_filter = scanner
+            .scan_with_filter(Some("id >>> 3".to_string()), None)
+            .err()
+            .expect("filter should not parse");
+        assert!(matches!(bad_filter, ScanError::PredicateParse(_)));
+        assert_eq!(bad_filter.code(), PgSqlErrorCode::ERRCODE_SYNTAX_ERROR);
+
+        let bad_projection = scanner
+            .scan_with_projection(
+                None,
+                Some(vec![("x".to_string(), "no_such_column".to_string())]),
+                None,
+            )
+            .err()
+            .expect("projection should not resolve");
+        assert!(matches!(bad_projection, ScanError::ProjectionInvalid(_)));
+
+        let missing_path = LanceScanner::new("/invalid/path/does/not/exist")
+            .err()
+            .expect("path should not exist");
+        assert_eq!(missing_path, PgSqlErrorCode::ERRCODE_UNDEFINED_FILE);
+        assert_ne!(bad_filter.code(), missing_path);
+        assert_ne!(bad_projection.code(), missing_path);
+
+        let caught = caught_sqlstate(|| {
+            crate::lance_scan_jsonb(
+                table_path.to_str().unwrap(),
+                None,
+                None,
+                None,
+                Some("id >>> 3"),
+                None,
+                false,
+                None,
+                None,
+                "numeric",
+                None,
+            )
+            .count();
+        });
+        assert_eq!(caught, Some(PgSqlErrorCode::ERRCODE_SYNTAX_ERROR));
+    }
+
+    #[pg_test]
+    fn test_lance_arrow_types() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_vector_table()
+            .expect("Failed to create vector table");
+        let types: Vec<_> = crate::lance_arrow_types(table_path.to_str().unwrap()).collect();
+        assert_eq!(types[0], ("id".to_string(), "Int32".to_string()));
+        assert_eq!(
+            types[2],
+            ("embedding".to_string(), "List(Float32)".to_string())
+        );
+
+        let item = Arc::new(Field::new("item", DataType::Float32, true));
+        let embeddings = arrow::array::FixedSizeListArray::new(
+            item.clone(),
+            4,
+            Arc::new(Float32Array::from(vec![0.1, 0.2, 0.3, 0.4])),
+            None,
+        );
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "embedding",
+            DataType::FixedSizeList(item, 4),
+            false,
+        )]));
+        let batch = RecordBatch::try_new(schema, vec![Arc::new(embeddings)]).unwrap();
+        let table_path = generator
+            .write_table("fixed_vector_table", batch)
+            .expect("Failed to create fixed vector table");
+        let types: Vec<_> = crate::lance_arrow_types(table_path.to_str().unwrap()).collect();
+        assert_eq!(
+            types,
+            vec![(
+                "embedding".to_string(),
+                "FixedSizeList(Float32, 4)".to_string()
+            )]
+        );
+
+        assert_eq!(
+            crate::types::arrow_type_name(&DataType::Struct(
+                vec![
+                    Field::new("a", DataType::Int32, true),
+                    Field::new(
+                        "b",
+                        DataType::List(Arc::new(Field::new("item", DataType::Utf8, true))),
+                        true,
+                    ),
+                ]
+                .into()
+            )),
+            "Struct(a: Int32, b: List(Utf8))"
+        );
+    }
+
+    #[pg_test]
+    fn test_lance_append_ipc() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_nullable_table()
+            .expect("Failed to create nullable table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let to_ipc = |batch: &RecordBatch| {
+            let mut buffer = Vec::new();
+            let mut writer =
+                arrow::ipc::writer::StreamWriter::try_new(&mut buffer, &batch.schema()).unwrap();
+            writer.write(batch).unwrap();
+            writer.finish().unwrap();
+            drop(writer);
+            buffer
+        };
+
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("score", DataType::Float64, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int32Array::from(vec![5, 6])),
+                Arc::new(arrow::array::Float64Array::from(vec![Some(2.5), None])),
+            ],
+        )
+        .unwrap();
+        let version = crate::lance_append_ipc(table_path_str, &to_ipc(&batch));
+        assert_eq!(version, 2);
+        let stats: Vec<_> = crate::lance_table_stats(table_path_str).collect();
+        assert_eq!(stats[0].1, 6);
+
+        let mismatched = RecordBatch::try_new(
+            Arc::new(Schema::new(vec![Field::new("id", DataType::Utf8, false)])),
+            vec![Arc::new(StringArray::from(vec!["7"]))],
+        )
+        .unwrap();
+        let scanner = crate::scanner::LanceScanner::new(table_path_str).unwrap();
+        assert_eq!(
+            scanner.append(mismatched.schema(), vec![mismatched]),
+            Err(PgSqlErrorCode::ERRCODE_DATATYPE_MISMATCH)
+        );
+    }
+
+    #[pg_test]
+    fn test_on_unsupported_type_error() {
+        let views = arrow::array::StringViewArray::from(vec!["a"]);
+
+        Spi::run("SET pglance.on_unsupported_type = 'text'").unwrap();
+        assert!(crate::arrow_value_to_datum(&views, 0).unwrap().is_some());
+
+        Spi::run("SET pglance.on_unsupported_type = 'error'").unwrap();
+        let err = crate::arrow_value_to_datum(&views, 0)
+            .expect_err("unsupported type should not become text");
+        assert_eq!(err.code, PgSqlErrorCode::ERRCODE_FEATURE_NOT_SUPPORTED);
+        assert!(err.message.contains("Utf8View"));
+
+        Spi::run("RESET pglance.on_unsupported_type").unwrap();
+    }
+
+    #[pg_test]
+    fn test_lance_scan_cursor() {
+        use crate::cursor::ScanCursor;
+
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        // Append a second fragment so the cursor has to cross fragments
+        let scanner = crate::scanner::LanceScanner::new(table_path_str).unwrap();
+        let batch = scanner.scan_with_filter(None, None).unwrap().batches[0].clone();
+        let reader =
+            arrow::record_batch::RecordBatchIterator::new(vec![Ok(batch.clone())], batch.schema());
+        let params = lance::dataset::WriteParams {
+            mode: lance::dataset::WriteMode::Append,
+            ..Default::default()
+        };
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(Dataset::write(reader, table_path_str, Some(params)))
+            .expect("Failed to append rows");
+
+        let first: Vec<_> = crate::lance_scan_cursor(table_path_str, None, 7).collect();
+        assert_eq!(first.len(), 7);
+        let token = first.last().unwrap().1.clone();
+        let second: Vec<_> = crate::lance_scan_cursor(table_path_str, Some(&token), 7).collect();
+        assert_eq!(second.len(), 3);
+
+        let all: Vec<_> = crate::lance_scan_jsonb(
+            table_path_str,
+            None,
+            None,
+            None,
+            None,
+            None,
+            false,
+            None,
+            None,
+            "numeric",
+            None,
+        )
+        .map(|(pgrx::JsonB(row),)| row)
+        .collect();
+        let paged: Vec<_> = first
+            .iter()
+            .chain(second.iter())
+            .map(|(pgrx::JsonB(row), _)| row.clone())
+            .collect();
+        assert_eq!(paged, all);
+        assert!(paged.iter().all(|row| row.get("_rowaddr").is_none()));
+
+        let cursor = ScanCursor::decode(&token, table_path_str).unwrap();
+        assert_eq!(cursor.offset, 2);
+        assert_eq!(
+            ScanCursor::decode(&token, "/some/other/table"),
+            Err(PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE)
+        );
+        let tampered = ScanCursor {
+            offset: 0,
+            ..cursor
+        }
+        .encode("/some/other/table");
+        assert!(ScanCursor::decode(&tampered, table_path_str).is_err());
+        assert!(ScanCursor::decode("not a token", table_path_str).is_err());
+    }
+
+    #[pg_test]
+    fn test_storage_options_encryption() {
+        use crate::scanner::OpenOptions;
+
+        let key = "MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=";
+        let options = OpenOptions::from_json(&serde_json::json!({
+            "encryption_key": key,
+            "region": "us-east-1",
+        }))
+        .expect("Failed to parse storage options");
+        assert!(options.encrypted);
+        assert_eq!(options.storage_options["aws_sse_customer_key_base64"], key);
+        assert!(!options.storage_options.contains_key("encryption_key"));
+        assert!(!format!("{:?}", options).contains(key));
+
+        let options = OpenOptions::from_json(&serde_json::json!({
+            "encryption_kms_key_id": "arn:aws:kms:us-east-1:123456789012:key/example",
+        }))
+        .expect("Failed to parse storage options");
+        assert!(options.encrypted);
+        assert_eq!(
+            options.storage_options["aws_server_side_encryption"],
+            "aws:kms"
+        );
+        assert_eq!(
+            options.storage_options["aws_sse_kms_key_id"],
+            "arn:aws:kms:us-east-1:123456789012:key/example"
+        );
+
+        for invalid in [
+            serde_json::json!({ "encryption_key": "not base64!" }),
+            serde_json::json!({ "encryption_key": "c2hvcnQ=" }),
+            serde_json::json!({ "encryption_key": key, "encryption_kms_key_id": "k" }),
+        ] {
+            assert!(matches!(
+                OpenOptions::from_json(&invalid),
+                Err(PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE)
+            ));
+        }
+    }
+
+    #[pg_test]
+    fn test_lance_column_summary() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let pgrx::JsonB(summary) = crate::lance_column_summary(table_path_str, "salary");
+        assert_eq!(summary["count"], 5);
+        assert_eq!(summary["null_count"], 0);
+        assert_eq!(summary["min"].as_f64(), Some(50000.5));
+        assert_eq!(summary["max"].as_f64(), Some(120000.0));
+        let mean = summary["mean"].as_f64().unwrap();
+        assert!((mean - 82000.3).abs() < 0.01, "mean was {}", mean);
+        assert!(summary["stddev"].as_f64().unwrap() > 0.0);
+
+        let pgrx::JsonB(summary) = crate::lance_column_summary(table_path_str, "name");
+        assert_eq!(summary["min"], "Alice");
+        assert_eq!(summary["max"], "Eve");
+        assert!(summary.get("mean").is_none());
+
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_nullable_table()
+            .expect("Failed to create nullable table");
+        let pgrx::JsonB(summary) =
+            crate::lance_column_summary(table_path.to_str().unwrap(), "score");
+        assert_eq!(summary["count"], 2);
+        assert_eq!(summary["null_count"], 2);
+        assert_eq!(summary["mean"].as_f64(), Some(1.0));
+    }
+
+    #[pg_test]
+    fn test_sliced_batch_to_json() {
+        use arrow::array::{Array, ArrayRef, FixedSizeListArray, ListArray, StructArray};
+        use arrow::datatypes::Int32Type;
+
+        let list = ListArray::from_iter_primitive::<Int32Type, _, _>(vec![
+            Some(vec![Some(1)]),
+            Some(vec![Some(2), Some(3)]),
+            None,
+            Some(vec![Some(4), None, Some(5)]),
+        ]);
+        let fixed = FixedSizeListArray::from_iter_primitive::<Int32Type, _, _>(
+            vec![
+                Some(vec![Some(1), Some(2)]),
+                Some(vec![Some(3), Some(4)]),
+                Some(vec![Some(5), Some(6)]),
+                Some(vec![Some(7), Some(8)]),
+            ],
+            2,
+        );
+        let structs = StructArray::from(vec![(
+            Arc::new(Field::new("n", DataType::Int32, true)),
+            Arc::new(Int32Array::from(vec![Some(10), Some(20), None, Some(40)])) as ArrayRef,
+        )]);
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("list", list.data_type().clone(), true),
+            Field::new("fixed", fixed.data_type().clone(), true),
+            Field::new("struct", structs.data_type().clone(), true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![Arc::new(list), Arc::new(fixed), Arc::new(structs)],
+        )
+        .expect("Failed to create record batch");
+
+        let sliced = batch.slice(1, 3);
+        let encoder = crate::BatchJsonEncoder::new(&sliced);
+        let rows: Vec<serde_json::Value> = (0..sliced.num_rows())
+            .map(|row_idx| encoder.encode_row(row_idx).unwrap())
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                serde_json::json!({"list": [2, 3], "fixed": [3, 4], "struct": {"n": 20}}),
+                serde_json::json!({"list": null, "fixed": [5, 6], "struct": {"n": null}}),
+                serde_json::json!({"list": [4, null, 5], "fixed": [7, 8], "struct": {"n": 40}}),
+            ]
+        );
+    }
+
+    #[pg_test]
+    fn test_lance_insert_from_query() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let version = crate::insert::lance_insert_from_query(
+            table_path_str,
+            "SELECT 'Frank' AS name, 6 AS id, 41 AS age, 70000.5 AS salary, true AS is_active",
+        );
+        assert_eq!(version, 2);
+
+        let row = Spi::get_one::<pgrx::JsonB>(&format!(
+            "SELECT row_data FROM lance_scan_jsonb('{}', filter => 'id = 6')",
+            table_path.display()
+        ))
+        .expect("scan failed")
+        .expect("inserted row should scan back");
+        assert_eq!(
+            row.0,
+            serde_json::json!({
+                "id": 6,
+                "name": "Frank",
+                "age": 41,
+                "salary": 70000.5,
+                "is_active": true
+            })
+        );
+
+        // An empty result appends nothing
+        let version = crate::insert::lance_insert_from_query(
+            table_path_str,
+            "SELECT 7 AS id, 'Grace' AS name, 30 AS age, 1.0 AS salary, true AS is_active \
+             WHERE false",
+        );
+        assert_eq!(version, 2);
+    }
+
+    #[pg_test]
+    fn test_lance_insert_from_query_type_mismatch() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+
+        let caught = caught_sqlstate(|| {
+            crate::insert::lance_insert_from_query(
+                table_path.to_str().unwrap(),
+                "SELECT 6 AS id, 'Frank' AS name, true AS age, 1.0 AS salary, true AS is_active",
+            );
+        });
+        assert_eq!(caught, Some(PgSqlErrorCode::ERRCODE_DATATYPE_MISMATCH));
+    }
+
+    #[pg_test]
+    fn test_lance_count_at_least() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        assert!(crate::lance_count_at_least(table_path_str, None, 3));
+        assert!(crate::lance_count_at_least(table_path_str, None, 5));
+        assert!(!crate::lance_count_at_least(table_path_str, None, 99));
+        assert!(crate::lance_count_at_least(
+            table_path_str,
+            Some("age > 30"),
+            3
+        ));
+        assert!(!crate::lance_count_at_least(
+            table_path_str,
+            Some("age > 30"),
+            4
+        ));
+        assert!(crate::lance_count_at_least(
+            table_path_str,
+            Some("id > 99"),
+            0
+        ));
+    }
+
+    #[pg_test]
+    fn test_json_encoder_omits_unselected_columns() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("score", DataType::Float64, true),
+            Field::new("name", DataType::Utf8, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2])),
+                Arc::new(arrow::array::Float64Array::from(vec![Some(0.5), None])),
+                Arc::new(StringArray::from(vec![Some("a"), None])),
+            ],
+        )
+        .unwrap();
+
+        let encoder = crate::BatchJsonEncoder::new(&batch).only(&["id", "score"]);
+        assert_eq!(
+            encoder.encode_row(0).unwrap(),
+            serde_json::json!({"id": 1, "score": 0.5})
+        );
+        let row = encoder.encode_row(1).unwrap();
+        assert_eq!(row, serde_json::json!({"id": 2, "score": null}));
+        assert!(row.get("name").is_none());
+    }
+
+    #[pg_test]
+    fn test_lance_knn_multiple_columns() {
+        let embeddings = |vectors: [[f32; 2]; 3]| {
+            let mut builder = arrow::array::ListBuilder::new(arrow::array::Float32Builder::new());
+            for vector in vectors {
+                builder.values().append_slice(&vector);
+                builder.append(true);
+            }
+            builder.finish()
+        };
+        let item = Arc::new(Field::new("item", DataType::Float32, true));
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("text_embedding", DataType::List(item.clone()), true),
+            Field::new("image_embedding", DataType::List(item), true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2, 3])),
+                Arc::new(embeddings([[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]])),
+                Arc::new(embeddings([[0.0, 1.0], [1.0, 1.0], [5.0, 5.0]])),
+            ],
+        )
+        .unwrap();
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .write_table("two_embeddings", batch)
+            .expect("Failed to create table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let ids = |rows: Vec<(pgrx::JsonB, f64)>| -> Vec<i64> {
+            rows.iter()
+                .map(|(row, _)| row.0["id"].as_i64().unwrap())
+                .collect()
+        };
+
+        let text: Vec<_> = crate::lance_knn(
+            table_path_str,
+            "text_embedding",
+            vec![1.0, 0.0],
+            2,
+            None,
+            true,
+        )
+        .collect();
+        assert_eq!(ids(text.clone()), vec![2, 1]);
+        assert_eq!(text[0].1, 0.0);
+
+        let image: Vec<_> = crate::lance_knn(
+            table_path_str,
+            "image_embedding",
+            vec![0.0, 1.0],
+            1,
+            None,
+            true,
+        )
+        .collect();
+        assert_eq!(ids(image), vec![1]);
+
+        let fused: Vec<_> = crate::lance_knn_fused(
+            table_path_str,
+            "text_embedding",
+            vec![0.0, 0.0],
+            "image_embedding",
+            vec![5.0, 5.0],
+            0.5,
+            3,
+        )
+        .collect();
+        assert_eq!(ids(fused.clone()), vec![3, 1, 2]);
+        assert!((fused[0].1 - 0.5).abs() < 1e-9);
+    }
+
+    #[pg_test]
+    fn test_lance_config() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let config: Vec<_> = crate::lance_config(table_path_str).collect();
+        assert!(config.is_empty());
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut dataset = Dataset::open(table_path_str).await.unwrap();
+            dataset
+                .update_config(vec![("index.num_partitions".to_string(), "16".to_string())])
+                .await
+                .unwrap();
+        });
+
+        let config: Vec<_> = crate::lance_config(table_path_str).collect();
+        assert_eq!(
+            config,
+            vec![("index.num_partitions".to_string(), "16".to_string())]
+        );
+    }
+
+    #[pg_test]
+    fn test_uint64_is_numeric() {
+        let values = arrow::array::UInt64Array::from(vec![u64::MAX]);
+        assert_eq!(
+            crate::types::arrow_data_type_to_pg_type(&DataType::UInt64),
+            Ok(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::NUMERICOID))
+        );
+        let datum = crate::arrow_value_to_datum(&values, 0).unwrap().unwrap();
+        let numeric = unsafe { pgrx::AnyNumeric::from_datum(datum, false) }.unwrap();
+        assert_eq!(numeric.to_string(), "18446744073709551615");
+
+        Spi::run("SET pglance.uint64_as_bigint = on").unwrap();
+        let pg_type = crate::types::arrow_data_type_to_pg_type(&DataType::UInt64);
+        let result = crate::arrow_value_to_datum(&values, 0);
+        Spi::run("RESET pglance.uint64_as_bigint").unwrap();
+        assert_eq!(
+            pg_type,
+            Ok(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::INT8OID))
+        );
+        assert_eq!(
+            result.unwrap_err().code,
+            PgSqlErrorCode::ERRCODE_NUMERIC_VALUE_OUT_OF_RANGE
+        );
+    }
+
+    #[pg_test]
+    fn test_coalesce_small_batches() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator.get_base_path().join("many_fragments");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Int32, false)]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![Arc::new(Int32Array::from_iter_values(0..200))],
+        )
+        .unwrap();
+        let reader = arrow::record_batch::RecordBatchIterator::new(vec![Ok(batch)], schema);
+        let params = lance::dataset::WriteParams {
+            max_rows_per_file: 10,
+            max_rows_per_group: 10,
+            ..Default::default()
+        };
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(Dataset::write(reader, table_path_str, Some(params)))
+            .expect("Failed to write table");
+
+        let scan = |coalesce: bool| {
+            crate::scanner::LanceScanner::new(table_path_str)
+                .unwrap()
+                .with_coalesce_batches(coalesce)
+                .scan_with_filter(Some("id % 10 = 0".to_string()), None)
+                .unwrap()
+                .batches
+        };
+        let ids = |batches: &[RecordBatch]| -> Vec<i32> {
+            batches
+                .iter()
+                .flat_map(|b| {
+                    b.column(0)
+                        .as_any()
+                        .downcast_ref::<Int32Array>()
+                        .unwrap()
+                        .values()
+                        .to_vec()
+                })
+                .collect()
+        };
+
+        let uncoalesced = scan(false);
+        let coalesced = scan(true);
+        assert!(coalesced.len() < uncoalesced.len());
+        assert_eq!(coalesced.len(), 1);
+        assert_eq!(ids(&coalesced), (0..200).step_by(10).collect::<Vec<i32>>());
+        assert_eq!(ids(&coalesced), ids(&uncoalesced));
+    }
+
+    #[pg_test]
+    fn test_scan_options() {
+        use crate::scanner::{LanceScanner, ScanOptions};
+
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let scanner = LanceScanner::new(table_path.to_str().unwrap()).unwrap();
+
+        let options = ScanOptions::new()
+            .with_filter("age > 25")
+            .with_columns(&["name", "age"])
+            .with_order_by("age", false)
+            .with_limit(3)
+            .with_offset(1)
+            .with_batch_size(1);
+        let batches = scanner.scan(&options).unwrap().batches;
+        assert!(batches.iter().all(|b| b.num_rows() <= 1));
+
+        let names: Vec<String> = batches
+            .iter()
+            .flat_map(|b| {
+                assert_eq!(b.schema().fields().len(), 2);
+                let names = b
+                    .column_by_name("name")
+                    .unwrap()
+                    .as_any()
+                    .downcast_ref::<StringArray>()
+                    .unwrap();
+                names
+                    .iter()
+                    .flatten()
+                    .map(str::to_string)
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        assert_eq!(names, vec!["David", "Charlie", "Bob"]);
+
+        // The positional scan methods are shorthands for the same options
+        let filtered = scanner
+            .scan_with_filter(Some("age > 25".to_string()), Some(2))
+            .unwrap();
+        let via_options = scanner
+            .scan(&ScanOptions::new().with_filter("age > 25").with_limit(2))
+            .unwrap();
+        assert_eq!(filtered.batches, via_options.batches);
+    }
+
+    #[pg_test]
+    fn test_lance_data_files() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+
+        let files: Vec<_> = crate::lance_data_files(table_path.to_str().unwrap()).collect();
+        assert!(!files.is_empty());
+        for (_, path, format, format_version, num_fields, uri) in &files {
+            assert!(path.ends_with(".lance"));
+            assert_eq!(format, "lance");
+            assert!(format_version.is_some());
+            assert_eq!(*num_fields, 5);
+            assert!(uri.ends_with(&format!("/data/{}", path)), "{}", uri);
+        }
+
+        // Files kept in another bucket are referenced by their full URI
+        let scanner = crate::scanner::LanceScanner::new(table_path.to_str().unwrap()).unwrap();
+        assert_eq!(
+            scanner.data_file_uri("s3://archive-bucket/data/old.lance"),
+            "s3://archive-bucket/data/old.lance"
+        );
+
+        assert_eq!(crate::data_file_format("part-0.parquet"), "parquet");
+        assert_eq!(crate::data_file_format("no_extension"), "unknown");
+    }
+
+    #[pg_test]
+    fn test_lance_scan_estimate() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let pgrx::JsonB(estimate) = crate::lance_scan_estimate(table_path_str, None, None);
+        assert_eq!(estimate["estimated_rows"], 5);
+        assert_eq!(estimate["uses_index"], false);
+        assert_eq!(estimate["upper_bound"], false);
+        let total_bytes = estimate["estimated_bytes"].as_u64().unwrap();
+        assert!(total_bytes > 0);
+
+        let pgrx::JsonB(estimate) =
+            crate::lance_scan_estimate(table_path_str, Some("age > 30"), None);
+        assert_eq!(estimate["estimated_rows"], 5);
+        assert_eq!(estimate["upper_bound"], true);
+        assert_eq!(estimate["estimated_bytes"].as_u64(), Some(total_bytes));
+    }
+
+    #[pg_test]
+    fn test_tz_aware_timestamp_is_timestamptz() {
+        let naive = arrow::array::TimestampMicrosecondArray::from(vec![1_700_000_000_000_000i64]);
+        let aware = naive.clone().with_timezone("+02:00");
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("naive", naive.data_type().clone(), false),
+            Field::new("aware", aware.data_type().clone(), false),
+        ]));
+        let batch =
+            RecordBatch::try_new(schema, vec![Arc::new(naive), Arc::new(aware.clone())]).unwrap();
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .write_table("timestamps", batch)
+            .expect("Failed to create timestamp table");
+
+        let info: Vec<_> = crate::lance_table_info(table_path.to_str().unwrap()).collect();
+        assert_eq!(info[0].1, "timestamp");
+        assert_eq!(info[1].1, "timestamptz");
+
+        let datum = crate::arrow_value_to_datum(&aware, 0).unwrap().unwrap();
+        let value = unsafe { pgrx::datum::TimestampWithTimeZone::from_datum(datum, false) }
+            .expect("timestamptz value should not be NULL");
+        let expected = crate::types::chrono_to_pg_timestamptz(
+            chrono::DateTime::from_timestamp_micros(1_700_000_000_000_000).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(value, expected);
+    }
+
+    #[pg_test]
+    fn test_lance_drop_table() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let caught = caught_sqlstate(|| {
+            crate::lance_drop_table(table_path_str, false);
+        });
+        assert_eq!(
+            caught,
+            Some(PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE)
+        );
+        assert!(crate::scanner::LanceScanner::new(table_path_str).is_ok());
+
+        crate::lance_drop_table(table_path_str, true);
+        assert!(!table_path.exists());
+        assert!(crate::scanner::LanceScanner::new(table_path_str).is_err());
+    }
+
+    #[pg_test]
+    fn test_scan_include_deleted() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let mut dataset = Dataset::open(table_path_str).await.unwrap();
+            dataset.delete("id = 2").await.unwrap();
+        });
+
+        let live: Vec<_> = crate::lance_scan_jsonb(
+            table_path_str,
+            None,
+            None,
+            None,
+            None,
+            None,
+            false,
+            None,
+            None,
+            "numeric",
+            None,
+        )
+        .collect();
+        assert_eq!(live.len(), 4);
+        assert!(live.iter().all(|(row,)| row.0.get("_deleted").is_none()));
+
+        let all: Vec<_> = crate::lance_scan_jsonb(
+            table_path_str,
+            None,
+            None,
+            None,
+            None,
+            None,
+            true,
+            None,
+            None,
+            "numeric",
+            None,
+        )
+        .collect();
+        assert_eq!(all.len(), 5);
+        let deleted: Vec<_> = all
+            .iter()
+            .filter(|(row,)| row.0["_deleted"] == serde_json::json!(true))
+            .collect();
+        assert_eq!(deleted.len(), 1);
+        assert_eq!(deleted[0].0 .0["id"], 2);
+        assert_eq!(deleted[0].0 .0["name"], "Bob");
+    }
+
+    #[pg_test]
+    fn test_max_binary_bytes() {
+        use base64::{engine::general_purpose::STANDARD, Engine as _};
+
+        let blob = vec![0x5au8; 4096];
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("blob", DataType::Binary, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2, 3])),
+                Arc::new(arrow::array::BinaryArray::from(vec![
+                    Some(blob.as_slice()),
+                    Some(&b"ab"[..]),
+                    None,
+                ])),
+            ],
+        )
+        .unwrap();
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .write_table("blob_table", batch)
+            .expect("Failed to create blob table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let rows: Vec<_> = crate::lance_scan_jsonb(
+            table_path_str,
+            None,
+            None,
+            None,
+            None,
+            None,
+            false,
+            Some(3),
+            None,
+            "numeric",
+            None,
+        )
+        .collect();
+        assert_eq!(rows[0].0 .0["blob"], "Wlpa...truncated");
+        assert_eq!(rows[1].0 .0["blob"], "YWI=");
+        assert!(rows[2].0 .0["blob"].is_null());
+
+        let full: Vec<_> = crate::lance_scan_jsonb(
+            table_path_str,
+            None,
+            None,
+            None,
+            None,
+            None,
+            false,
+            None,
+            None,
+            "numeric",
+            None,
+        )
+        .collect();
+        let encoded = full[0].0 .0["blob"].as_str().unwrap().to_string();
+        assert_eq!(STANDARD.decode(encoded).unwrap(), blob);
+    }
+
+    #[pg_test]
+    fn test_lance_changes_since() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_nullable_table()
+            .expect("Failed to create nullable table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let appended = RecordBatch::try_new(
+            Arc::new(Schema::new(vec![
+                Field::new("id", DataType::Int32, false),
+                Field::new("score", DataType::Float64, true),
+            ])),
+            vec![
+                Arc::new(Int32Array::from(vec![5])),
+                Arc::new(arrow::array::Float64Array::from(vec![Some(2.5)])),
+            ],
+        )
+        .unwrap();
+        let scanner = crate::scanner::LanceScanner::new(table_path_str).unwrap();
+        let version = scanner
+            .append(appended.schema(), vec![appended])
+            .expect("append failed");
+        assert_eq!(version, 2);
+
+        let changes: Vec<_> = crate::lance_changes_since(table_path_str, 1, None).collect();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].0, "added");
+        assert_eq!(
+            changes[0].1 .0,
+            serde_json::json!({ "id": 5, "score": 2.5 })
+        );
+
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let mut dataset = Dataset::open(table_path_str).await.unwrap();
+            dataset.delete("id = 1").await.unwrap();
+        });
+        let changes: Vec<_> = crate::lance_changes_since(table_path_str, 2, None).collect();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].0, "deleted");
+        assert_eq!(changes[0].1 .0["id"], 1);
+
+        assert!(crate::lance_changes_since(table_path_str, 3, None)
+            .next()
+            .is_none());
+    }
+
+    #[pg_test]
+    fn test_concurrent_cached_schema_reads() {
+        use crate::scanner::{cached_dataset, dataset_cache_key, LanceScanner, OpenOptions};
+
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let scanner = LanceScanner::new(table_path_str).unwrap();
+        let key = dataset_cache_key(table_path_str, &OpenOptions::default());
+
+        let field_counts: Vec<usize> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..8)
+                .map(|_| {
+                    scope.spawn(|| {
+                        (0..100)
+                            .map(|_| {
+                                let dataset = cached_dataset(&key).expect("dataset is cached");
+                                assert_eq!(
+                                    dataset.schema().fields.len(),
+                                    scanner.schema().fields().len()
+                                );
+                                scanner.schema().fields().len()
+                            })
+                            .sum()
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        assert_eq!(field_counts, vec![500; 8]);
+    }
+
+    #[pg_test]
+    fn test_lance_scan_field() {
+        use arrow::array::{ArrayRef, StructArray};
+        use arrow::buffer::NullBuffer;
+        use arrow::datatypes::Fields;
+
+        let metadata_fields = Fields::from(vec![
+            Field::new("author", DataType::Utf8, true),
+            Field::new("pages", DataType::Int32, true),
+        ]);
+        let metadata = StructArray::new(
+            metadata_fields.clone(),
+            vec![
+                Arc::new(StringArray::from(vec![Some("Ann"), Some("hidden"), None])) as ArrayRef,
+                Arc::new(Int32Array::from(vec![Some(120), Some(7), Some(300)])) as ArrayRef,
+            ],
+            Some(NullBuffer::from(vec![true, false, true])),
+        );
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("metadata", DataType::Struct(metadata_fields), true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2, 3])),
+                Arc::new(metadata),
+            ],
+        )
+        .unwrap();
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .write_table("books", batch)
+            .expect("Failed to create books table");
+
+        let authors = Spi::get_one::<Vec<Option<String>>>(&format!(
+            "SELECT array_agg(author) FROM lance_scan_field('{}', 'metadata.author') AS t(author text)",
+            table_path.display()
+        ))
+        .expect("field scan failed")
+        .expect("array is not null");
+        assert_eq!(authors, vec![Some("Ann".to_string()), None, None]);
+
+        let pages = Spi::get_one::<i64>(&format!(
+            "SELECT sum(pages) FROM lance_scan_field('{}', 'metadata.pages') AS t(pages int4)",
+            table_path.display()
+        ))
+        .expect("field scan failed");
+        assert_eq!(pages, Some(420));
+
+        let caught = caught_sqlstate(|| {
+            Spi::run(&format!(
+                "SELECT * FROM lance_scan_field('{}', 'metadata.title') AS t(title text)",
+                table_path.display()
+            ))
+            .ok();
+        });
+        assert_eq!(caught, Some(PgSqlErrorCode::ERRCODE_UNDEFINED_COLUMN));
+    }
+
+    #[pg_test]
+    fn test_lance_append_validate() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let valid = serde_json::json!({
+            "id": 6, "name": "Frank", "age": 50, "salary": 70000.5, "is_active": true
+        });
+        let mismatched = serde_json::json!({
+            "id": 7, "name": "Grace", "age": "old", "salary": 1.0, "is_active": false
+        });
+
+        let pgrx::JsonB(errors) =
+            crate::insert::lance_append_validate(table_path_str, vec![pgrx::JsonB(valid.clone())]);
+        assert_eq!(errors, serde_json::json!([]));
+
+        let pgrx::JsonB(errors) = crate::insert::lance_append_validate(
+            table_path_str,
+            vec![pgrx::JsonB(valid), pgrx::JsonB(mismatched)],
+        );
+        let errors = errors.as_array().unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0]["row"], 2);
+        assert_eq!(errors[0]["column"], "age");
+
+        let stats: Vec<_> = crate::lance_table_stats(table_path_str).collect();
+        assert_eq!(stats[0].1, 5);
+    }
+
+    #[pg_test]
+    fn test_lance_vector_distance() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_vector_table()
+            .expect("Failed to create vector table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let l2 = crate::lance_vector_distance(table_path_str, "embedding", 0, 1, "l2").unwrap();
+        assert!((l2 - 0.8).abs() < 1e-5);
+        let same = crate::lance_vector_distance(table_path_str, "embedding", 2, 2, "l2").unwrap();
+        assert_eq!(same, 0.0);
+        let cosine =
+            crate::lance_vector_distance(table_path_str, "embedding", 0, 2, "cosine").unwrap();
+        assert!(cosine > 0.0 && cosine < 0.1);
+
+        let caught = caught_sqlstate(|| {
+            crate::lance_vector_distance(table_path_str, "embedding", 0, 99, "l2");
+        });
+        assert_eq!(caught, Some(PgSqlErrorCode::ERRCODE_NO_DATA_FOUND));
+    }
+
+    #[pg_test]
+    fn test_lance_knn_prefilter() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_vector_table()
+            .expect("Failed to create vector table");
+        let table_path_str = table_path.to_str().unwrap();
+        let query = vec![0.1, 0.2, 0.3, 0.4];
+        let ids = |rows: Vec<(pgrx::JsonB, f64)>| -> Vec<i64> {
+            rows.iter()
+                .map(|(row, _)| row.0["id"].as_i64().unwrap())
+                .collect()
+        };
+
+        let prefiltered: Vec<_> = crate::lance_knn(
+            table_path_str,
+            "embedding",
+            query.clone(),
+            1,
+            Some("id > 1"),
+            true,
+        )
+        .collect();
+        assert_eq!(ids(prefiltered), vec![2]);
+
+        // The nearest row does not match, so postfiltering leaves nothing
+        let postfiltered: Vec<_> = crate::lance_knn(
+            table_path_str,
+            "embedding",
+            query.clone(),
+            1,
+            Some("id > 1"),
+            false,
+        )
+        .collect();
+        assert!(postfiltered.is_empty());
+
+        let postfiltered: Vec<_> =
+            crate::lance_knn(table_path_str, "embedding", query, 2, Some("id > 1"), false)
+                .collect();
+        assert_eq!(postfiltered[0].0 .0.get("_rowaddr"), None);
+        assert_eq!(ids(postfiltered), vec![2]);
+    }
+
+    #[pg_test]
+    fn test_lance_logical_size() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+
+        // Three 4-byte numeric columns plus the sampled name and flag columns
+        let size = crate::lance_logical_size(table_path.to_str().unwrap());
+        assert!(
+            size >= 5 * 12,
+            "estimate {} is below the fixed widths",
+            size
+        );
+        assert!(size <= 5 * 64, "estimate {} is too large for 5 rows", size);
+    }
+
+    #[pg_test]
+    fn test_vector_null_element_policy() {
+        use arrow::array::FixedSizeListArray;
+        use arrow::datatypes::Float32Type;
+
+        let vectors = FixedSizeListArray::from_iter_primitive::<Float32Type, _, _>(
+            vec![
+                Some(vec![Some(1.0), None, Some(3.0)]),
+                Some(vec![Some(4.0), Some(5.0), Some(6.0)]),
+            ],
+            3,
+        );
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "embedding",
+            vectors.data_type().clone(),
+            true,
+        )]));
+        let batch = RecordBatch::try_new(schema, vec![Arc::new(vectors)]).unwrap();
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .write_table("null_element_vectors", batch)
+            .expect("Failed to create vector table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let nulls: Vec<_> =
+            crate::lance_scan_vectors(table_path_str, "embedding", None, "null").collect();
+        assert_eq!(nulls[0].0, Some(vec![Some(1.0), None, Some(3.0)]));
+
+        let zeros: Vec<_> =
+            crate::lance_scan_vectors(table_path_str, "embedding", None, "zero").collect();
+        assert_eq!(zeros[0].0, Some(vec![Some(1.0), Some(0.0), Some(3.0)]));
+        assert_eq!(zeros[1].0, Some(vec![Some(4.0), Some(5.0), Some(6.0)]));
+
+        let caught = caught_sqlstate(|| {
+            crate::lance_scan_vectors(table_path_str, "embedding", None, "error").count();
+        });
+        assert_eq!(caught, Some(PgSqlErrorCode::ERRCODE_NULL_VALUE_NOT_ALLOWED));
+    }
+
+    #[pg_test]
+    fn test_lance_versions_operations() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_nullable_table()
+            .expect("Failed to create nullable table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let appended = RecordBatch::try_new(
+            Arc::new(Schema::new(vec![
+                Field::new("id", DataType::Int32, false),
+                Field::new("score", DataType::Float64, true),
+            ])),
+            vec![
+                Arc::new(Int32Array::from(vec![5])),
+                Arc::new(arrow::array::Float64Array::from(vec![Some(2.5)])),
+            ],
+        )
+        .unwrap();
+        crate::scanner::LanceScanner::new(table_path_str)
+            .unwrap()
+            .append(appended.schema(), vec![appended])
+            .expect("append failed");
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let mut dataset = Dataset::open(table_path_str).await.unwrap();
+            dataset.delete("id = 5").await.unwrap();
+        });
+
+        let versions: Vec<_> = crate::lance_versions(table_path_str).collect();
+        let operations: Vec<(i64, &str)> = versions
+            .iter()
+            .map(|(version, _, operation)| (*version, operation.as_str()))
+            .collect();
+        assert_eq!(operations.len(), 3);
+        assert_eq!(operations[1], (2, "Append"));
+        assert_eq!(operations[2], (3, "Delete"));
+    }
+
+    #[pg_test]
+    fn test_scan_timeout() {
+        // Reading a few million rows takes far longer than a millisecond
+        let ids = arrow::array::Int64Array::from_iter_values(0..4_000_000);
+        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Int64, false)]));
+        let batch = RecordBatch::try_new(schema, vec![Arc::new(ids)]).unwrap();
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .write_table("large", batch)
+            .expect("Failed to create large table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let timed_out = |scan: &dyn Fn()| caught_sqlstate(scan);
+        let canceled = Some(PgSqlErrorCode::ERRCODE_QUERY_CANCELED);
+        assert_eq!(
+            timed_out(&|| {
+                crate::lance_scan_jsonb(
+                    table_path_str,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    false,
+                    None,
+                    Some(1),
+                    "numeric",
+                    None,
+                )
+                .count();
+            }),
+            canceled
+        );
+        assert_eq!(
+            timed_out(&|| {
+                crate::lance_scan_batches(table_path_str, None, None, Some(1)).count();
+            }),
+            canceled
+        );
+        assert_eq!(
+            timed_out(&|| {
+                crate::lance_scan_with_addr(table_path_str, None, Some(1)).count();
+            }),
+            canceled
+        );
+        assert_eq!(
+            timed_out(&|| {
+                Spi::get_one::<i64>(&format!(
+                    "SELECT count(*) FROM lance_scan_typed('{}', NULL, 1) AS t(id int8)",
+                    table_path_str
+                ))
+                .unwrap();
+            }),
+            canceled
+        );
+
+        // A generous timeout does not get in the way
+        let rows = crate::lance_scan_with_addr(table_path_str, Some(5), Some(60_000)).count();
+        assert_eq!(rows, 5);
+    }
+
+    #[pg_test]
+    fn test_lance_check_typed() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let table_path_str = table_path.to_str().unwrap();
+        let types = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+
+        let pgrx::JsonB(report) = crate::typed::lance_check_typed(
+            table_path_str,
+            types(&["integer", "text", "int4", "real", "bool"]),
+        );
+        assert_eq!(report, serde_json::json!([]));
+
+        let pgrx::JsonB(report) = crate::typed::lance_check_typed(
+            table_path_str,
+            types(&["text", "text", "int4", "real", "boolean"]),
+        );
+        assert_eq!(
+            report,
+            serde_json::json!([{
+                "position": 1,
+                "column": "id",
+                "expected": "int4",
+                "requested": "text",
+            }])
+        );
+
+        let pgrx::JsonB(report) =
+            crate::typed::lance_check_typed(table_path_str, types(&["int4", "text"]));
+        let missing = report.as_array().unwrap();
+        assert_eq!(missing.len(), 3);
+        assert_eq!(missing[0]["position"], 3);
+        assert!(missing.iter().all(|m| m["requested"].is_null()));
+    }
+
+    #[pg_test]
+    fn test_scan_mem_budget() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        Spi::run("SET pglance.scan_mem_budget_bytes = 64").unwrap();
+        let caught = caught_sqlstate(|| {
+            crate::lance_scan_jsonb(
+                table_path_str,
+                None,
+                None,
+                None,
+                None,
+                None,
+                false,
+                None,
+                None,
+                "numeric",
+                None,
+            )
+            .count();
+        });
+        assert_eq!(caught, Some(PgSqlErrorCode::ERRCODE_OUT_OF_MEMORY));
+
+        Spi::run("SET pglance.scan_mem_budget_bytes = 1000000").unwrap();
+        let rows = crate::lance_scan_jsonb(
+            table_path_str,
+            None,
+            None,
+            None,
+            None,
+            None,
+            false,
+            None,
+            None,
+            "numeric",
+            None,
+        )
+        .count();
+        Spi::run("RESET pglance.scan_mem_budget_bytes").unwrap();
+        assert_eq!(rows, 5);
+    }
+
+    #[pg_test]
+    fn test_lance_index_details() {
+        use arrow::array::FixedSizeListArray;
+        use arrow::datatypes::Float32Type;
+        use lance::index::vector::VectorIndexParams;
+        use lance_index::{DatasetIndexExt, IndexType};
+        use lance_linalg::distance::DistanceType;
+
+        let vectors = FixedSizeListArray::from_iter_primitive::<Float32Type, _, _>(
+            (0..512).map(|i| Some((0..8).map(move |j| Some(((i * 7 + j * 13) % 97) as f32)))),
+            8,
+        );
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "embedding",
+            vectors.data_type().clone(),
+            true,
+        )]));
+        let batch = RecordBatch::try_new(schema, vec![Arc::new(vectors)]).unwrap();
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .write_table("indexed_vectors", batch)
+            .expect("Failed to create vector table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let pgrx::JsonB(details) = crate::lance_index_details(table_path_str);
+        assert_eq!(details, serde_json::json!([]));
+
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let mut dataset = Dataset::open(table_path_str).await.unwrap();
+            let params = VectorIndexParams::ivf_pq(4, 8, 2, DistanceType::L2, 10);
+            dataset
+                .create_index(&["embedding"], IndexType::Vector, None, &params, true)
+                .await
+                .unwrap();
+        });
+
+        let pgrx::JsonB(details) = crate::lance_index_details(table_path_str);
+        let details = details.as_array().unwrap();
+        assert_eq!(details.len(), 1);
+        let index = &details[0];
+        assert_eq!(index["type"], "IVF_PQ");
+        assert_eq!(index["columns"], serde_json::json!(["embedding"]));
+        assert_eq!(index["num_partitions"], 4);
+        assert_eq!(index["num_sub_vectors"], 2);
+        assert_eq!(index["metric"], "l2");
+    }
+
+    #[pg_test]
+    fn test_scan_pins_version_during_concurrent_append() {
+        use crate::scanner::LanceScanner;
+
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let scanner = LanceScanner::new(table_path_str)
+            .unwrap()
+            .with_batch_size(2)
+            .with_coalesce_batches(false);
+        let batch = scanner.scan_with_filter(None, None).unwrap().batches[0].clone();
+
+        // Another writer appends after the scanner was opened
+        let reader =
+            arrow::record_batch::RecordBatchIterator::new(vec![Ok(batch.clone())], batch.schema());
+        let params = lance::dataset::WriteParams {
+            mode: lance::dataset::WriteMode::Append,
+            ..Default::default()
+        };
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(Dataset::write(reader, table_path_str, Some(params)))
+            .expect("Failed to append rows");
+
+        let batches = scanner.scan_with_filter(None, None).unwrap().batches;
+        assert!(
+            batches.len() > 1,
+            "expected the scan to span several batches"
+        );
+        let rows: usize = batches.iter().map(|batch| batch.num_rows()).sum();
+        assert_eq!(rows, 5);
+        assert_eq!(scanner.version(), 1);
+        assert_eq!(scanner.get_stats().unwrap().num_rows, 5);
+
+        let reopened = LanceScanner::new(table_path_str).unwrap();
+        assert_eq!(reopened.version(), 2);
+        assert_eq!(reopened.get_stats().unwrap().num_rows, 10);
+    }
+
+    #[pg_test]
+    fn test_scan_decimal_as_scaled_int() {
+        let prices = arrow::array::Decimal128Array::from(vec![
+            Some(12345),
+            None,
+            Some(-99999999999999999999999999999999999999),
+        ])
+        .with_precision_and_scale(38, 2)
+        .unwrap();
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "price",
+            prices.data_type().clone(),
+            true,
+        )]));
+        let batch = RecordBatch::try_new(schema, vec![Arc::new(prices)]).unwrap();
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .write_table("decimal_prices", batch)
+            .expect("Failed to create decimal table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let scan = |decimal_as| -> Vec<serde_json::Value> {
+            crate::lance_scan_jsonb(
+                table_path_str,
+                None,
+                None,
+                None,
+                None,
+                None,
+                false,
+                None,
+                None,
+                decimal_as,
+                None,
+            )
+            .map(|(pgrx::JsonB(row),)| row["price"].clone())
+            .collect()
+        };
+
+        let scaled = scan("scaled_int");
+        assert_eq!(
+            scaled[0],
+            serde_json::json!({ "unscaled": "12345", "scale": 2 })
+        );
+        assert_eq!(scaled[1], serde_json::Value::Null);
+        assert_eq!(
+            scaled[2],
+            serde_json::json!({
+                "unscaled": "-99999999999999999999999999999999999999",
+                "scale": 2
+            })
+        );
+
+        let numeric = scan("numeric");
+        assert_eq!(numeric[0], serde_json::json!(123.45));
+        assert_eq!(numeric[1], serde_json::Value::Null);
+        assert_eq!(numeric[2].as_f64(), Some(-1e36));
+
+        // Strings keep every digit that a JSON number would lose
+        let text = scan("text");
+        assert_eq!(text[0], serde_json::json!("123.45"));
+        assert_eq!(text[1], serde_json::Value::Null);
+        assert_eq!(
+            text[2],
+            serde_json::json!("-999999999999999999999999999999999999.99")
+        );
+    }
+
+    #[pg_test]
+    fn test_scan_mask_columns() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let rows: Vec<_> = crate::lance_scan_jsonb(
+            table_path_str,
+            None,
+            None,
+            None,
+            None,
+            None,
+            false,
+            None,
+            None,
+            "numeric",
+            Some(vec!["salary".to_string()]),
+        )
+        .map(|(pgrx::JsonB(row),)| row)
+        .collect();
+        assert_eq!(rows.len(), 5);
+        for row in &rows {
+            let object = row.as_object().unwrap();
+            assert!(object.contains_key("salary"));
+            assert_eq!(object["salary"], serde_json::Value::Null);
+        }
+        assert_eq!(rows[0]["name"], "Alice");
+        assert_eq!(rows[0]["age"], 25);
+
+        let caught = caught_sqlstate(|| {
+            crate::lance_scan_jsonb(
+                table_path_str,
+                None,
+                Some("id, name"),
+                None,
+                None,
+                None,
+                false,
+                None,
+                None,
+                "numeric",
+                Some(vec!["salary".to_string()]),
+            )
+            .count();
+        });
+        assert_eq!(caught, Some(PgSqlErrorCode::ERRCODE_UNDEFINED_COLUMN));
+
+        // Filters must not select rows by the hidden values, whether they read
+        // a masked column directly or the column behind a masked alias
+        let masked_filter_error = |select: Option<&'static str>,
+                                   filter: Option<&'static str>,
+                                   require_non_null: Option<&'static str>,
+                                   mask: &str| {
+            let mask = mask.to_string();
+            caught_sqlstate(|| {
+                crate::lance_scan_jsonb(
+                    table_path_str,
+                    None,
+                    select,
+                    None,
+                    filter,
+                    require_non_null,
+                    false,
+                    None,
+                    None,
+                    "numeric",
+                    Some(vec![mask.clone()]),
+                )
+                .count();
+            })
+        };
+        let denied = Some(PgSqlErrorCode::ERRCODE_INSUFFICIENT_PRIVILEGE);
+        assert_eq!(
+            masked_filter_error(None, Some("salary > 60000"), None, "salary"),
+            denied
+        );
+        assert_eq!(
+            masked_filter_error(None, None, Some("salary"), "salary"),
+            denied
+        );
+        assert_eq!(
+            masked_filter_error(
+                Some("name, salary * 2 AS pay"),
+                Some("salary > 60000"),
+                None,
+                "pay"
+            ),
+            denied
+        );
+        assert_eq!(
+            masked_filter_error(None, Some("age > 30"), None, "salary"),
+            None
+        );
+    }
+
+    #[pg_test]
+    fn test_lance_column_bounds() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let pgrx::JsonB(bounds) = crate::lance_column_bounds(table_path_str, "age");
+        assert_eq!(bounds["min"], 25);
+        assert_eq!(bounds["max"], 45);
+        assert_eq!(bounds["source"], "scan");
+        assert_eq!(bounds["exact"], true);
+
+        let pgrx::JsonB(bounds) = crate::lance_column_bounds(table_path_str, "name");
+        assert_eq!(bounds["min"], "Alice");
+        assert_eq!(bounds["max"], "Eve");
+    }
+
+    #[pg_test]
+    fn test_lance_append_jsonb_schema_evolution() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let row = serde_json::json!({
+            "id": 6, "name": "Frank", "age": 50, "salary": 70000.5, "is_active": true,
+            "team": "search"
+        });
 
-            let batch = RecordBatch::try_new(
-                schema.clone(),
-                vec![
-                    Arc::new(id_array),
-                    Arc::new(name_array),
-                    Arc::new(age_array),
-                    Arc::new(salary_array),
-                    Arc::new(is_active_array),
-                ],
-            )?;
+        let caught = caught_sqlstate(|| {
+            crate::insert::lance_append_jsonb(
+                table_path_str,
+                vec![pgrx::JsonB(row.clone())],
+                false,
+            );
+        });
+        assert_eq!(caught, Some(PgSqlErrorCode::ERRCODE_UNDEFINED_COLUMN));
 
-            // Use RecordBatchIterator for lance
-            let reader = arrow::record_batch::RecordBatchIterator::new(vec![Ok(batch)], schema);
+        let version =
+            crate::insert::lance_append_jsonb(table_path_str, vec![pgrx::JsonB(row)], true);
+        assert_eq!(version, 3);
 
-            // Use a new runtime for async operation
-            let rt = tokio::runtime::Runtime::new().unwrap();
-            rt.block_on(async {
-                Dataset::write(reader, table_path.to_str().unwrap(), None).await
-            })?;
+        let info: Vec<_> = crate::lance_table_info(table_path_str).collect();
+        let team = info
+            .iter()
+            .find(|(name, _, _)| name == "team")
+            .expect("team column was not added");
+        assert_eq!(team.1, "text");
+        assert!(team.2);
 
-            Ok(table_path)
-        }
+        let rows: Vec<_> = crate::lance_scan_jsonb(
+            table_path_str,
+            None,
+            None,
+            None,
+            None,
+            None,
+            false,
+            None,
+            None,
+            "numeric",
+            None,
+        )
+        .map(|(pgrx::JsonB(row),)| row)
+        .collect();
+        assert_eq!(rows.len(), 6);
+        assert_eq!(rows[0]["team"], serde_json::Value::Null);
+        assert_eq!(rows[5]["team"], "search");
+    }
 
-        /// Create a table with vector embeddings
-        fn create_vector_table(&self) -> Result<std::path::PathBuf, Box<dyn std::error::Error>> {
-            let table_path = self.get_base_path().join("vector_table");
+    #[pg_test]
+    fn test_timestamp_json_session_timezone() {
+        use arrow::array::TimestampMicrosecondArray;
 
-            let id_array = Int32Array::from(vec![1, 2, 3]);
-            let document_array = StringArray::from(vec!["doc1", "doc2", "doc3"]);
+        // 2024-01-01 00:00:00 UTC
+        let micros = 1_704_067_200_000_000i64;
+        let aware = TimestampMicrosecondArray::from(vec![micros]).with_timezone("UTC");
+        let naive = TimestampMicrosecondArray::from(vec![micros]);
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("aware", aware.data_type().clone(), true),
+            Field::new("naive", naive.data_type().clone(), true),
+        ]));
+        let batch = RecordBatch::try_new(schema, vec![Arc::new(aware), Arc::new(naive)]).unwrap();
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .write_table("session_timestamps", batch)
+            .expect("Failed to create timestamp table");
+        let table_path_str = table_path.to_str().unwrap();
 
-            // Create vector embeddings as List array
-            let mut list_builder =
-                arrow::array::ListBuilder::new(arrow::array::Float32Builder::new());
+        let first_row = || -> serde_json::Value {
+            crate::lance_scan_jsonb(
+                table_path_str,
+                None,
+                None,
+                None,
+                None,
+                None,
+                false,
+                None,
+                None,
+                "numeric",
+                None,
+            )
+            .map(|(pgrx::JsonB(row),)| row)
+            .next()
+            .unwrap()
+        };
 
-            // Add each embedding vector
-            for embedding in [
-                vec![0.1, 0.2, 0.3, 0.4],
-                vec![0.5, 0.6, 0.7, 0.8],
-                vec![0.9, 1.0, 1.1, 1.2],
-            ] {
-                for value in embedding {
-                    list_builder.values().append_value(value);
-                }
-                list_builder.append(true);
-            }
-            let list_array = list_builder.finish();
+        Spi::run("SET TimeZone = 'Asia/Tokyo'").unwrap();
+        Spi::run("SET DateStyle = 'ISO, MDY'").unwrap();
+        let row = first_row();
+        assert_eq!(row["aware"], "2024-01-01 00:00:00 UTC");
+        assert_eq!(row["naive"], "2024-01-01 00:00:00");
 
-            let schema = Arc::new(Schema::new(vec![
-                Field::new("id", DataType::Int32, false),
-                Field::new("document", DataType::Utf8, false),
-                Field::new(
-                    "embedding",
-                    DataType::List(Arc::new(Field::new("item", DataType::Float32, true))),
-                    false,
-                ),
-            ]));
+        Spi::run("SET pglance.timestamp_json_timezone = 'session'").unwrap();
+        let row = first_row();
+        assert_eq!(row["aware"], "2024-01-01 09:00:00+09");
+        assert_eq!(row["naive"], "2024-01-01 00:00:00");
 
-            let batch = RecordBatch::try_new(
-                schema.clone(),
-                vec![
-                    Arc::new(id_array),
-                    Arc::new(document_array),
-                    Arc::new(list_array),
-                ],
-            )?;
+        Spi::run("SET pglance.timestamp_json_timezone = 'session_assume_utc'").unwrap();
+        let row = first_row();
+        assert_eq!(row["naive"], "2024-01-01 09:00:00+09");
 
-            let reader = arrow::record_batch::RecordBatchIterator::new(vec![Ok(batch)], schema);
+        Spi::run("RESET pglance.timestamp_json_timezone").unwrap();
+        Spi::run("RESET DateStyle").unwrap();
+        Spi::run("RESET TimeZone").unwrap();
+    }
 
-            let rt = tokio::runtime::Runtime::new().unwrap();
-            rt.block_on(async {
-                Dataset::write(reader, table_path.to_str().unwrap(), None).await
-            })?;
+    #[pg_test]
+    fn test_lance_get_cell() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let table_path_str = table_path.to_str().unwrap();
 
-            Ok(table_path)
+        let pgrx::JsonB(name) = crate::lance_get_cell(table_path_str, 1, "name");
+        assert_eq!(name, serde_json::json!("Bob"));
+        let pgrx::JsonB(age) = crate::lance_get_cell(table_path_str, 4, "age");
+        assert_eq!(age, serde_json::json!(45));
+
+        for (rowid, column, expected) in [
+            (99, "name", PgSqlErrorCode::ERRCODE_NO_DATA_FOUND),
+            (-1, "name", PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE),
+            (1, "missing", PgSqlErrorCode::ERRCODE_UNDEFINED_COLUMN),
+        ] {
+            let caught = caught_sqlstate(|| {
+                crate::lance_get_cell(table_path_str, rowid, column);
+            });
+            assert_eq!(caught, Some(expected));
         }
     }
 
     #[pg_test]
-    fn test_hello_pglance() {
-        assert_eq!("Hello, pglance", crate::hello_pglance());
+    fn test_nested_booleans() {
+        use arrow::array::{ArrayRef, StructArray};
+        use arrow::datatypes::Fields;
+
+        let mut flags_builder = arrow::array::ListBuilder::new(arrow::array::BooleanBuilder::new());
+        flags_builder.values().append_value(true);
+        flags_builder.values().append_null();
+        flags_builder.append(true);
+        flags_builder.values().append_value(false);
+        flags_builder.append(true);
+        let flags = flags_builder.finish();
+
+        let settings_fields = Fields::from(vec![
+            Field::new("enabled", DataType::Boolean, true),
+            Field::new("flags", flags.data_type().clone(), true),
+        ]);
+        let settings = StructArray::new(
+            settings_fields.clone(),
+            vec![
+                Arc::new(BooleanArray::from(vec![true, false])) as ArrayRef,
+                Arc::new(flags) as ArrayRef,
+            ],
+            None,
+        );
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            "settings",
+            DataType::Struct(settings_fields),
+            true,
+        )]));
+        let batch = RecordBatch::try_new(schema, vec![Arc::new(settings)]).unwrap();
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .write_table("nested_booleans", batch)
+            .expect("Failed to create nested boolean table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let scanner = crate::scanner::LanceScanner::new(table_path_str).unwrap();
+        let batches = scanner.scan_with_filter(None, None).unwrap().batches;
+        let datum = crate::arrow_value_to_datum(batches[0].column(0).as_ref(), 0)
+            .unwrap()
+            .expect("struct value should not be NULL");
+        let pgrx::JsonB(settings) = unsafe { pgrx::JsonB::from_datum(datum, false) }.unwrap();
+        assert_eq!(
+            settings,
+            serde_json::json!({ "enabled": true, "flags": [true, null] })
+        );
+
+        let enabled = Spi::get_one::<Vec<Option<bool>>>(&format!(
+            "SELECT array_agg(enabled) FROM lance_scan_field('{}', 'settings.enabled') AS t(enabled boolean)",
+            table_path.display()
+        ))
+        .expect("field scan failed")
+        .expect("array is not null");
+        assert_eq!(enabled, vec![Some(true), Some(false)]);
+
+        let flags = Spi::get_one::<Vec<Option<bool>>>(&format!(
+            "SELECT flags FROM lance_scan_field('{}', 'settings.flags') AS t(flags boolean[]) LIMIT 1",
+            table_path.display()
+        ))
+        .expect("field scan failed")
+        .expect("flags are not null");
+        assert_eq!(flags, vec![Some(true), None]);
     }
 
     #[pg_test]
-    fn test_error_handling() {
-        // Test with invalid path
-        let result = std::panic::catch_unwind(|| {
-            let _: Vec<(String, String, bool)> =
-                crate::lance_table_info("/invalid/path/does/not/exist").collect::<Vec<_>>();
-        });
-        assert!(result.is_err());
+    fn test_lance_hash_sample() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_simple_table()
+            .expect("Failed to create simple table");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let ids = |remainder| -> Vec<i64> {
+            crate::lance_hash_sample(table_path_str, 2, remainder, None)
+                .map(|(pgrx::JsonB(row),)| row["id"].as_i64().unwrap())
+                .collect()
+        };
+        let even = ids(0);
+        let odd = ids(1);
+        assert!(even.iter().all(|id| !odd.contains(id)));
+        let mut all: Vec<i64> = even.iter().chain(&odd).copied().collect();
+        all.sort_unstable();
+        assert_eq!(all, vec![1, 2, 3, 4, 5]);
+        assert_eq!(ids(0), even);
+
+        let limited = crate::lance_hash_sample(table_path_str, 1, 0, Some(2)).count();
+        assert_eq!(limited, 2);
     }
 
     #[pg_test]
-    fn test_simple_table_integration() {
+    fn test_lance_fragment_stats() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator.get_base_path().join("uneven_fragments");
+        let table_path_str = table_path.to_str().unwrap();
+
+        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Int32, false)]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![Arc::new(Int32Array::from_iter_values(0..25))],
+        )
+        .unwrap();
+        let reader = arrow::record_batch::RecordBatchIterator::new(vec![Ok(batch)], schema);
+        let params = lance::dataset::WriteParams {
+            max_rows_per_file: 10,
+            max_rows_per_group: 10,
+            ..Default::default()
+        };
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(Dataset::write(reader, table_path_str, Some(params)))
+            .expect("Failed to write table");
+
+        let (num_fragments, avg_rows, min_rows, max_rows) =
+            crate::lance_fragment_stats(table_path_str).next().unwrap();
+        assert_eq!(num_fragments, 3);
+        assert!((avg_rows.unwrap() - 25.0 / 3.0).abs() < 1e-9);
+        assert_eq!(min_rows, Some(5));
+        assert_eq!(max_rows, Some(10));
+    }
+
+    #[pg_test]
+    fn test_large_string_empty_and_null() {
+        let labels = arrow::array::LargeStringArray::from(vec![Some("a"), Some(""), None]);
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("label", DataType::LargeUtf8, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![Arc::new(Int32Array::from(vec![1, 2, 3])), Arc::new(labels)],
+        )
+        .unwrap();
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .write_table("large_strings", batch)
+            .expect("Failed to create large string table");
+
+        let scanner = crate::scanner::LanceScanner::new(table_path.to_str().unwrap()).unwrap();
+        let batches = scanner.scan_with_filter(None, None).unwrap().batches;
+        let column = batches[0].column(1);
+        assert_eq!(column.data_type(), &DataType::LargeUtf8);
+
+        let empty = crate::arrow_value_to_datum(column.as_ref(), 1)
+            .unwrap()
+            .expect("empty string must not be NULL");
+        assert_eq!(
+            unsafe { String::from_datum(empty, false) },
+            Some(String::new())
+        );
+        assert!(crate::arrow_value_to_datum(column.as_ref(), 2)
+            .unwrap()
+            .is_none());
+
+        let is_empty = Spi::get_one::<bool>(&format!(
+            "SELECT label = '' FROM lance_scan_typed('{}') AS t(id int4, label text) WHERE id = 2",
+            table_path.display()
+        ))
+        .expect("typed scan failed");
+        assert_eq!(is_empty, Some(true));
+        let is_null = Spi::get_one::<bool>(&format!(
+            "SELECT label IS NULL FROM lance_scan_typed('{}') AS t(id int4, label text) WHERE id = 3",
+            table_path.display()
+        ))
+        .expect("typed scan failed");
+        assert_eq!(is_null, Some(true));
+    }
+
+    #[pg_test]
+    fn test_lance_scan_sql() {
         let generator =
             LanceTestDataGenerator::new().expect("Failed to create test data generator");
         let table_path = generator
@@ -516,100 +7704,367 @@ mod tests {
             .expect("Failed to create simple table");
         let table_path_str = table_path.to_str().unwrap();
 
-        // Test table info
-        let table_info: Vec<(String, String, bool)> =
-            crate::lance_table_info(table_path_str).collect::<Vec<_>>();
-
-        assert_eq!(table_info.len(), 5);
+        let names: Vec<_> = crate::lance_scan_sql(
+            table_path_str,
+            "SELECT name FROM t WHERE age > 30 ORDER BY age",
+            None,
+            None,
+        )
+        .map(|(pgrx::JsonB(row),)| row)
+        .collect();
+        assert_eq!(
+            names,
+            vec![
+                serde_json::json!({ "name": "Charlie" }),
+                serde_json::json!({ "name": "David" }),
+                serde_json::json!({ "name": "Eve" }),
+            ]
+        );
 
-        // Check specific columns
-        let id_column = table_info.iter().find(|(name, _, _)| name == "id").unwrap();
-        assert_eq!(id_column.1, "int4");
-        assert!(!id_column.2); // not nullable
+        let pgrx::JsonB(total) = crate::lance_scan_sql(
+            table_path_str,
+            "SELECT count(*) AS n, max(age) AS oldest FROM t",
+            None,
+            None,
+        )
+        .next()
+        .unwrap()
+        .0;
+        assert_eq!(total, serde_json::json!({ "n": 5, "oldest": 45 }));
 
-        let name_column = table_info
-            .iter()
-            .find(|(name, _, _)| name == "name")
-            .unwrap();
-        assert_eq!(name_column.1, "text");
+        let caught = caught_sqlstate(|| {
+            crate::lance_scan_sql(table_path_str, "DROP TABLE t", None, None).count();
+        });
+        assert_eq!(caught, Some(PgSqlErrorCode::ERRCODE_SYNTAX_ERROR));
 
-        let salary_column = table_info
-            .iter()
-            .find(|(name, _, _)| name == "salary")
-            .unwrap();
-        assert_eq!(salary_column.1, "float4");
+        let youngest: Vec<_> = crate::lance_scan_sql(
+            table_path_str,
+            "SELECT name FROM t ORDER BY age",
+            Some(2),
+            Some(60_000),
+        )
+        .map(|(pgrx::JsonB(row),)| row)
+        .collect();
+        assert_eq!(
+            youngest,
+            vec![
+                serde_json::json!({ "name": "Alice" }),
+                serde_json::json!({ "name": "Bob" }),
+            ]
+        );
 
-        // Test table stats
-        let stats: Vec<(i64, i64, i32)> =
-            crate::lance_table_stats(table_path_str).collect::<Vec<_>>();
+        // Sort state and results share the memory budget
+        Spi::run("SET pglance.scan_mem_budget_bytes = 1").unwrap();
+        let caught = caught_sqlstate(|| {
+            crate::lance_scan_sql(table_path_str, "SELECT * FROM t ORDER BY name", None, None)
+                .count();
+        });
+        Spi::run("RESET pglance.scan_mem_budget_bytes").unwrap();
+        assert_eq!(caught, Some(PgSqlErrorCode::ERRCODE_OUT_OF_MEMORY));
+    }
 
-        assert_eq!(stats.len(), 1);
-        let (version, num_rows, num_columns) = stats[0];
-        assert!(version >= 1);
-        assert_eq!(num_rows, 5);
-        assert_eq!(num_columns, 5);
+    #[pg_test]
+    fn test_lance_scan_typed_composite_struct() {
+        use arrow::array::{ArrayRef, StructArray};
+        use arrow::buffer::NullBuffer;
+        use arrow::datatypes::Fields;
 
-        // Test data scanning
-        let data: Vec<(pgrx::JsonB,)> =
-            crate::lance_scan_jsonb(table_path_str, Some(3)).collect::<Vec<_>>();
+        let person_fields = Fields::from(vec![
+            Field::new("name", DataType::Utf8, true),
+            Field::new("age", DataType::Int32, true),
+        ]);
+        let person = StructArray::new(
+            person_fields.clone(),
+            vec![
+                Arc::new(StringArray::from(vec![
+                    Some("Ann"),
+                    Some("hidden"),
+                    Some("Ben"),
+                ])) as ArrayRef,
+                Arc::new(Int32Array::from(vec![Some(31), Some(7), None])) as ArrayRef,
+            ],
+            Some(NullBuffer::from(vec![true, false, true])),
+        );
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("person", DataType::Struct(person_fields), true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![Arc::new(Int32Array::from(vec![1, 2, 3])), Arc::new(person)],
+        )
+        .unwrap();
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .write_table("people", batch)
+            .expect("Failed to create people table");
 
-        assert_eq!(data.len(), 3);
+        Spi::run("CREATE TYPE person_t AS (name text, age int4)").unwrap();
+        let rows = Spi::get_one::<String>(&format!(
+            "SELECT string_agg(format('%s:%s:%s:%s', id, p IS NULL, (p).name, (p).age), ',' ORDER BY id) \
+             FROM lance_scan_typed('{}') AS t(id int4, p person_t)",
+            table_path.display()
+        ))
+        .expect("typed scan failed");
+        assert_eq!(rows.as_deref(), Some("1:f:Ann:31,2:t::,3:f:Ben:"));
 
-        // Verify first row data
-        let first_row = &data[0].0;
-        let json_value = &first_row.0;
-        assert_eq!(json_value["id"], 1);
-        assert_eq!(json_value["name"], "Alice");
-        assert_eq!(json_value["age"], 25);
-        // Use approximate comparison for floating point
-        let salary = json_value["salary"].as_f64().unwrap();
-        assert!((salary - 50000.5).abs() < 0.1);
-        assert_eq!(json_value["is_active"], true);
+        Spi::run("CREATE TYPE renamed_t AS (full_name text, age int4)").unwrap();
+        let caught = caught_error(|| {
+            Spi::run(&format!(
+                "SELECT * FROM lance_scan_typed('{}') AS t(id int4, p renamed_t)",
+                table_path.display()
+            ))
+            .ok();
+        });
+        let report = caught.expect("mismatched composite type was accepted");
+        assert_eq!(
+            report.sql_error_code(),
+            PgSqlErrorCode::ERRCODE_DATATYPE_MISMATCH
+        );
+        assert!(
+            report.message().contains("\"full_name\""),
+            "{}",
+            report.message()
+        );
     }
 
     #[pg_test]
-    fn test_vector_table_integration() {
+    fn test_lance_clone() {
         let generator =
             LanceTestDataGenerator::new().expect("Failed to create test data generator");
         let table_path = generator
-            .create_vector_table()
-            .expect("Failed to create vector table");
+            .create_simple_table()
+            .expect("Failed to create simple table");
         let table_path_str = table_path.to_str().unwrap();
+        let clone_path = generator.get_base_path().join("simple_clone");
+        let clone_path_str = clone_path.to_str().unwrap();
 
-        // Test table info
-        let table_info: Vec<(String, String, bool)> =
-            crate::lance_table_info(table_path_str).collect::<Vec<_>>();
+        let version = crate::lance_clone(table_path_str, clone_path_str, true);
+        assert_eq!(version, 1);
 
-        assert_eq!(table_info.len(), 3);
+        let scan = |path: &str| -> Vec<serde_json::Value> {
+            crate::lance_scan_jsonb(
+                path, None, None, None, None, None, false, None, None, "numeric", None,
+            )
+            .map(|(pgrx::JsonB(row),)| row)
+            .collect()
+        };
+        assert_eq!(scan(clone_path_str), scan(table_path_str));
 
-        // Check embedding column (should be a list type)
-        let embedding_column = table_info
-            .iter()
-            .find(|(name, _, _)| name == "embedding")
+        // The clone does not share files with the source
+        crate::lance_drop_table(table_path_str, true);
+        assert_eq!(scan(clone_path_str).len(), 5);
+
+        let caught = caught_sqlstate(|| {
+            crate::lance_clone(clone_path_str, clone_path_str, true);
+        });
+        assert_eq!(caught, Some(PgSqlErrorCode::ERRCODE_DUPLICATE_OBJECT));
+    }
+
+    #[pg_test]
+    fn test_lance_scan_typed_list_as_json_text_array() {
+        use arrow::array::{ArrayRef, ListArray, StructArray};
+        use arrow::buffer::{NullBuffer, OffsetBuffer};
+        use arrow::datatypes::Fields;
+
+        let item_fields = Fields::from(vec![
+            Field::new("sku", DataType::Utf8, true),
+            Field::new("qty", DataType::Int32, true),
+        ]);
+        let items = StructArray::new(
+            item_fields.clone(),
+            vec![
+                Arc::new(StringArray::from(vec![Some("a-1"), None, Some("b-2")])) as ArrayRef,
+                Arc::new(Int32Array::from(vec![Some(2), None, Some(5)])) as ArrayRef,
+            ],
+            Some(NullBuffer::from(vec![true, false, true])),
+        );
+        let item_field = Arc::new(Field::new("item", DataType::Struct(item_fields), true));
+        // Rows: [a-1, NULL], NULL, [b-2]
+        let lists = ListArray::new(
+            item_field.clone(),
+            OffsetBuffer::new(vec![0, 2, 2, 3].into()),
+            Arc::new(items),
+            Some(NullBuffer::from(vec![true, false, true])),
+        );
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("items", DataType::List(item_field), true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![Arc::new(Int32Array::from(vec![1, 2, 3])), Arc::new(lists)],
+        )
+        .unwrap();
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .write_table("orders", batch)
+            .expect("Failed to create orders table");
+
+        let first = Spi::get_one::<Vec<Option<String>>>(&format!(
+            "SELECT items FROM lance_scan_typed('{}') AS t(id int4, items text[]) WHERE id = 1",
+            table_path.display()
+        ))
+        .expect("typed scan failed")
+        .expect("list is not null");
+        assert_eq!(first.len(), 2);
+        let element: serde_json::Value =
+            serde_json::from_str(first[0].as_deref().unwrap()).unwrap();
+        assert_eq!(element, serde_json::json!({ "sku": "a-1", "qty": 2 }));
+        assert_eq!(first[1], None);
+
+        let missing = Spi::get_one::<bool>(&format!(
+            "SELECT items IS NULL FROM lance_scan_typed('{}') AS t(id int4, items text[]) WHERE id = 2",
+            table_path.display()
+        ))
+        .expect("typed scan failed");
+        assert_eq!(missing, Some(true));
+
+        let skus = Spi::get_one::<String>(&format!(
+            "SELECT string_agg(item::jsonb->>'sku', ',') \
+             FROM lance_scan_typed('{}') AS t(id int4, items text[]), unnest(items) AS item",
+            table_path.display()
+        ))
+        .expect("typed scan failed");
+        assert_eq!(skus.as_deref(), Some("a-1,b-2"));
+    }
+
+    #[pg_test]
+    fn test_version_to_i64_boundary() {
+        assert_eq!(crate::scanner::version_to_i64(1).unwrap(), 1);
+        assert_eq!(
+            crate::scanner::version_to_i64(i64::MAX as u64).unwrap(),
+            i64::MAX
+        );
+
+        let err = crate::scanner::version_to_i64(i64::MAX as u64 + 1).unwrap_err();
+        assert_eq!(err.code, PgSqlErrorCode::ERRCODE_NUMERIC_VALUE_OUT_OF_RANGE);
+        assert!(err.message.contains("9223372036854775808"));
+        assert!(crate::scanner::version_to_i64(u64::MAX).is_err());
+    }
+
+    #[pg_test]
+    fn test_typed_decimal_and_interval() {
+        use arrow::array::{Decimal128Array, IntervalMonthDayNanoArray};
+        use arrow::datatypes::IntervalMonthDayNano;
+
+        let amounts = Decimal128Array::from(vec![Some(12345), None, Some(-5)])
+            .with_precision_and_scale(10, 2)
             .unwrap();
-        assert!(embedding_column.1.contains("json")); // Lists are converted to JSON in PostgreSQL
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("amount", DataType::Decimal128(10, 2), true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![Arc::new(Int32Array::from(vec![1, 2, 3])), Arc::new(amounts)],
+        )
+        .unwrap();
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .write_table("amounts", batch)
+            .expect("Failed to create amounts table");
 
-        // Test data scanning with limit
-        let data: Vec<(pgrx::JsonB,)> =
-            crate::lance_scan_jsonb(table_path_str, Some(2)).collect::<Vec<_>>();
+        let amounts = Spi::get_one::<String>(&format!(
+            "SELECT string_agg(coalesce(amount::text, 'NULL'), ',' ORDER BY id) \
+             FROM lance_scan_typed('{}') AS t(id int4, amount numeric)",
+            table_path.display()
+        ))
+        .expect("typed scan failed");
+        assert_eq!(amounts.as_deref(), Some("123.45,NULL,-0.05"));
+        let total = Spi::get_one::<pgrx::AnyNumeric>(&format!(
+            "SELECT sum(amount) FROM lance_scan_field('{}', 'amount') AS t(amount numeric)",
+            table_path.display()
+        ))
+        .expect("field scan failed")
+        .expect("sum is not null");
+        assert_eq!(total.to_string(), "123.40");
 
-        assert_eq!(data.len(), 2);
+        // Interval columns go through the same datum conversion as typed scans
+        let intervals =
+            IntervalMonthDayNanoArray::from(vec![IntervalMonthDayNano::new(1, 2, -1_500_000_000)]);
+        let datum = crate::arrow_value_to_datum(&intervals, 0)
+            .unwrap()
+            .expect("interval is not null");
+        let interval = unsafe { pgrx::datum::Interval::from_datum(datum, false) }.unwrap();
+        assert_eq!(
+            interval,
+            pgrx::datum::Interval::new(1, 2, -1_500_000).unwrap()
+        );
+        assert_eq!(
+            crate::arrow_value_to_serde_json(&intervals, 0).unwrap(),
+            serde_json::json!("P1M2DT-1.5S")
+        );
+    }
 
-        // Verify first row has vector data
-        let first_row = &data[0].0;
-        let json_value = &first_row.0;
-        assert_eq!(json_value["id"], 1);
-        assert_eq!(json_value["document"], "doc1");
+    #[pg_test]
+    fn test_lance_changes_since_removed_fragments() {
+        let generator =
+            LanceTestDataGenerator::new().expect("Failed to create test data generator");
+        let table_path = generator
+            .create_nullable_table()
+            .expect("Failed to create nullable table");
+        let table_path_str = table_path.to_str().unwrap();
 
-        // Check that embedding is an array
-        assert!(json_value["embedding"].is_array());
-        let embedding = json_value["embedding"].as_array().unwrap();
-        assert_eq!(embedding.len(), 4);
-        // Use approximate comparison for floating point values
-        let val0 = embedding[0].as_f64().unwrap();
-        let val1 = embedding[1].as_f64().unwrap();
-        assert!((val0 - 0.1).abs() < 0.01);
-        assert!((val1 - 0.2).abs() < 0.01);
+        let appended = RecordBatch::try_new(
+            Arc::new(Schema::new(vec![
+                Field::new("id", DataType::Int32, false),
+                Field::new("score", DataType::Float64, true),
+            ])),
+            vec![
+                Arc::new(Int32Array::from(vec![5, 6])),
+                Arc::new(arrow::array::Float64Array::from(vec![Some(2.5), None])),
+            ],
+        )
+        .unwrap();
+        let scanner = crate::scanner::LanceScanner::new(table_path_str).unwrap();
+        let version = scanner
+            .append(appended.schema(), vec![appended])
+            .expect("append failed");
+        assert_eq!(version, 2);
+
+        // Deleting every row of a fragment removes the fragment
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let mut dataset = Dataset::open(table_path_str).await.unwrap();
+            dataset.delete("id >= 5").await.unwrap();
+        });
+        let mut deleted: Vec<_> = crate::lance_changes_since(table_path_str, 2, None)
+            .map(|(change_type, pgrx::JsonB(row))| (change_type, row["id"].clone()))
+            .collect();
+        deleted.sort_by_key(|(_, id)| id.as_i64());
+        assert_eq!(
+            deleted,
+            vec![
+                ("deleted".to_string(), serde_json::json!(5)),
+                ("deleted".to_string(), serde_json::json!(6)),
+            ]
+        );
+
+        // Compaction moves surviving rows into new fragments
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let mut dataset = Dataset::open(table_path_str).await.unwrap();
+            dataset.delete("id = 1").await.unwrap();
+            lance::dataset::optimize::compact_files(
+                &mut dataset,
+                lance::dataset::optimize::CompactionOptions {
+                    materialize_deletions_threshold: 0.0,
+                    ..Default::default()
+                },
+                None,
+            )
+            .await
+            .unwrap();
+        });
+        let caught = caught_sqlstate(|| {
+            crate::lance_changes_since(table_path_str, 1, None).count();
+        });
+        assert_eq!(
+            caught,
+            Some(PgSqlErrorCode::ERRCODE_OBJECT_NOT_IN_PREREQUISITE_STATE)
+        );
     }
 }
 
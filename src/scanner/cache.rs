@@ -0,0 +1,104 @@
+use lance::Dataset;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+use super::{LanceTableStats, OpenOptions};
+
+/// Per-backend cache of opened datasets, keyed by URI and storage options
+///
+/// Backends and parallel workers are separate processes, so each has its own
+/// cache and the lock only guards threads within one process. Lookups far
+/// outnumber inserts, so readers take a shared lock and never wait for each
+/// other.
+static DATASET_CACHE: OnceLock<RwLock<HashMap<String, Dataset>>> = OnceLock::new();
+
+fn dataset_cache() -> &'static RwLock<HashMap<String, Dataset>> {
+    DATASET_CACHE.get_or_init(Default::default)
+}
+
+/// Build the cache key for a dataset URI opened with the given options
+pub fn dataset_cache_key(uri: &str, options: &OpenOptions) -> String {
+    let mut storage_options: Vec<_> = options.storage_options.iter().collect();
+    storage_options.sort();
+
+    let mut key = uri.to_string();
+    for (name, value) in storage_options {
+        key.push_str(&format!("|{}={}", name, value));
+    }
+    let mut headers: Vec<_> = options.http_headers.iter().collect();
+    headers.sort();
+    for (name, value) in headers {
+        key.push_str(&format!("|#header.{}={}", name, value));
+    }
+    if let Some(size) = options.metadata_cache_size {
+        key.push_str(&format!("|#metadata_cache_size={}", size));
+    }
+    if let Some(size) = options.index_cache_size {
+        key.push_str(&format!("|#index_cache_size={}", size));
+    }
+    key
+}
+
+/// Get a clone of a cached dataset handle
+pub fn cached_dataset(key: &str) -> Option<Dataset> {
+    dataset_cache().read().ok()?.get(key).cloned()
+}
+
+/// Store a dataset handle in the cache
+pub fn cache_dataset(key: String, dataset: Dataset) {
+    if let Ok(mut cache) = dataset_cache().write() {
+        cache.insert(key, dataset);
+    }
+}
+
+/// Forget every cached handle of the dataset at `uri`, whatever its options
+pub fn evict_dataset(uri: &str) {
+    if let Ok(mut cache) = dataset_cache().write() {
+        let prefix = format!("{}|", uri);
+        cache.retain(|key, _| key != uri && !key.starts_with(&prefix));
+    }
+    if let Ok(mut cache) = stats_cache().lock() {
+        cache.remove(uri);
+    }
+}
+
+/// Whether a dataset opened without storage options is cached for this path
+pub fn is_dataset_cached(table_path: &str) -> bool {
+    let key = dataset_cache_key(table_path, &OpenOptions::default());
+    dataset_cache()
+        .read()
+        .map(|cache| cache.contains_key(&key))
+        .unwrap_or(false)
+}
+
+/// Per-backend cache of table statistics, keyed by table path
+static STATS_CACHE: OnceLock<Mutex<HashMap<String, (Instant, LanceTableStats)>>> = OnceLock::new();
+
+/// Number of times statistics were computed rather than served from the cache
+static STATS_REFRESH_COUNT: AtomicU64 = AtomicU64::new(0);
+
+fn stats_cache() -> &'static Mutex<HashMap<String, (Instant, LanceTableStats)>> {
+    STATS_CACHE.get_or_init(Default::default)
+}
+
+/// Get cached statistics for a table if they are younger than `ttl`
+pub fn cached_stats(table_path: &str, ttl: Duration) -> Option<LanceTableStats> {
+    let cache = stats_cache().lock().ok()?;
+    let (cached_at, stats) = cache.get(table_path)?;
+    (cached_at.elapsed() < ttl).then(|| stats.clone())
+}
+
+/// Store freshly computed statistics for a table
+pub fn cache_stats(table_path: &str, stats: LanceTableStats) {
+    STATS_REFRESH_COUNT.fetch_add(1, Ordering::Relaxed);
+    if let Ok(mut cache) = stats_cache().lock() {
+        cache.insert(table_path.to_string(), (Instant::now(), stats));
+    }
+}
+
+/// Number of statistics refreshes performed in this backend
+pub fn stats_refresh_count() -> u64 {
+    STATS_REFRESH_COUNT.load(Ordering::Relaxed)
+}
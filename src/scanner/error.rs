@@ -0,0 +1,91 @@
+use std::fmt;
+
+use pgrx::PgSqlErrorCode;
+
+/// Why a scan could not be planned or executed
+///
+/// Each kind maps to its own SQLSTATE so clients can tell a bad predicate
+/// from a missing table without parsing the message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanError {
+    /// The filter expression could not be parsed or planned
+    PredicateParse(String),
+    /// The projection names unknown columns or contains invalid expressions
+    ProjectionInvalid(String),
+    /// The limit or offset was rejected
+    InvalidLimit(String),
+    /// Reading from the object store failed
+    Io(String),
+    /// A file the scan needed does not exist
+    NotFound(String),
+    /// The version being read does not exist or has been cleaned up
+    VersionInvalid(String),
+    /// The scan did not finish within its timeout
+    TimedOut(String),
+    /// The scanned batches outgrew `pglance.scan_mem_budget_bytes`
+    MemoryBudget(String),
+    /// A SQL query could not be parsed or planned, or is not a read-only query
+    QueryInvalid(String),
+}
+
+impl ScanError {
+    /// SQLSTATE raised for this error
+    pub fn code(&self) -> PgSqlErrorCode {
+        match self {
+            ScanError::PredicateParse(_) => PgSqlErrorCode::ERRCODE_SYNTAX_ERROR,
+            ScanError::ProjectionInvalid(_) => PgSqlErrorCode::ERRCODE_UNDEFINED_COLUMN,
+            ScanError::InvalidLimit(_) => PgSqlErrorCode::ERRCODE_INVALID_ROW_COUNT_IN_LIMIT_CLAUSE,
+            ScanError::Io(_) => PgSqlErrorCode::ERRCODE_IO_ERROR,
+            ScanError::NotFound(_) => PgSqlErrorCode::ERRCODE_UNDEFINED_FILE,
+            ScanError::VersionInvalid(_) => PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE,
+            ScanError::TimedOut(_) => PgSqlErrorCode::ERRCODE_QUERY_CANCELED,
+            ScanError::MemoryBudget(_) => PgSqlErrorCode::ERRCODE_OUT_OF_MEMORY,
+            ScanError::QueryInvalid(_) => PgSqlErrorCode::ERRCODE_SYNTAX_ERROR,
+        }
+    }
+
+    /// Message of the underlying Lance error
+    pub fn message(&self) -> &str {
+        match self {
+            ScanError::PredicateParse(message)
+            | ScanError::ProjectionInvalid(message)
+            | ScanError::InvalidLimit(message)
+            | ScanError::Io(message)
+            | ScanError::NotFound(message)
+            | ScanError::VersionInvalid(message)
+            | ScanError::TimedOut(message)
+            | ScanError::MemoryBudget(message)
+            | ScanError::QueryInvalid(message) => message,
+        }
+    }
+
+    /// Classify an error raised while executing a scan
+    pub fn from_lance(e: lance::Error) -> Self {
+        match e {
+            lance::Error::DatasetNotFound { .. } | lance::Error::NotFound { .. } => {
+                ScanError::NotFound(e.to_string())
+            }
+            lance::Error::VersionNotFound { .. } => ScanError::VersionInvalid(e.to_string()),
+            _ => ScanError::Io(e.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            ScanError::PredicateParse(_) => "invalid filter",
+            ScanError::ProjectionInvalid(_) => "invalid projection",
+            ScanError::InvalidLimit(_) => "invalid limit",
+            ScanError::Io(_) => "I/O error",
+            ScanError::NotFound(_) => "not found",
+            ScanError::VersionInvalid(_) => "invalid version",
+            ScanError::TimedOut(_) => "timed out",
+            ScanError::MemoryBudget(_) => "memory budget exceeded",
+            ScanError::QueryInvalid(_) => "invalid query",
+        };
+        write!(f, "{}: {}", kind, self.message())
+    }
+}
+
+impl std::error::Error for ScanError {}
@@ -0,0 +1,213 @@
+use arrow::datatypes::{DataType, SchemaRef};
+use lance_datafusion::planner::Planner;
+use serde_json::Value;
+use std::collections::HashSet;
+
+/// Quote a column name so it can be used safely inside a Lance filter expression
+pub fn quote_identifier(name: &str) -> String {
+    format!("`{}`", name.replace('`', "``"))
+}
+
+/// Quote a string literal for a Lance filter expression
+pub fn quote_string_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Render a JSON value as a Lance filter literal matching the column's Arrow type
+pub fn json_to_filter_literal(
+    value: &Value,
+    data_type: &DataType,
+) -> Result<String, pgrx::PgSqlErrorCode> {
+    match (data_type, value) {
+        (DataType::Boolean, Value::Bool(b)) => Ok(b.to_string()),
+        (
+            DataType::Int8
+            | DataType::Int16
+            | DataType::Int32
+            | DataType::Int64
+            | DataType::UInt8
+            | DataType::UInt16
+            | DataType::UInt32
+            | DataType::UInt64,
+            Value::Number(n),
+        ) if n.is_i64() || n.is_u64() => Ok(n.to_string()),
+        (DataType::Float16 | DataType::Float32 | DataType::Float64, Value::Number(n)) => {
+            Ok(n.to_string())
+        }
+        (DataType::Utf8 | DataType::LargeUtf8, Value::String(s)) => Ok(quote_string_literal(s)),
+        (DataType::Date32 | DataType::Date64, Value::String(s)) => {
+            Ok(format!("date {}", quote_string_literal(s)))
+        }
+        (DataType::Timestamp(_, _), Value::String(s)) => {
+            Ok(format!("timestamp {}", quote_string_literal(s)))
+        }
+        (DataType::Dictionary(_, value_type), _) => json_to_filter_literal(value, value_type),
+        _ => Err(pgrx::PgSqlErrorCode::ERRCODE_DATATYPE_MISMATCH),
+    }
+}
+
+/// Build an equality predicate `column = literal` for a Lance filter
+pub fn equality_predicate(
+    column: &str,
+    value: &Value,
+    data_type: &DataType,
+) -> Result<String, pgrx::PgSqlErrorCode> {
+    let column = quote_identifier(column);
+    if value.is_null() {
+        return Ok(format!("{} IS NULL", column));
+    }
+    let literal = json_to_filter_literal(value, data_type)?;
+    Ok(format!("{} = {}", column, literal))
+}
+
+/// Maximum number of literals placed in a single `IN (...)` clause
+pub const IN_LIST_CHUNK_SIZE: usize = 1000;
+
+/// Build a `column IN (...)` predicate for a Lance filter
+///
+/// Long value lists are split into several `IN` clauses of at most
+/// `IN_LIST_CHUNK_SIZE` literals joined with `OR`. A JSON `null` in the list
+/// matches NULL values. Returns `None` when `values` is empty.
+pub fn in_list_predicate(
+    column: &str,
+    values: &[Value],
+    data_type: &DataType,
+) -> Result<Option<String>, pgrx::PgSqlErrorCode> {
+    let quoted = quote_identifier(column);
+    let literals = values
+        .iter()
+        .filter(|v| !v.is_null())
+        .map(|v| json_to_filter_literal(v, data_type))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut clauses: Vec<String> = literals
+        .chunks(IN_LIST_CHUNK_SIZE)
+        .map(|chunk| format!("{} IN ({})", quoted, chunk.join(", ")))
+        .collect();
+    if values.iter().any(Value::is_null) {
+        clauses.push(format!("{} IS NULL", quoted));
+    }
+
+    Ok(match clauses.len() {
+        0 => None,
+        1 => clauses.pop(),
+        _ => Some(
+            clauses
+                .iter()
+                .map(|c| format!("({})", c))
+                .collect::<Vec<_>>()
+                .join(" OR "),
+        ),
+    })
+}
+
+/// Top-level columns of `schema` read by a Lance SQL expression
+///
+/// Nested field references count as reads of their top-level column. Fails
+/// with `ERRCODE_SYNTAX_ERROR` when the expression does not parse.
+pub fn referenced_columns(
+    expr: &str,
+    schema: SchemaRef,
+) -> Result<HashSet<String>, pgrx::PgSqlErrorCode> {
+    let planner = Planner::new(schema.clone());
+    let expr = planner
+        .parse_expr(expr)
+        .map_err(|_e| pgrx::PgSqlErrorCode::ERRCODE_SYNTAX_ERROR)?;
+    Ok(Planner::column_names_in_expr(&expr)
+        .into_iter()
+        .map(|name| match name.split_once('.') {
+            Some((column, _)) if schema.field_with_name(&name).is_err() => column.to_string(),
+            _ => name,
+        })
+        .collect())
+}
+
+/// Build an `IS NOT NULL` predicate for a column
+pub fn not_null_predicate(column: &str) -> String {
+    format!("{} IS NOT NULL", quote_identifier(column))
+}
+
+/// Combine filter expressions with `AND`, returning `None` when there are none
+pub fn and_filters<I>(filters: I) -> Option<String>
+where
+    I: IntoIterator<Item = String>,
+{
+    let filters: Vec<String> = filters.into_iter().collect();
+    match filters.len() {
+        0 => None,
+        1 => filters.into_iter().next(),
+        _ => Some(
+            filters
+                .iter()
+                .map(|f| format!("({})", f))
+                .collect::<Vec<_>>()
+                .join(" AND "),
+        ),
+    }
+}
+
+/// Render a JSON value as a Lance filter literal, inferring the type from the JSON value
+pub fn json_to_inferred_literal(value: &Value) -> Result<String, pgrx::PgSqlErrorCode> {
+    match value {
+        Value::Null => Ok("NULL".to_string()),
+        Value::Bool(b) => Ok(b.to_string()),
+        Value::Number(n) => Ok(n.to_string()),
+        Value::String(s) => Ok(quote_string_literal(s)),
+        Value::Array(_) | Value::Object(_) => {
+            Err(pgrx::PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE)
+        }
+    }
+}
+
+/// Substitute `$1`, `$2`, ... placeholders in a filter template with quoted literals
+///
+/// Placeholders inside quoted strings or backtick-quoted identifiers are left
+/// untouched, and every parameter is rendered as a single literal, so values
+/// can never change the structure of the predicate.
+pub fn substitute_params(template: &str, params: &[Value]) -> Result<String, pgrx::PgSqlErrorCode> {
+    let mut output = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+    let mut quote: Option<char> = None;
+
+    while let Some(ch) = chars.next() {
+        match quote {
+            Some(q) => {
+                if ch == q {
+                    quote = None;
+                }
+                output.push(ch);
+            }
+            None if ch == '\'' || ch == '`' || ch == '"' => {
+                quote = Some(ch);
+                output.push(ch);
+            }
+            None if ch == '$' => {
+                let mut digits = String::new();
+                while let Some(&d) = chars.peek() {
+                    if !d.is_ascii_digit() {
+                        break;
+                    }
+                    digits.push(d);
+                    chars.next();
+                }
+                if digits.is_empty() {
+                    return Err(pgrx::PgSqlErrorCode::ERRCODE_SYNTAX_ERROR);
+                }
+                let index: usize = digits
+                    .parse()
+                    .map_err(|_| pgrx::PgSqlErrorCode::ERRCODE_UNDEFINED_PARAMETER)?;
+                let value = index
+                    .checked_sub(1)
+                    .and_then(|i| params.get(i))
+                    .ok_or(pgrx::PgSqlErrorCode::ERRCODE_UNDEFINED_PARAMETER)?;
+                output.push_str(&json_to_inferred_literal(value)?);
+            }
+            None => output.push(ch),
+        }
+    }
+
+    if quote.is_some() {
+        return Err(pgrx::PgSqlErrorCode::ERRCODE_SYNTAX_ERROR);
+    }
+    Ok(output)
+}
@@ -1,51 +1,238 @@
 use arrow::record_batch::RecordBatch;
+use lance::dataset::builder::DatasetBuilder;
+use lance::dataset::scanner::ColumnOrdering;
+use lance::dataset::ReadParams;
+use lance::io::{ObjectStoreParams, WrappingObjectStore};
 use lance::Dataset;
-use std::sync::Arc;
+use lance_index::DatasetIndexExt;
+use lance_table::io::commit::RenameCommitHandler;
+use object_store::http::HttpBuilder;
+use object_store::{ClientOptions, DynObjectStore};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::ops::ControlFlow;
+use std::sync::{Arc, OnceLock};
 use tokio::runtime::Runtime;
+use xxhash_rust::xxh3::xxh3_64;
+
+use super::{
+    cache_dataset, cached_dataset, dataset_cache_key, evict_dataset, http_location, OpenOptions,
+    ReadConsistency, ScanError, ScanOptions, DELETED_COLUMN,
+};
+
+/// Most parts of a file `copy_table` uploads at once between stores
+const COPY_MAX_PENDING_PARTS: usize = 4;
+
+/// Most data file sizes `LanceScanner::data_file_sizes` requests at once,
+/// unless `pglance.io_threads` sets a lower limit
+const DATA_FILE_HEAD_CONCURRENCY: usize = 16;
+
+/// Longest `LanceScanner::sql` runs without calling back for interrupt checks
+const SQL_INTERRUPT_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
 
 /// Lance table scanner
+///
+/// The table version is resolved once, when the scanner is opened, and every
+/// scan and lookup through the scanner reads that version. Commits made by
+/// other processes afterwards are not seen, and since Lance never rewrites the
+/// files of a committed version, they cannot disturb a scan in progress.
 pub struct LanceScanner {
     dataset: Dataset,
     runtime: Arc<Runtime>,
     batch_size: usize,
+    io_threads: Option<usize>,
+    coalesce_batches: bool,
+    mem_budget: Option<usize>,
+    schema: Arc<arrow::datatypes::Schema>,
 }
 
 impl LanceScanner {
     /// Create a new Lance scanner
     pub fn new(table_path: &str) -> Result<Self, pgrx::PgSqlErrorCode> {
-        // Create async runtime
-        let runtime =
-            Arc::new(Runtime::new().map_err(|_e| pgrx::PgSqlErrorCode::ERRCODE_INTERNAL_ERROR)?);
+        Self::open(table_path, &OpenOptions::default())
+    }
+
+    /// Create a new Lance scanner with storage options
+    ///
+    /// Dataset handles are cached per backend. With `ReadConsistency::Latest` a
+    /// cached handle is refreshed to the latest version instead of reopening the
+    /// dataset from scratch; a pinned version is checked out from the cached
+    /// handle without looking for newer versions.
+    pub fn open(table_path: &str, options: &OpenOptions) -> Result<Self, pgrx::PgSqlErrorCode> {
+        options.check_credentials()?;
+        let runtime = shared_runtime()?;
+
+        let uri = options.resolve_uri(table_path)?;
+        let io_threads = configured_io_threads();
+        // The request limit is part of the object store, so handles opened
+        // under another `pglance.io_threads` cannot be shared
+        let mut cache_key = dataset_cache_key(&uri, options);
+        if let Some(io_threads) = io_threads {
+            cache_key.push_str(&format!("|#io_threads={}", io_threads));
+        }
+
+        let load_error = |e: lance::Error| match e {
+            // A wrong encryption key is rejected by the store as a permission
+            // failure, not reported as corrupt data
+            e if options.encrypted && is_access_denied(&e) => {
+                pgrx::PgSqlErrorCode::ERRCODE_INSUFFICIENT_PRIVILEGE
+            }
+            e => open_error(e),
+        };
 
         // Open dataset in async runtime
         let dataset = runtime.block_on(async {
-            Dataset::open(table_path)
-                .await
-                .map_err(|_e| pgrx::PgSqlErrorCode::ERRCODE_INTERNAL_ERROR)
+            match cached_dataset(&cache_key) {
+                Some(dataset) if options.consistency != ReadConsistency::Latest => Ok(dataset),
+                Some(mut dataset) => {
+                    dataset.checkout_latest().await.map_err(load_error)?;
+                    Ok(dataset)
+                }
+                None => {
+                    let request_limit = io_threads.map(RequestLimit);
+                    let mut builder = DatasetBuilder::from_uri(&uri);
+                    if let Some(request_limit) = &request_limit {
+                        builder = builder.with_read_params(ReadParams {
+                            store_options: Some(ObjectStoreParams {
+                                object_store_wrapper: Some(Arc::new(request_limit.clone())),
+                                ..Default::default()
+                            }),
+                            ..Default::default()
+                        });
+                    }
+                    builder = builder.with_storage_options(options.storage_options.clone());
+                    if let Some(location) = http_location(&uri)? {
+                        let (mut store, location) = http_object_store(location, options)?;
+                        if let Some(request_limit) = &request_limit {
+                            store = request_limit.wrap(store);
+                        }
+                        builder = builder.with_object_store(
+                            store,
+                            location,
+                            Arc::new(RenameCommitHandler),
+                        );
+                    }
+                    if let Some(size) = options.metadata_cache_size {
+                        builder = builder.with_metadata_cache_size(size);
+                    }
+                    if let Some(size) = options.index_cache_size {
+                        builder = builder.with_index_cache_size(size);
+                    }
+                    builder.load().await.map_err(load_error)
+                }
+            }
         })?;
+        cache_dataset(cache_key, dataset.clone());
+
+        let dataset = match options.consistency {
+            ReadConsistency::Version(version) if dataset.version().version != version => runtime
+                .block_on(dataset.checkout_version(version))
+                .map_err(open_error)?,
+            _ => dataset,
+        };
+
+        let schema = convert_schema(&dataset);
 
         Ok(Self {
             dataset,
             runtime,
             batch_size: 1024,
+            io_threads,
+            coalesce_batches: true,
+            mem_budget: configured_mem_budget(),
+            schema,
         })
     }
 
+    /// Set the maximum number of rows per scanned batch
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Limit how many fragments and batches a scan reads concurrently
+    ///
+    /// Defaults to `pglance.io_threads`; `None` keeps Lance's own defaults.
+    /// The object store's request limit is set from `pglance.io_threads` when
+    /// the table is opened and is not changed by this.
+    pub fn with_io_threads(mut self, io_threads: Option<usize>) -> Self {
+        self.io_threads = io_threads.map(|n| n.max(1));
+        self
+    }
+
+    /// Merge consecutive small batches up to the batch size, on by default
+    ///
+    /// Selective filters can leave only a few rows in each batch Lance reads,
+    /// and per-batch overhead then dominates converting the rows.
+    pub fn with_coalesce_batches(mut self, coalesce_batches: bool) -> Self {
+        self.coalesce_batches = coalesce_batches;
+        self
+    }
+
+    /// Fail scans holding more than this many bytes of record batches
+    ///
+    /// Defaults to `pglance.scan_mem_budget_bytes`; `None` disables the limit.
+    pub fn with_mem_budget(mut self, mem_budget: Option<usize>) -> Self {
+        self.mem_budget = mem_budget;
+        self
+    }
+
+    /// Concurrent IO requests per scan, if limited
+    pub fn io_threads(&self) -> Option<usize> {
+        self.io_threads
+    }
+
+    /// Description of the object store the table is read through
+    pub fn object_store_description(&self) -> String {
+        self.dataset.object_store().inner.to_string()
+    }
+
+    /// Batch size actually requested from Lance
+    ///
+    /// Capped at the number of physical rows recorded in the manifest, so a
+    /// batch size far larger than the table does not make Lance size buffers
+    /// for rows that do not exist.
+    pub fn effective_batch_size(&self) -> usize {
+        self.capped_batch_size(self.batch_size)
+    }
+
+    fn capped_batch_size(&self, batch_size: usize) -> usize {
+        let physical_rows: Option<usize> = self
+            .dataset
+            .fragments()
+            .iter()
+            .map(|fragment| fragment.physical_rows)
+            .sum();
+        match physical_rows {
+            Some(rows) => batch_size.min(rows.max(1)),
+            None => batch_size,
+        }
+    }
+
+    /// Load fragment metadata so the first real query does not pay for it
+    pub fn warmup(&self) -> Result<(), pgrx::PgSqlErrorCode> {
+        self.runtime.block_on(async {
+            self.dataset
+                .count_rows(None)
+                .await
+                .map(|_| ())
+                .map_err(|_e| pgrx::PgSqlErrorCode::ERRCODE_INTERNAL_ERROR)
+        })
+    }
+
+    /// Version of the table this scanner reads
+    pub fn version(&self) -> u64 {
+        self.dataset.version().version
+    }
+
     /// Get table schema
     pub fn schema(&self) -> Arc<arrow::datatypes::Schema> {
-        let lance_schema = self.dataset.schema();
-        let arrow_fields: Vec<Arc<arrow::datatypes::Field>> = lance_schema
-            .fields
-            .iter()
-            .map(|field| {
-                Arc::new(arrow::datatypes::Field::new(
-                    field.name.clone(),
-                    field.data_type().clone(),
-                    field.nullable,
-                ))
-            })
-            .collect();
-        Arc::new(arrow::datatypes::Schema::new(arrow_fields))
+        Arc::clone(&self.schema)
+    }
+
+    /// Scan the table as configured by `options`
+    pub fn scan(&self, options: &ScanOptions) -> Result<LanceScanIterator, ScanError> {
+        self.scan_batches(options.clone(), ScanExtras::default())
     }
 
     /// Scan with filter conditions
@@ -53,64 +240,851 @@ impl LanceScanner {
         &self,
         filter: Option<String>,
         limit: Option<i64>,
-    ) -> Result<LanceScanIterator, pgrx::PgSqlErrorCode> {
+    ) -> Result<LanceScanIterator, ScanError> {
+        self.scan_with_offset(filter, None, limit, None)
+    }
+
+    /// Scan with filter conditions and an optional `(alias, expression)` projection
+    pub fn scan_with_projection(
+        &self,
+        filter: Option<String>,
+        projection: Option<Vec<(String, String)>>,
+        limit: Option<i64>,
+    ) -> Result<LanceScanIterator, ScanError> {
+        self.scan_with_offset(filter, projection, limit, None)
+    }
+
+    /// Scan with filter conditions, projection, and a `limit`/`offset` window
+    pub fn scan_with_offset(
+        &self,
+        filter: Option<String>,
+        projection: Option<Vec<(String, String)>>,
+        limit: Option<i64>,
+        offset: Option<i64>,
+    ) -> Result<LanceScanIterator, ScanError> {
+        self.scan(&ScanOptions {
+            filter,
+            projection,
+            limit,
+            offset,
+            ..Default::default()
+        })
+    }
+
+    /// Scan all columns plus each row's address as a trailing `_rowaddr` column
+    ///
+    /// A row address is `fragment_id << 32 | offset_in_fragment`.
+    pub fn scan_with_row_address(
+        &self,
+        options: &ScanOptions,
+    ) -> Result<LanceScanIterator, ScanError> {
+        let extras = ScanExtras {
+            with_row_address: true,
+            ..Default::default()
+        };
+        self.scan_batches(options.clone(), extras)
+    }
+
+    /// Scan with row addresses, starting `offset` rows into the given fragment
+    ///
+    /// Reads that fragment and every fragment after it in manifest order. The
+    /// offset counts rows remaining after deletions.
+    pub fn scan_from_fragment(
+        &self,
+        fragment_id: u64,
+        offset: u64,
+        limit: i64,
+    ) -> Result<LanceScanIterator, ScanError> {
+        let fragments: Vec<_> = self
+            .fragments()
+            .into_iter()
+            .skip_while(|fragment| fragment.id != fragment_id)
+            .collect();
+        if fragments.is_empty() {
+            return Err(ScanError::NotFound(format!(
+                "fragment {} does not exist in version {}",
+                fragment_id,
+                self.version()
+            )));
+        }
+        let extras = ScanExtras {
+            with_row_address: true,
+            fragments: Some(fragments),
+            ..Default::default()
+        };
+        let options = ScanOptions {
+            limit: Some(limit),
+            offset: Some(offset as i64),
+            ..Default::default()
+        };
+        self.scan_batches(options, extras)
+    }
+
+    /// Scan only the given fragments, with row addresses
+    ///
+    /// Fragments are read in manifest order; ids that are not part of this
+    /// version are ignored.
+    pub fn scan_fragments(&self, fragment_ids: &[u64]) -> Result<LanceScanIterator, ScanError> {
+        let fragments: Vec<_> = self
+            .fragments()
+            .into_iter()
+            .filter(|fragment| fragment_ids.contains(&fragment.id))
+            .collect();
+        if fragments.is_empty() {
+            return Ok(LanceScanIterator::new(Vec::new()));
+        }
+        let extras = ScanExtras {
+            with_row_address: true,
+            fragments: Some(fragments),
+            ..Default::default()
+        };
+        self.scan_batches(ScanOptions::default(), extras)
+    }
+
+    /// Count the rows matching `filter`, stopping once `threshold` is reached
+    ///
+    /// Only row ids are read. `between_batches` runs after every batch so
+    /// callers can check for cancellation. Returns whether the threshold was
+    /// reached.
+    pub fn count_at_least(
+        &self,
+        filter: Option<String>,
+        threshold: u64,
+        mut between_batches: impl FnMut(),
+    ) -> Result<bool, ScanError> {
+        if threshold == 0 {
+            return Ok(true);
+        }
+        let extras = ScanExtras {
+            no_columns: true,
+            ..Default::default()
+        };
+        let options = ScanOptions {
+            filter,
+            ..Default::default()
+        };
+        let mut count = 0u64;
+        self.for_each_batch(options, extras, |batch| {
+            count += batch.num_rows() as u64;
+            between_batches();
+            if count >= threshold {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        })?;
+        Ok(count >= threshold)
+    }
+
+    /// Stream a scan to `visit` batch by batch, with a trailing `_rowid` column
+    ///
+    /// Batches are dropped once visited, so scans of any size can be reduced
+    /// in bounded memory.
+    pub fn for_each_batch_with_row_id(
+        &self,
+        options: ScanOptions,
+        visit: impl FnMut(RecordBatch) -> ControlFlow<()>,
+    ) -> Result<(), ScanError> {
+        let extras = ScanExtras {
+            with_row_id: true,
+            ..Default::default()
+        };
+        self.for_each_batch(options, extras, visit)
+    }
+
+    fn scan_batches(
+        &self,
+        options: ScanOptions,
+        extras: ScanExtras,
+    ) -> Result<LanceScanIterator, ScanError> {
+        let target_rows = if self.coalesce_batches {
+            self.batch_size_for(&options)
+        } else {
+            0
+        };
+        let mut coalescer = BatchCoalescer::new(target_rows);
+        let mut scan_error = None;
+        let mut held_bytes = 0usize;
+        self.for_each_batch(options, extras, |batch| {
+            held_bytes += batch.get_array_memory_size();
+            if let Err(e) = self.check_mem_budget(held_bytes) {
+                scan_error = Some(e);
+                return ControlFlow::Break(());
+            }
+            match coalescer.push(batch) {
+                Ok(()) => ControlFlow::Continue(()),
+                Err(e) => {
+                    scan_error = Some(ScanError::Io(e.to_string()));
+                    ControlFlow::Break(())
+                }
+            }
+        })?;
+        if let Some(e) = scan_error {
+            return Err(e);
+        }
+        let batches = coalescer
+            .finish()
+            .map_err(|e| ScanError::Io(e.to_string()))?;
+        Ok(LanceScanIterator::new(batches))
+    }
+
+    /// Batch size requested from Lance for a scan with these options
+    fn batch_size_for(&self, options: &ScanOptions) -> usize {
+        self.capped_batch_size(options.batch_size.unwrap_or(self.batch_size))
+    }
+
+    /// Stream the scan's batches into `visit` until it breaks or the scan ends
+    fn for_each_batch(
+        &self,
+        options: ScanOptions,
+        extras: ScanExtras,
+        mut visit: impl FnMut(RecordBatch) -> ControlFlow<()>,
+    ) -> Result<(), ScanError> {
         let runtime = Arc::clone(&self.runtime);
         let dataset = self.dataset.clone();
-        let batch_size = self.batch_size;
+        let batch_size = self.batch_size_for(&options);
+        let io_threads = self.io_threads;
+        let ScanOptions {
+            filter,
+            projection,
+            limit,
+            offset,
+            order_by,
+            batch_size: _,
+            include_deleted,
+            timeout,
+        } = options;
+        let has_filter = filter.is_some();
 
-        let batches = runtime.block_on(async move {
+        runtime.block_on(with_timeout(timeout, async move {
             let mut scan = dataset.scan();
 
             scan.batch_size(batch_size);
+            if let Some(io_threads) = io_threads {
+                scan.fragment_readahead(io_threads);
+                scan.batch_readahead(io_threads);
+            }
+
+            if extras.with_row_address {
+                scan.with_row_address();
+            }
+            if extras.with_row_id {
+                scan.with_row_id();
+            }
+
+            // Deleted rows keep their values but come back with a null row id
+            if include_deleted {
+                scan.with_row_id();
+                scan.include_deleted_rows();
+            }
+
+            if extras.no_columns {
+                scan.project::<&str>(&[])
+                    .map_err(|e| ScanError::ProjectionInvalid(e.to_string()))?;
+                if !extras.with_row_address {
+                    scan.with_row_id();
+                }
+            }
+
+            if let Some(fragments) = extras.fragments {
+                scan.with_fragments(fragments);
+            }
+
+            if let Some(columns) = projection {
+                scan.project_with_transform(&columns)
+                    .map_err(|e| ScanError::ProjectionInvalid(e.to_string()))?;
+            }
 
             if let Some(filter_expr) = filter {
                 scan.filter(&filter_expr)
-                    .map_err(|_e| pgrx::PgSqlErrorCode::ERRCODE_SYNTAX_ERROR)?;
+                    .map_err(|e| ScanError::PredicateParse(e.to_string()))?;
             }
 
-            if let Some(limit_val) = limit {
-                let _ = scan.limit(Some(limit_val), None);
+            if !order_by.is_empty() {
+                let ordering = order_by
+                    .into_iter()
+                    .map(|sort| match sort.ascending {
+                        true => ColumnOrdering::asc_nulls_last(sort.column),
+                        false => ColumnOrdering::desc_nulls_first(sort.column),
+                    })
+                    .collect();
+                scan.order_by(Some(ordering))
+                    .map_err(|e| ScanError::ProjectionInvalid(e.to_string()))?;
             }
 
-            let stream = scan
-                .try_into_stream()
-                .await
-                .map_err(|_e| pgrx::PgSqlErrorCode::ERRCODE_INTERNAL_ERROR)?;
+            if limit.is_some() || offset.is_some() {
+                scan.limit(limit, offset)
+                    .map_err(|e| ScanError::InvalidLimit(e.to_string()))?;
+            }
+
+            // Filters are only fully planned here, so planning failures with a
+            // filter present are reported as predicate errors
+            let stream = scan.try_into_stream().await.map_err(|e| match e {
+                lance::Error::InvalidInput { .. } | lance::Error::Schema { .. } if has_filter => {
+                    ScanError::PredicateParse(e.to_string())
+                }
+                e => ScanError::from_lance(e),
+            })?;
 
-            let mut batches = Vec::new();
             use futures::StreamExt;
 
             let mut stream = Box::pin(stream);
             while let Some(batch_result) = stream.next().await {
-                let batch =
-                    batch_result.map_err(|_e| pgrx::PgSqlErrorCode::ERRCODE_INTERNAL_ERROR)?;
-                batches.push(batch);
+                let mut batch = batch_result.map_err(ScanError::from_lance)?;
+                if include_deleted {
+                    batch = mark_deleted_rows(batch).map_err(|e| ScanError::Io(e.to_string()))?;
+                }
+                if visit(batch).is_break() {
+                    break;
+                }
             }
 
-            Ok::<Vec<RecordBatch>, pgrx::PgSqlErrorCode>(batches)
-        })?;
+            Ok::<(), ScanError>(())
+        }))
+    }
 
+    /// Randomly sample approximately `fraction` of the rows
+    ///
+    /// Picks `round(fraction * num_rows)` distinct row offsets with a seedable
+    /// RNG and fetches them with a single take, preserving table order.
+    pub fn sample(
+        &self,
+        fraction: f64,
+        seed: Option<u64>,
+    ) -> Result<LanceScanIterator, pgrx::PgSqlErrorCode> {
+        self.runtime.block_on(async {
+            let num_rows = self
+                .dataset
+                .count_rows(None)
+                .await
+                .map_err(|_e| pgrx::PgSqlErrorCode::ERRCODE_INTERNAL_ERROR)?;
+            let sample_size = ((num_rows as f64) * fraction).round() as usize;
+            if sample_size == 0 {
+                return Ok(LanceScanIterator::new(Vec::new()));
+            }
+
+            let mut rng = match seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy(),
+            };
+            let mut offsets: Vec<u64> =
+                rand::seq::index::sample(&mut rng, num_rows, sample_size.min(num_rows))
+                    .into_iter()
+                    .map(|i| i as u64)
+                    .collect();
+            offsets.sort_unstable();
+
+            let batch = self
+                .dataset
+                .take(&offsets, self.dataset.schema().clone())
+                .await
+                .map_err(|_e| pgrx::PgSqlErrorCode::ERRCODE_INTERNAL_ERROR)?;
+
+            Ok(LanceScanIterator::new(vec![batch]))
+        })
+    }
+
+    /// Rows whose hashed row id leaves `remainder` modulo `modulus`, in table order
+    ///
+    /// Row ids are hashed with XXH3, so the subset is the same on every call
+    /// without a seed, and the subsets for all remainders partition the table.
+    pub fn hash_sample(
+        &self,
+        modulus: u64,
+        remainder: u64,
+        limit: Option<i64>,
+    ) -> Result<LanceScanIterator, ScanError> {
+        let extras = ScanExtras {
+            with_row_id: true,
+            ..Default::default()
+        };
+        let mut remaining = limit.map(|limit| limit.max(0) as usize);
+        let mut batches = Vec::new();
+        let mut sample_error = None;
+        self.for_each_batch(ScanOptions::default(), extras, |batch| {
+            let mut sampled = match sample_by_row_id(&batch, modulus, remainder) {
+                Ok(sampled) => sampled,
+                Err(e) => {
+                    sample_error = Some(ScanError::Io(e.to_string()));
+                    return ControlFlow::Break(());
+                }
+            };
+            if let Some(remaining) = remaining.as_mut() {
+                sampled = sampled.slice(0, sampled.num_rows().min(*remaining));
+                *remaining -= sampled.num_rows();
+            }
+            if sampled.num_rows() > 0 {
+                batches.push(sampled);
+            }
+            match remaining {
+                Some(0) => ControlFlow::Break(()),
+                _ => ControlFlow::Continue(()),
+            }
+        })?;
+        if let Some(e) = sample_error {
+            return Err(e);
+        }
         Ok(LanceScanIterator::new(batches))
     }
 
+    /// Run a read-only DataFusion SQL query over the table, registered as `table_name`
+    ///
+    /// DDL, DML and other statements are rejected. `limit` is pushed into the
+    /// query plan. DataFusion's operators share a memory pool of the scan
+    /// memory budget, or PostgreSQL's `work_mem` when no budget is set, and
+    /// query results count against the budget like scan results do.
+    /// `between_batches` is called for every batch and at least every
+    /// `SQL_INTERRUPT_CHECK_INTERVAL` while the query runs, so callers can
+    /// check for interrupts.
+    pub fn sql(
+        &self,
+        table_name: &str,
+        query: &str,
+        limit: Option<i64>,
+        timeout: Option<std::time::Duration>,
+        mut between_batches: impl FnMut(),
+    ) -> Result<LanceScanIterator, ScanError> {
+        use datafusion::error::DataFusionError;
+        use datafusion::execution::context::{SQLOptions, SessionConfig, SessionContext};
+        use datafusion::execution::runtime_env::RuntimeEnvBuilder;
+        use futures::StreamExt;
+
+        let invalid = |e: DataFusionError| ScanError::QueryInvalid(e.to_string());
+        let failed = |e: DataFusionError| match e {
+            DataFusionError::ResourcesExhausted(message) => ScanError::MemoryBudget(message),
+            e => ScanError::Io(e.to_string()),
+        };
+        let limit = limit.map(|limit| limit.max(0) as usize);
+        let pool_bytes = self.mem_budget.unwrap_or_else(work_mem_bytes);
+
+        self.runtime.block_on(with_timeout(timeout, async {
+            let runtime_env = RuntimeEnvBuilder::new()
+                .with_memory_limit(pool_bytes, 1.0)
+                .build_arc()
+                .map_err(failed)?;
+            let ctx = SessionContext::new_with_config_rt(SessionConfig::new(), runtime_env);
+            let provider = lance::datafusion::LanceTableProvider::new(
+                Arc::new(self.dataset.clone()),
+                false,
+                false,
+            );
+            ctx.register_table(table_name, Arc::new(provider))
+                .map_err(invalid)?;
+            let options = SQLOptions::new()
+                .with_allow_ddl(false)
+                .with_allow_dml(false)
+                .with_allow_statements(false);
+            let mut frame = ctx
+                .sql_with_options(query, options)
+                .await
+                .map_err(invalid)?;
+            if let Some(limit) = limit {
+                frame = frame.limit(0, Some(limit)).map_err(invalid)?;
+            }
+            let mut stream = frame.execute_stream().await.map_err(failed)?;
+
+            let mut batches = Vec::new();
+            let mut held_bytes = 0usize;
+            let mut rows = 0usize;
+            let mut ticks = tokio::time::interval(SQL_INTERRUPT_CHECK_INTERVAL);
+            loop {
+                tokio::select! {
+                    batch = stream.next() => {
+                        let Some(batch) = batch else {
+                            break;
+                        };
+                        let batch = batch.map_err(failed)?;
+                        between_batches();
+                        held_bytes += batch.get_array_memory_size();
+                        self.check_mem_budget(held_bytes)?;
+                        rows += batch.num_rows();
+                        batches.push(batch);
+                        if limit.is_some_and(|limit| rows >= limit) {
+                            break;
+                        }
+                    }
+                    _ = ticks.tick() => between_batches(),
+                }
+            }
+            Ok(LanceScanIterator::new(batches))
+        }))
+    }
+
+    /// Fail once the batches held by a scan outgrow the memory budget
+    fn check_mem_budget(&self, held_bytes: usize) -> Result<(), ScanError> {
+        match self.mem_budget {
+            Some(budget) if held_bytes > budget => Err(ScanError::MemoryBudget(format!(
+                "scan holds {} bytes, over the budget of {} bytes",
+                held_bytes, budget
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Build the physical plan of a scan without executing it
+    pub fn explain_scan(
+        &self,
+        filter: Option<String>,
+        columns: Option<Vec<String>>,
+        limit: Option<i64>,
+    ) -> Result<ScanPlan, ScanError> {
+        self.runtime.block_on(async {
+            let mut scan = self.dataset.scan();
+
+            scan.batch_size(self.effective_batch_size());
+            if let Some(io_threads) = self.io_threads {
+                scan.fragment_readahead(io_threads);
+                scan.batch_readahead(io_threads);
+            }
+
+            if let Some(columns) = &columns {
+                scan.project(columns)
+                    .map_err(|e| ScanError::ProjectionInvalid(e.to_string()))?;
+            }
+
+            if let Some(filter_expr) = &filter {
+                scan.filter(filter_expr)
+                    .map_err(|e| ScanError::PredicateParse(e.to_string()))?;
+            }
+
+            if let Some(limit_val) = limit {
+                scan.limit(Some(limit_val), None)
+                    .map_err(|e| ScanError::InvalidLimit(e.to_string()))?;
+            }
+
+            let plan = scan.create_plan().await.map_err(ScanError::from_lance)?;
+            Ok(ScanPlan {
+                text: datafusion::physical_plan::displayable(plan.as_ref())
+                    .indent(true)
+                    .to_string(),
+                uses_index: plan_uses_index(plan.as_ref()),
+            })
+        })
+    }
+
+    /// Append record batches as a new version of the table
+    ///
+    /// The batches must have exactly the table's column names and types, in
+    /// order. Returns the version created by the append.
+    pub fn append(
+        &self,
+        schema: Arc<arrow::datatypes::Schema>,
+        batches: Vec<RecordBatch>,
+    ) -> Result<u64, pgrx::PgSqlErrorCode> {
+        let matches_table = schema.fields().len() == self.schema.fields().len()
+            && schema
+                .fields()
+                .iter()
+                .zip(self.schema.fields())
+                .all(|(new, existing)| {
+                    new.name() == existing.name() && new.data_type() == existing.data_type()
+                });
+        if !matches_table {
+            return Err(pgrx::PgSqlErrorCode::ERRCODE_DATATYPE_MISMATCH);
+        }
+
+        let mut dataset = self.dataset.clone();
+        let reader =
+            arrow::record_batch::RecordBatchIterator::new(batches.into_iter().map(Ok), schema);
+        self.runtime.block_on(async {
+            dataset.append(reader, None).await.map_err(|e| match e {
+                lance::Error::Schema { .. } => pgrx::PgSqlErrorCode::ERRCODE_DATATYPE_MISMATCH,
+                _ => pgrx::PgSqlErrorCode::ERRCODE_INTERNAL_ERROR,
+            })
+        })?;
+        Ok(dataset.version().version)
+    }
+
+    /// Add nullable columns that are NULL in every existing row
+    ///
+    /// Returns the table version created by the schema change.
+    pub fn add_null_columns(
+        &self,
+        schema: Arc<arrow::datatypes::Schema>,
+    ) -> Result<u64, pgrx::PgSqlErrorCode> {
+        let mut dataset = self.dataset.clone();
+        self.runtime.block_on(async {
+            dataset
+                .add_columns(
+                    lance::dataset::NewColumnTransform::AllNulls(schema),
+                    None,
+                    None,
+                )
+                .await
+                .map_err(|e| match e {
+                    lance::Error::Schema { .. } | lance::Error::InvalidInput { .. } => {
+                        pgrx::PgSqlErrorCode::ERRCODE_DATATYPE_MISMATCH
+                    }
+                    _ => pgrx::PgSqlErrorCode::ERRCODE_INTERNAL_ERROR,
+                })
+        })?;
+        Ok(dataset.version().version)
+    }
+
+    /// Delete every file of the table, including all of its versions
+    ///
+    /// Local tables are removed from the file system and remote ones by
+    /// deleting everything under the table's prefix. Cached handles of the
+    /// table are dropped so that later opens fail. A table at the root of a
+    /// bucket is refused with `ERRCODE_INVALID_PARAMETER_VALUE`, since its
+    /// prefix is the whole bucket.
+    pub fn remove_table(self, table_path: &str) -> Result<(), pgrx::PgSqlErrorCode> {
+        let base = self.base_path();
+        if base.parts().next().is_none() {
+            return Err(pgrx::PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE);
+        }
+        self.runtime.block_on(async {
+            self.dataset
+                .object_store()
+                .remove_dir_all(base)
+                .await
+                .map_err(|_e| pgrx::PgSqlErrorCode::ERRCODE_IO_ERROR)
+        })?;
+        evict_dataset(table_path);
+        Ok(())
+    }
+
+    /// Copy every file of the table, including all of its versions, to `dest_uri`
+    ///
+    /// Files are copied by the object store when the destination is on the
+    /// same store as the table, and streamed through otherwise. Fails with
+    /// `ERRCODE_DUPLICATE_OBJECT` when the destination already holds files,
+    /// and with `ERRCODE_INVALID_PARAMETER_VALUE` for a table at the root of
+    /// a bucket. Returns the number of files copied.
+    pub fn copy_table(&self, dest_uri: &str) -> Result<u64, pgrx::PgSqlErrorCode> {
+        use futures::StreamExt;
+
+        let io_error = |_e: object_store::Error| pgrx::PgSqlErrorCode::ERRCODE_IO_ERROR;
+        let base = self.base_path();
+        if base.parts().next().is_none() {
+            return Err(pgrx::PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE);
+        }
+        let same_store = store_root(self.dataset.uri()) == store_root(dest_uri);
+        self.runtime.block_on(async {
+            let (dest, dest_base) = lance::io::ObjectStore::from_uri(dest_uri)
+                .await
+                .map_err(|_e| pgrx::PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE)?;
+            if dest.inner.list(Some(&dest_base)).next().await.is_some() {
+                return Err(pgrx::PgSqlErrorCode::ERRCODE_DUPLICATE_OBJECT);
+            }
+
+            let source = &self.dataset.object_store().inner;
+            let mut objects = source.list(Some(&base));
+            let mut copied = 0u64;
+            while let Some(object) = objects.next().await {
+                let from = object.map_err(io_error)?.location;
+                let Some(relative) = from.prefix_match(&base) else {
+                    continue;
+                };
+                let to: object_store::path::Path = dest_base.parts().chain(relative).collect();
+                if same_store {
+                    source.copy(&from, &to).await.map_err(io_error)?;
+                } else {
+                    let upload = dest.inner.put_multipart(&to).await.map_err(io_error)?;
+                    let mut writer = object_store::WriteMultipart::new(upload);
+                    let mut chunks = source.get(&from).await.map_err(io_error)?.into_stream();
+                    while let Some(chunk) = chunks.next().await {
+                        writer
+                            .wait_for_capacity(COPY_MAX_PENDING_PARTS)
+                            .await
+                            .map_err(io_error)?;
+                        writer.write(&chunk.map_err(io_error)?);
+                    }
+                    writer.finish().await.map_err(io_error)?;
+                }
+                copied += 1;
+            }
+            Ok(copied)
+        })
+    }
+
+    /// Object store path of the table's root directory
+    fn base_path(&self) -> object_store::path::Path {
+        let data_dir = self.dataset.data_dir();
+        let parts: Vec<_> = data_dir.parts().collect();
+        parts[..parts.len().saturating_sub(1)]
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Read the given columns of rows by row id, in the order of `row_ids`
+    ///
+    /// Fails with `ERRCODE_NO_DATA_FOUND` when any row id does not exist.
+    pub fn take_rows(
+        &self,
+        row_ids: &[u64],
+        columns: &[&str],
+    ) -> Result<RecordBatch, pgrx::PgSqlErrorCode> {
+        let projection = self
+            .dataset
+            .schema()
+            .project(columns)
+            .map_err(|_e| pgrx::PgSqlErrorCode::ERRCODE_UNDEFINED_COLUMN)?;
+        let batch = self
+            .runtime
+            .block_on(self.dataset.take_rows(row_ids, &projection))
+            .map_err(|e| match e {
+                lance::Error::InvalidInput { .. } | lance::Error::NotFound { .. } => {
+                    pgrx::PgSqlErrorCode::ERRCODE_NO_DATA_FOUND
+                }
+                _ => pgrx::PgSqlErrorCode::ERRCODE_INTERNAL_ERROR,
+            })?;
+        if batch.num_rows() != row_ids.len() {
+            return Err(pgrx::PgSqlErrorCode::ERRCODE_NO_DATA_FOUND);
+        }
+        Ok(batch)
+    }
+
+    /// List the fragments of the current version
+    pub fn fragments(&self) -> Vec<lance_table::format::Fragment> {
+        self.dataset.fragments().as_ref().clone()
+    }
+
+    /// Fully qualified location of a data file referenced by the manifest
+    ///
+    /// Paths are normally relative to the table's `data/` directory. Datasets
+    /// assembled from several buckets may instead reference files by full URI,
+    /// which is returned unchanged.
+    pub fn data_file_uri(&self, path: &str) -> String {
+        if is_qualified_uri(path) {
+            return path.to_string();
+        }
+        format!("{}/data/{}", self.dataset.uri().trim_end_matches('/'), path)
+    }
+
+    /// Size in bytes of every data file, with the field ids stored in it
+    ///
+    /// Only the object store's metadata is read, not the files themselves.
+    pub fn data_file_sizes(&self) -> Result<Vec<(Vec<i32>, u64)>, pgrx::PgSqlErrorCode> {
+        use futures::{StreamExt, TryStreamExt};
+
+        let data_dir = self.dataset.data_dir();
+        let files: Vec<_> = self
+            .dataset
+            .fragments()
+            .iter()
+            .flat_map(|fragment| fragment.files.iter())
+            .collect();
+        // The table's object store cannot reach files kept in other buckets
+        if files.iter().any(|file| is_qualified_uri(&file.path)) {
+            return Err(pgrx::PgSqlErrorCode::ERRCODE_FEATURE_NOT_SUPPORTED);
+        }
+        let object_store = self.dataset.object_store();
+        let concurrency = self.io_threads.unwrap_or(DATA_FILE_HEAD_CONCURRENCY);
+        self.runtime.block_on(
+            futures::stream::iter(files)
+                .map(|file| {
+                    let path = data_dir.child(file.path.as_str());
+                    async move {
+                        let size = object_store
+                            .size(&path)
+                            .await
+                            .map_err(|_e| pgrx::PgSqlErrorCode::ERRCODE_IO_ERROR)?;
+                        Ok((file.fields.clone(), size as u64))
+                    }
+                })
+                .buffered(concurrency)
+                .try_collect(),
+        )
+    }
+
+    /// Dataset configuration recorded in the manifest, sorted by key
+    pub fn config(&self) -> Vec<(String, String)> {
+        let mut config: Vec<(String, String)> = self
+            .dataset
+            .manifest()
+            .config
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        config.sort();
+        config
+    }
+
+    /// Lance field ids and names of the top-level columns
+    ///
+    /// Field ids survive renames, so they identify a column across versions.
+    pub fn field_ids(&self) -> Vec<(i32, String)> {
+        self.dataset
+            .schema()
+            .fields
+            .iter()
+            .map(|field| (field.id, field.name.clone()))
+            .collect()
+    }
+
+    /// Each index as its name, the names of its columns, and Lance's statistics
+    ///
+    /// The statistics are Lance's own JSON description of the index, which
+    /// records its type and build parameters.
+    pub fn indexes(
+        &self,
+    ) -> Result<Vec<(String, Vec<String>, serde_json::Value)>, pgrx::PgSqlErrorCode> {
+        let internal = |_e| pgrx::PgSqlErrorCode::ERRCODE_INTERNAL_ERROR;
+        self.runtime.block_on(async {
+            let indices = self.dataset.load_indices().await.map_err(internal)?;
+            let schema = self.dataset.schema();
+            // An index updated incrementally has one entry per delta
+            let mut seen = std::collections::HashSet::new();
+            let mut indexes = Vec::new();
+            for index in indices.iter() {
+                if !seen.insert(index.name.clone()) {
+                    continue;
+                }
+                let columns = index
+                    .fields
+                    .iter()
+                    .filter_map(|id| schema.field_by_id(*id))
+                    .map(|field| field.name.clone())
+                    .collect();
+                let statistics = self
+                    .dataset
+                    .index_statistics(&index.name)
+                    .await
+                    .map_err(internal)?;
+                let statistics = serde_json::from_str(&statistics)
+                    .map_err(|_e| pgrx::PgSqlErrorCode::ERRCODE_INTERNAL_ERROR)?;
+                indexes.push((index.name.clone(), columns, statistics));
+            }
+            Ok(indexes)
+        })
+    }
+
+    /// List all versions of the table, oldest first
+    pub fn versions(&self) -> Result<Vec<lance::dataset::Version>, pgrx::PgSqlErrorCode> {
+        self.runtime.block_on(async {
+            self.dataset
+                .versions()
+                .await
+                .map_err(|_e| pgrx::PgSqlErrorCode::ERRCODE_INTERNAL_ERROR)
+        })
+    }
+
+    /// Name of the operation that wrote `version`, from its transaction file
+    ///
+    /// Compactions are reported as `Compact` rather than Lance's `Rewrite`.
+    /// Returns `None` when the version has no readable transaction, as for
+    /// versions written by old Lance releases.
+    pub fn version_operation(&self, version: u64) -> Option<String> {
+        let transaction = self
+            .runtime
+            .block_on(self.dataset.read_transaction_by_version(version))
+            .ok()
+            .flatten()?;
+        let name = match transaction.operation.name() {
+            "Rewrite" => "Compact",
+            name => name,
+        };
+        Some(name.to_string())
+    }
+
     /// Get table statistics
     pub fn get_stats(&self) -> Result<LanceTableStats, pgrx::PgSqlErrorCode> {
         let dataset = &self.dataset;
 
         let version = dataset.version().version;
-        let lance_schema = dataset.schema();
-        let arrow_fields: Vec<Arc<arrow::datatypes::Field>> = lance_schema
-            .fields
-            .iter()
-            .map(|field| {
-                Arc::new(arrow::datatypes::Field::new(
-                    field.name.clone(),
-                    field.data_type().clone(),
-                    field.nullable,
-                ))
-            })
-            .collect();
-        let schema = Arc::new(arrow::datatypes::Schema::new(arrow_fields));
+        let schema = self.schema();
 
         let num_rows = self.runtime.block_on(async {
             dataset
@@ -127,19 +1101,394 @@ impl LanceScanner {
     }
 }
 
+/// Less common scan settings, kept apart from the public scan arguments
+#[derive(Default)]
+struct ScanExtras {
+    /// Add a trailing `_rowaddr` column
+    with_row_address: bool,
+    /// Add a trailing `_rowid` column
+    with_row_id: bool,
+    /// Read only these fragments, in this order
+    fragments: Option<Vec<lance_table::format::Fragment>>,
+    /// Read no table columns, only row ids, or only row addresses together
+    /// with `with_row_address`
+    no_columns: bool,
+}
+
+/// Run a scan future, failing with `ScanError::TimedOut` once `timeout` passes
+///
+/// Batches already handed out before the deadline stay delivered; the scan
+/// is dropped at its next await point.
+pub async fn with_timeout<T>(
+    timeout: Option<std::time::Duration>,
+    scan: impl std::future::Future<Output = Result<T, ScanError>>,
+) -> Result<T, ScanError> {
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, scan).await.map_err(|_| {
+            ScanError::TimedOut(format!("scan exceeded {} ms", timeout.as_millis()))
+        })?,
+        None => scan.await,
+    }
+}
+
+/// Keep the rows of a batch whose trailing `_rowid` hashes to `remainder`
+///
+/// The row id column is dropped from the result.
+fn sample_by_row_id(
+    batch: &RecordBatch,
+    modulus: u64,
+    remainder: u64,
+) -> Result<RecordBatch, arrow::error::ArrowError> {
+    let row_id_idx = batch.num_columns() - 1;
+    let row_ids = batch
+        .column(row_id_idx)
+        .as_any()
+        .downcast_ref::<arrow::array::UInt64Array>()
+        .ok_or_else(|| {
+            arrow::error::ArrowError::SchemaError("row ids are not UInt64".to_string())
+        })?;
+    let keep: arrow::array::BooleanArray = row_ids
+        .values()
+        .iter()
+        .map(|row_id| Some(xxh3_64(&row_id.to_le_bytes()) % modulus == remainder))
+        .collect();
+    let columns: Vec<usize> = (0..row_id_idx).collect();
+    arrow::compute::filter_record_batch(&batch.project(&columns)?, &keep)
+}
+
+/// Replace the trailing row id column with a `_deleted` marker
+///
+/// Scans including deleted rows return a null row id for every deleted row.
+fn mark_deleted_rows(batch: RecordBatch) -> Result<RecordBatch, arrow::error::ArrowError> {
+    let row_id_idx = batch.num_columns() - 1;
+    let deleted = arrow::compute::is_null(batch.column(row_id_idx).as_ref())?;
+
+    let schema = batch.schema();
+    let mut fields: Vec<_> = schema.fields()[..row_id_idx].to_vec();
+    fields.push(Arc::new(arrow::datatypes::Field::new(
+        DELETED_COLUMN,
+        arrow::datatypes::DataType::Boolean,
+        false,
+    )));
+    let mut columns = batch.columns()[..row_id_idx].to_vec();
+    columns.push(Arc::new(deleted));
+    RecordBatch::try_new(Arc::new(arrow::datatypes::Schema::new(fields)), columns)
+}
+
+/// Merges consecutive batches smaller than `target_rows`
+///
+/// Batches are only concatenated while the result stays within
+/// `target_rows`, so the scanner's batch size is still an upper bound.
+struct BatchCoalescer {
+    target_rows: usize,
+    pending: Vec<RecordBatch>,
+    pending_rows: usize,
+    output: Vec<RecordBatch>,
+}
+
+impl BatchCoalescer {
+    fn new(target_rows: usize) -> Self {
+        Self {
+            target_rows,
+            pending: Vec::new(),
+            pending_rows: 0,
+            output: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, batch: RecordBatch) -> Result<(), arrow::error::ArrowError> {
+        if self.pending_rows + batch.num_rows() > self.target_rows {
+            self.flush()?;
+        }
+        if batch.num_rows() >= self.target_rows {
+            self.output.push(batch);
+        } else {
+            self.pending_rows += batch.num_rows();
+            self.pending.push(batch);
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), arrow::error::ArrowError> {
+        match self.pending.len() {
+            0 => {}
+            1 => self.output.extend(self.pending.pop()),
+            _ => {
+                let schema = self.pending[0].schema();
+                self.output
+                    .push(arrow::compute::concat_batches(&schema, &self.pending)?);
+                self.pending.clear();
+            }
+        }
+        self.pending_rows = 0;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<Vec<RecordBatch>, arrow::error::ArrowError> {
+        self.flush()?;
+        Ok(self.output)
+    }
+}
+
+/// Async runtime shared by all scanners in this backend
+///
+/// Cached dataset handles keep object store clients bound to the runtime that
+/// created them, so every scanner must run on the same runtime.
+static RUNTIME: OnceLock<Arc<Runtime>> = OnceLock::new();
+
+fn shared_runtime() -> Result<Arc<Runtime>, pgrx::PgSqlErrorCode> {
+    if let Some(runtime) = RUNTIME.get() {
+        return Ok(Arc::clone(runtime));
+    }
+    let runtime =
+        Arc::new(Runtime::new().map_err(|_e| pgrx::PgSqlErrorCode::ERRCODE_INTERNAL_ERROR)?);
+    Ok(Arc::clone(RUNTIME.get_or_init(|| runtime)))
+}
+
+/// Wraps a dataset's object store so at most this many requests are in flight
+#[derive(Debug, Clone)]
+struct RequestLimit(usize);
+
+impl WrappingObjectStore for RequestLimit {
+    fn wrap(&self, original: Arc<DynObjectStore>) -> Arc<DynObjectStore> {
+        Arc::new(object_store::limit::LimitStore::new(original, self.0))
+    }
+}
+
+/// IO concurrency requested through `pglance.io_threads`, 0 meaning Lance's default
+fn configured_io_threads() -> Option<usize> {
+    match crate::guc::IO_THREADS.get() {
+        n if n > 0 => Some(n as usize),
+        _ => None,
+    }
+}
+
+/// Whether a path starts with a URI scheme such as `s3://`
+fn is_qualified_uri(path: &str) -> bool {
+    path.split_once("://").is_some_and(|(scheme, _)| {
+        !scheme.is_empty()
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    })
+}
+
+/// PostgreSQL's `work_mem` in bytes
+fn work_mem_bytes() -> usize {
+    // SAFETY: work_mem is a plain GUC variable of the backend
+    let kilobytes = unsafe { pgrx::pg_sys::work_mem };
+    kilobytes.max(64) as usize * 1024
+}
+
+/// Scan memory budget set through `pglance.scan_mem_budget_bytes`, 0 meaning none
+fn configured_mem_budget() -> Option<usize> {
+    match crate::guc::SCAN_MEM_BUDGET_BYTES.get() {
+        n if n > 0 => Some(n as usize),
+        _ => None,
+    }
+}
+
+/// Map a failure to open a dataset onto a PostgreSQL error code
+fn open_error(e: lance::Error) -> pgrx::PgSqlErrorCode {
+    match e {
+        lance::Error::DatasetNotFound { .. } | lance::Error::NotFound { .. } => {
+            pgrx::PgSqlErrorCode::ERRCODE_UNDEFINED_FILE
+        }
+        lance::Error::VersionNotFound { .. } => {
+            pgrx::PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE
+        }
+        // Stores such as HTTP answer a missing object with a 404 that Lance
+        // passes on as an IO error
+        e if matches!(
+            object_store_cause(&e),
+            Some(object_store::Error::NotFound { .. })
+        ) =>
+        {
+            pgrx::PgSqlErrorCode::ERRCODE_UNDEFINED_FILE
+        }
+        _ => pgrx::PgSqlErrorCode::ERRCODE_INTERNAL_ERROR,
+    }
+}
+
+/// The object store error a Lance error was caused by, if any
+fn object_store_cause(e: &lance::Error) -> Option<&object_store::Error> {
+    let mut cause: Option<&(dyn std::error::Error + 'static)> = Some(e);
+    while let Some(error) = cause {
+        if let Some(e) = error.downcast_ref::<object_store::Error>() {
+            return Some(e);
+        }
+        cause = error.source();
+    }
+    None
+}
+
+/// Whether an object store error means the request was refused
+fn is_access_denied(e: &lance::Error) -> bool {
+    matches!(
+        object_store_cause(e),
+        Some(
+            object_store::Error::PermissionDenied { .. }
+                | object_store::Error::Unauthenticated { .. }
+        )
+    )
+}
+
+/// Build a read-only object store for a dataset served over HTTP(S)
+///
+/// The server must support range requests. Headers given as `http_header.*`
+/// storage options are sent with every request.
+fn http_object_store(
+    location: url::Url,
+    options: &OpenOptions,
+) -> Result<(Arc<DynObjectStore>, url::Url), pgrx::PgSqlErrorCode> {
+    let mut headers = http::HeaderMap::new();
+    for (name, value) in &options.http_headers {
+        let name = http::HeaderName::try_from(name.as_str())
+            .map_err(|_e| pgrx::PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE)?;
+        let value = http::HeaderValue::try_from(value.as_str())
+            .map_err(|_e| pgrx::PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE)?;
+        headers.insert(name, value);
+    }
+
+    let mut base = location.clone();
+    base.set_path("");
+    base.set_query(None);
+    let store = HttpBuilder::new()
+        .with_url(base.as_str())
+        .with_client_options(
+            ClientOptions::new()
+                .with_default_headers(headers)
+                .with_allow_http(location.scheme() == "http"),
+        )
+        .build()
+        .map_err(|_e| pgrx::PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE)?;
+
+    Ok((Arc::new(store), location))
+}
+
+/// Scheme and bucket or host of a table location, empty for local paths
+fn store_root(uri: &str) -> (String, String) {
+    match url::Url::parse(uri) {
+        Ok(url) if url.scheme() != "file" && url.scheme().len() > 1 => (
+            url.scheme().to_string(),
+            url.host_str().unwrap_or_default().to_string(),
+        ),
+        // Single letter schemes are Windows drive letters
+        _ => Default::default(),
+    }
+}
+
+/// Convert the Lance schema of a dataset into an Arrow schema
+///
+/// Field and table-level metadata are carried over.
+fn convert_schema(dataset: &Dataset) -> Arc<arrow::datatypes::Schema> {
+    let lance_schema = dataset.schema();
+    let arrow_fields: Vec<Arc<arrow::datatypes::Field>> = lance_schema
+        .fields
+        .iter()
+        .map(|field| {
+            Arc::new(
+                arrow::datatypes::Field::new(
+                    field.name.clone(),
+                    field.data_type().clone(),
+                    field.nullable,
+                )
+                .with_metadata(field.metadata.clone()),
+            )
+        })
+        .collect();
+    Arc::new(
+        arrow::datatypes::Schema::new(arrow_fields).with_metadata(lance_schema.metadata.clone()),
+    )
+}
+
 /// Lance scan iterator
+///
+/// Yields the scanned rows in order, batch by batch, as views into the batches
+/// it holds. Rows are read from materialized batches, so no item is currently
+/// an error; the `Result` keeps the interface stable for scans that read
+/// lazily.
 pub struct LanceScanIterator {
     pub batches: Vec<RecordBatch>,
+    batch_idx: usize,
+    row_idx: usize,
 }
 
 impl LanceScanIterator {
-    fn new(batches: Vec<RecordBatch>) -> Self {
-        Self { batches }
+    pub fn new(batches: Vec<RecordBatch>) -> Self {
+        Self {
+            batches,
+            batch_idx: 0,
+            row_idx: 0,
+        }
+    }
+
+    /// Get the next row of the scan, or `None` once all batches are consumed
+    pub fn next_row(&mut self) -> Option<Result<LanceRow<'_>, pgrx::PgSqlErrorCode>> {
+        while let Some(batch) = self.batches.get(self.batch_idx) {
+            if self.row_idx < batch.num_rows() {
+                let row = LanceRow {
+                    batch,
+                    row_idx: self.row_idx,
+                };
+                self.row_idx += 1;
+                return Some(Ok(row));
+            }
+            self.batch_idx += 1;
+            self.row_idx = 0;
+        }
+        None
     }
+
+    /// Iterate over every row of the scan, regardless of `next_row` progress
+    pub fn rows(&self) -> impl Iterator<Item = Result<LanceRow<'_>, pgrx::PgSqlErrorCode>> {
+        self.batches.iter().flat_map(|batch| {
+            (0..batch.num_rows()).map(move |row_idx| Ok(LanceRow { batch, row_idx }))
+        })
+    }
+}
+
+/// A single row of a scan, referencing the record batch that holds it
+#[derive(Debug, Clone, Copy)]
+pub struct LanceRow<'a> {
+    batch: &'a RecordBatch,
+    row_idx: usize,
+}
+
+impl<'a> LanceRow<'a> {
+    /// Record batch containing the row
+    pub fn batch(&self) -> &'a RecordBatch {
+        self.batch
+    }
+
+    /// Position of the row within its batch
+    pub fn row_idx(&self) -> usize {
+        self.row_idx
+    }
+}
+
+/// Physical plan of a scan, as planned by `LanceScanner::explain_scan`
+#[derive(Debug, Clone)]
+pub struct ScanPlan {
+    /// Indented rendering of the plan
+    pub text: String,
+    /// Whether the plan answers the filter from a scalar index
+    pub uses_index: bool,
+}
+
+/// Whether a physical plan, or any plan below it, reads a scalar index
+fn plan_uses_index(plan: &dyn datafusion::physical_plan::ExecutionPlan) -> bool {
+    matches!(
+        plan.name(),
+        "ScalarIndexExec" | "MaterializeIndexExec" | "MapIndexExec"
+    ) || plan
+        .children()
+        .into_iter()
+        .any(|child| plan_uses_index(child.as_ref()))
 }
 
 /// Lance table statistics
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct LanceTableStats {
     pub version: u64,
     pub num_rows: usize,
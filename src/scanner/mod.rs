@@ -1,3 +1,15 @@
+pub mod cache;
+pub mod error;
+pub mod filter;
 pub mod lance_scanner;
+pub mod options;
+pub mod projection;
+pub mod scan_options;
 
+pub use cache::*;
+pub use error::*;
+pub use filter::*;
 pub use lance_scanner::*;
+pub use options::*;
+pub use projection::*;
+pub use scan_options::*;
@@ -0,0 +1,282 @@
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::collections::HashMap;
+use url::Url;
+
+use crate::types::ConversionError;
+
+/// Storage option naming the DynamoDB table used as Lance's external commit store
+pub const DDB_TABLE_NAME_KEY: &str = "ddb_table_name";
+
+/// Prefix of storage options sent as HTTP headers, e.g. `http_header.Authorization`
+pub const HTTP_HEADER_PREFIX: &str = "http_header.";
+
+/// Storage option selecting which version of the dataset is read
+pub const CONSISTENCY_KEY: &str = "consistency";
+
+/// Which version of a dataset a scan reads
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReadConsistency {
+    /// Check for a newer version every time the table is opened
+    #[default]
+    Latest,
+    /// Read a fixed version, never checking for newer ones
+    Version(u64),
+}
+
+impl ReadConsistency {
+    /// Parse `latest` or `version-<n>`
+    pub fn parse(value: &str) -> Result<Self, pgrx::PgSqlErrorCode> {
+        if value.eq_ignore_ascii_case("latest") {
+            return Ok(Self::Latest);
+        }
+        value
+            .strip_prefix("version-")
+            .and_then(|v| v.parse().ok())
+            .map(Self::Version)
+            .ok_or(pgrx::PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE)
+    }
+}
+
+/// Convert a Lance table version to PostgreSQL's `bigint`
+///
+/// Lance numbers versions with `u64`, so versions past `i64::MAX` fail with
+/// `ERRCODE_NUMERIC_VALUE_OUT_OF_RANGE` instead of wrapping to negative.
+pub fn version_to_i64(version: u64) -> Result<i64, ConversionError> {
+    i64::try_from(version).map_err(|_| {
+        ConversionError::new(
+            pgrx::PgSqlErrorCode::ERRCODE_NUMERIC_VALUE_OUT_OF_RANGE,
+            format!("Lance table version {} is out of range for bigint", version),
+        )
+    })
+}
+
+/// Storage option setting the number of entries in Lance's metadata cache
+pub const METADATA_CACHE_SIZE_KEY: &str = "metadata_cache_size";
+
+/// Storage option setting the number of entries in Lance's index cache
+pub const INDEX_CACHE_SIZE_KEY: &str = "index_cache_size";
+
+/// Storage option giving the RFC 3339 expiry of temporary (STS) credentials
+pub const SESSION_TOKEN_EXPIRY_KEY: &str = "aws_session_token_expiry";
+
+/// Storage option giving the base64-encoded 256-bit key of an encrypted dataset
+///
+/// Objects are read with S3 server-side encryption using this customer key (SSE-C).
+pub const ENCRYPTION_KEY_KEY: &str = "encryption_key";
+
+/// Storage option naming the KMS key of a dataset encrypted with SSE-KMS
+pub const ENCRYPTION_KMS_KEY_ID_KEY: &str = "encryption_kms_key_id";
+
+/// Object store option carrying an SSE-C customer key
+const SSE_CUSTOMER_KEY_OPTION: &str = "aws_sse_customer_key_base64";
+
+/// Object store options selecting SSE-KMS and its key
+const SERVER_SIDE_ENCRYPTION_OPTION: &str = "aws_server_side_encryption";
+const SSE_KMS_KEY_ID_OPTION: &str = "aws_sse_kms_key_id";
+
+/// Whether a storage option holds a credential that must never be logged
+pub fn is_secret_option(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    key.starts_with(HTTP_HEADER_PREFIX)
+        || [
+            "secret",
+            "token",
+            "password",
+            "credential",
+            "sas_key",
+            "account_key",
+            "encryption_key",
+            "customer_key",
+        ]
+        .iter()
+        .any(|word| key.contains(word))
+}
+
+/// Copy of a storage options JSON object with credential values masked
+pub fn redact_storage_options(value: &Value) -> Value {
+    match value {
+        Value::Object(object) => Value::Object(
+            object
+                .iter()
+                .map(|(key, value)| {
+                    let value = if is_secret_option(key) {
+                        Value::String("***".to_string())
+                    } else {
+                        value.clone()
+                    };
+                    (key.clone(), value)
+                })
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// Options used when opening a Lance dataset
+///
+/// Keys understood by pglance are extracted while parsing; everything else is
+/// forwarded to Lance's object store as storage options. The `Debug` output
+/// masks credentials.
+#[derive(Clone, Default)]
+pub struct OpenOptions {
+    pub storage_options: HashMap<String, String>,
+    pub ddb_table_name: Option<String>,
+    pub metadata_cache_size: Option<usize>,
+    pub index_cache_size: Option<usize>,
+    pub http_headers: HashMap<String, String>,
+    pub consistency: ReadConsistency,
+    pub session_token_expiry: Option<DateTime<Utc>>,
+    /// Whether the dataset is read with an encryption key or KMS configuration
+    pub encrypted: bool,
+}
+
+impl std::fmt::Debug for OpenOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let redact = |map: &HashMap<String, String>, all: bool| -> HashMap<String, String> {
+            map.iter()
+                .map(|(key, value)| {
+                    let value = if all || is_secret_option(key) {
+                        "***".to_string()
+                    } else {
+                        value.clone()
+                    };
+                    (key.clone(), value)
+                })
+                .collect()
+        };
+        f.debug_struct("OpenOptions")
+            .field("storage_options", &redact(&self.storage_options, false))
+            .field("ddb_table_name", &self.ddb_table_name)
+            .field("metadata_cache_size", &self.metadata_cache_size)
+            .field("index_cache_size", &self.index_cache_size)
+            .field("http_headers", &redact(&self.http_headers, true))
+            .field("consistency", &self.consistency)
+            .field("session_token_expiry", &self.session_token_expiry)
+            .field("encrypted", &self.encrypted)
+            .finish()
+    }
+}
+
+impl OpenOptions {
+    /// Parse options from a flat JSON object of string, number or boolean values
+    pub fn from_json(value: &Value) -> Result<Self, pgrx::PgSqlErrorCode> {
+        let object = value
+            .as_object()
+            .ok_or(pgrx::PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE)?;
+
+        let mut options = Self::default();
+        for (key, value) in object {
+            let value = match value {
+                Value::String(s) => s.clone(),
+                Value::Number(n) => n.to_string(),
+                Value::Bool(b) => b.to_string(),
+                _ => return Err(pgrx::PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE),
+            };
+            match key.as_str() {
+                DDB_TABLE_NAME_KEY => options.ddb_table_name = Some(value),
+                CONSISTENCY_KEY => options.consistency = ReadConsistency::parse(&value)?,
+                SESSION_TOKEN_EXPIRY_KEY => {
+                    let expiry = DateTime::parse_from_rfc3339(&value)
+                        .map_err(|_| pgrx::PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE)?;
+                    options.session_token_expiry = Some(expiry.with_timezone(&Utc));
+                }
+                METADATA_CACHE_SIZE_KEY => {
+                    options.metadata_cache_size = Some(parse_cache_size(&value)?)
+                }
+                INDEX_CACHE_SIZE_KEY => options.index_cache_size = Some(parse_cache_size(&value)?),
+                ENCRYPTION_KEY_KEY => {
+                    let key = STANDARD
+                        .decode(&value)
+                        .map_err(|_| pgrx::PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE)?;
+                    if key.len() != 32 {
+                        return Err(pgrx::PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE);
+                    }
+                    options
+                        .storage_options
+                        .insert(SSE_CUSTOMER_KEY_OPTION.to_string(), value);
+                    options.encrypted = true;
+                }
+                ENCRYPTION_KMS_KEY_ID_KEY => {
+                    options.storage_options.insert(
+                        SERVER_SIDE_ENCRYPTION_OPTION.to_string(),
+                        "aws:kms".to_string(),
+                    );
+                    options
+                        .storage_options
+                        .insert(SSE_KMS_KEY_ID_OPTION.to_string(), value);
+                    options.encrypted = true;
+                }
+                _ => match key.strip_prefix(HTTP_HEADER_PREFIX) {
+                    Some(header) if !header.is_empty() => {
+                        options.http_headers.insert(header.to_string(), value);
+                    }
+                    Some(_) => return Err(pgrx::PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE),
+                    None => {
+                        options.storage_options.insert(key.clone(), value);
+                    }
+                },
+            }
+        }
+        if options
+            .storage_options
+            .contains_key(SSE_CUSTOMER_KEY_OPTION)
+            && options.storage_options.contains_key(SSE_KMS_KEY_ID_OPTION)
+        {
+            return Err(pgrx::PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE);
+        }
+        Ok(options)
+    }
+
+    /// Fail with `ERRCODE_INVALID_AUTHORIZATION_SPECIFICATION` once temporary
+    /// credentials have expired, instead of sending requests that will be rejected
+    pub fn check_credentials(&self) -> Result<(), pgrx::PgSqlErrorCode> {
+        match self.session_token_expiry {
+            Some(expiry) if expiry <= Utc::now() => {
+                Err(pgrx::PgSqlErrorCode::ERRCODE_INVALID_AUTHORIZATION_SPECIFICATION)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Resolve the URI handed to Lance for the given table path
+    ///
+    /// With a DynamoDB commit store configured, `s3://` URIs are rewritten to
+    /// Lance's `s3+ddb://` scheme so the latest version is resolved through the
+    /// external manifest store instead of listing the bucket.
+    pub fn resolve_uri(&self, table_path: &str) -> Result<String, pgrx::PgSqlErrorCode> {
+        match &self.ddb_table_name {
+            None => Ok(table_path.to_string()),
+            Some(ddb_table) => {
+                let rest = table_path
+                    .strip_prefix("s3://")
+                    .ok_or(pgrx::PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE)?;
+                let ddb_table: String =
+                    url::form_urlencoded::byte_serialize(ddb_table.as_bytes()).collect();
+                Ok(format!("s3+ddb://{}?ddbTableName={}", rest, ddb_table))
+            }
+        }
+    }
+}
+
+/// Parse an `http://` or `https://` table location
+///
+/// Returns `None` for any other scheme and fails with
+/// `ERRCODE_INVALID_PARAMETER_VALUE` when an HTTP URL is malformed.
+pub fn http_location(uri: &str) -> Result<Option<Url>, pgrx::PgSqlErrorCode> {
+    if !(uri.starts_with("http://") || uri.starts_with("https://")) {
+        return Ok(None);
+    }
+    let url = Url::parse(uri).map_err(|_| pgrx::PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE)?;
+    if url.host_str().is_none() {
+        return Err(pgrx::PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE);
+    }
+    Ok(Some(url))
+}
+
+fn parse_cache_size(value: &str) -> Result<usize, pgrx::PgSqlErrorCode> {
+    value
+        .parse()
+        .map_err(|_| pgrx::PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE)
+}
@@ -0,0 +1,100 @@
+/// Split a comma-separated select list into `(alias, expression)` pairs
+///
+/// Commas nested inside parentheses or quotes do not split items, so calls
+/// like `coalesce(a, b) AS c` stay intact. Items without an `AS` alias use
+/// the expression text itself as the output name.
+pub fn parse_select_list(select: &str) -> Result<Vec<(String, String)>, pgrx::PgSqlErrorCode> {
+    let mut items = Vec::new();
+    for item in split_top_level(select, ',')? {
+        let item = item.trim();
+        if item.is_empty() {
+            return Err(pgrx::PgSqlErrorCode::ERRCODE_SYNTAX_ERROR);
+        }
+
+        let (expr, alias) = match find_top_level_as(item) {
+            Some(pos) => (item[..pos].trim(), unquote_alias(item[pos + 4..].trim())),
+            None => (item, unquote_alias(item)),
+        };
+        if expr.is_empty() || alias.is_empty() {
+            return Err(pgrx::PgSqlErrorCode::ERRCODE_SYNTAX_ERROR);
+        }
+        items.push((alias, expr.to_string()));
+    }
+    Ok(items)
+}
+
+fn split_top_level(input: &str, separator: char) -> Result<Vec<&str>, pgrx::PgSqlErrorCode> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut quote: Option<char> = None;
+    let mut start = 0;
+
+    for (idx, ch) in input.char_indices() {
+        match quote {
+            Some(q) if ch == q => quote = None,
+            Some(_) => {}
+            None => match ch {
+                '\'' | '"' | '`' => quote = Some(ch),
+                '(' => depth += 1,
+                ')' => {
+                    depth -= 1;
+                    if depth < 0 {
+                        return Err(pgrx::PgSqlErrorCode::ERRCODE_SYNTAX_ERROR);
+                    }
+                }
+                c if c == separator && depth == 0 => {
+                    parts.push(&input[start..idx]);
+                    start = idx + c.len_utf8();
+                }
+                _ => {}
+            },
+        }
+    }
+
+    if quote.is_some() || depth != 0 {
+        return Err(pgrx::PgSqlErrorCode::ERRCODE_SYNTAX_ERROR);
+    }
+    parts.push(&input[start..]);
+    Ok(parts)
+}
+
+/// Find the byte offset of the last top-level ` AS ` keyword in a select item
+fn find_top_level_as(item: &str) -> Option<usize> {
+    let upper = item.to_ascii_uppercase();
+    let bytes = upper.as_bytes();
+    let mut depth = 0i32;
+    let mut quote: Option<u8> = None;
+    let mut found = None;
+
+    for (idx, &b) in bytes.iter().enumerate() {
+        match quote {
+            Some(q) if b == q => quote = None,
+            Some(_) => {}
+            None => match b {
+                b'\'' | b'"' | b'`' => quote = Some(b),
+                b'(' => depth += 1,
+                b')' => depth -= 1,
+                _ if depth == 0
+                    && b.is_ascii_whitespace()
+                    && bytes.len() > idx + 4
+                    && &bytes[idx + 1..idx + 3] == b"AS"
+                    && bytes[idx + 3].is_ascii_whitespace() =>
+                {
+                    found = Some(idx);
+                }
+                _ => {}
+            },
+        }
+    }
+    found
+}
+
+fn unquote_alias(alias: &str) -> String {
+    let trimmed = alias.trim();
+    for q in ['"', '`'] {
+        if trimmed.len() >= 2 && trimmed.starts_with(q) && trimmed.ends_with(q) {
+            return trimmed[1..trimmed.len() - 1].to_string();
+        }
+    }
+    trimmed.to_string()
+}
@@ -0,0 +1,103 @@
+use std::time::Duration;
+
+use super::quote_identifier;
+
+/// Name of the column marking deleted rows in scans that include them
+pub const DELETED_COLUMN: &str = "_deleted";
+
+/// A column to sort scan results by
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortColumn {
+    pub column: String,
+    /// Ascending with NULLs last, or descending with NULLs first, matching
+    /// PostgreSQL's defaults
+    pub ascending: bool,
+}
+
+/// Settings for a single scan, passed to `LanceScanner::scan`
+///
+/// Every setting is optional; the default scans all columns of all rows in
+/// table order with the scanner's batch size.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanOptions {
+    /// Lance SQL predicate rows must satisfy
+    pub filter: Option<String>,
+    /// Output columns as `(alias, expression)` pairs
+    pub projection: Option<Vec<(String, String)>>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    pub order_by: Vec<SortColumn>,
+    /// Overrides the scanner's batch size for this scan
+    pub batch_size: Option<usize>,
+    /// Also read rows that are deleted but not yet compacted away, adding a
+    /// trailing boolean `_deleted` column
+    pub include_deleted: bool,
+    /// Longest the whole scan may take before failing with `ScanError::TimedOut`
+    pub timeout: Option<Duration>,
+}
+
+impl ScanOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep only rows matching a Lance SQL predicate
+    pub fn with_filter(mut self, filter: impl Into<String>) -> Self {
+        self.filter = Some(filter.into());
+        self
+    }
+
+    /// Read only the named columns, in the given order
+    pub fn with_columns<S: AsRef<str>>(mut self, columns: &[S]) -> Self {
+        let projection = columns
+            .iter()
+            .map(|column| {
+                let column = column.as_ref();
+                (column.to_string(), quote_identifier(column))
+            })
+            .collect();
+        self.projection = Some(projection);
+        self
+    }
+
+    /// Output `(alias, expression)` pairs instead of the table's columns
+    pub fn with_projection(mut self, projection: Vec<(String, String)>) -> Self {
+        self.projection = Some(projection);
+        self
+    }
+
+    pub fn with_limit(mut self, limit: i64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn with_offset(mut self, offset: i64) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Sort by a column after any earlier sort columns
+    pub fn with_order_by(mut self, column: impl Into<String>, ascending: bool) -> Self {
+        self.order_by.push(SortColumn {
+            column: column.into(),
+            ascending,
+        });
+        self
+    }
+
+    /// Include deleted rows, marked in a trailing `_deleted` column
+    pub fn with_deleted_rows(mut self) -> Self {
+        self.include_deleted = true;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = Some(batch_size.max(1));
+        self
+    }
+}
@@ -0,0 +1,639 @@
+use std::sync::Arc;
+
+use arrow::array::{Array, LargeListArray, ListArray, StructArray};
+use arrow::datatypes::{DataType, Field, FieldRef, Fields, Schema};
+use pgrx::prelude::*;
+use pgrx::{pg_sys, PgMemoryContexts, PgTupleDesc};
+use serde_json::{json, Value};
+
+use crate::scanner::ScanOptions;
+use crate::types::{arrow_to_pg_type, downcast_array, pg_type_name, ConversionError};
+use crate::{arrow_value_to_datum, arrow_value_to_serde_json, guc};
+
+/// Most columns a typed scan can return, PostgreSQL's `MaxHeapAttributeNumber`
+pub const MAX_TYPED_COLUMNS: usize = pg_sys::MaxHeapAttributeNumber as usize;
+
+extension_sql!(
+    r#"
+CREATE FUNCTION lance_scan_typed(
+    table_path TEXT,
+    "limit" BIGINT DEFAULT NULL,
+    timeout_ms INTEGER DEFAULT NULL
+) RETURNS SETOF record
+LANGUAGE c
+AS 'MODULE_PATHNAME', 'lance_scan_typed_wrapper';
+"#,
+    name = "lance_scan_typed",
+);
+
+extension_sql!(
+    r#"
+CREATE FUNCTION lance_scan_field(
+    table_path TEXT,
+    field_path TEXT,
+    "limit" BIGINT DEFAULT NULL,
+    timeout_ms INTEGER DEFAULT NULL
+) RETURNS SETOF record
+LANGUAGE c
+AS 'MODULE_PATHNAME', 'lance_scan_field_wrapper';
+"#,
+    name = "lance_scan_field",
+);
+
+/// Check that a table's columns fit in a PostgreSQL row
+///
+/// JSONB scans are not affected; only scans producing one PostgreSQL column
+/// per Lance column are bounded by the tuple column limit.
+pub fn check_typed_column_count(schema: &Schema) -> Result<(), ConversionError> {
+    let num_columns = schema.fields().len();
+    if num_columns > MAX_TYPED_COLUMNS {
+        return Err(ConversionError::new(
+            PgSqlErrorCode::ERRCODE_TOO_MANY_COLUMNS,
+            format!(
+                "Lance table has {} columns, but typed scans can return at most {}; use lance_scan_jsonb instead",
+                num_columns, MAX_TYPED_COLUMNS
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// How a value declared with a type other than its default one is converted
+enum ValueTarget {
+    /// A struct read as a record of a composite type
+    Composite(CompositeTarget),
+    /// A list read as `text[]` holding the JSON encoding of each element
+    JsonTextArray,
+}
+
+/// PostgreSQL composite type a struct value is read into
+///
+/// Struct fields map to the composite's attributes by position, and their
+/// names must match.
+struct CompositeTarget {
+    tupdesc: PgTupleDesc<'static>,
+    /// Attribute index of each struct field; dropped attributes are skipped
+    positions: Vec<usize>,
+    /// Targets of the struct's fields that are declared with other types
+    fields: Vec<Option<ValueTarget>>,
+}
+
+impl CompositeTarget {
+    /// Convert a struct value into a composite datum, `None` for NULL
+    fn to_datum(
+        &self,
+        array: &dyn Array,
+        row_idx: usize,
+    ) -> Result<Option<pg_sys::Datum>, ConversionError> {
+        if array.is_null(row_idx) {
+            return Ok(None);
+        }
+        let struct_array = downcast_array::<StructArray>(array, "StructArray")?;
+        let natts = self.tupdesc.len();
+        let mut datums = vec![pg_sys::Datum::from(0); natts];
+        let mut nulls = vec![true; natts];
+        for (field_idx, child) in struct_array.columns().iter().enumerate() {
+            let position = self.positions[field_idx];
+            if let Some(datum) =
+                value_to_datum(child.as_ref(), row_idx, self.fields[field_idx].as_ref())?
+            {
+                datums[position] = datum;
+                nulls[position] = false;
+            }
+        }
+        // SAFETY: the arrays have one entry per attribute of the descriptor
+        let datum = unsafe {
+            let tuple = pg_sys::heap_form_tuple(
+                self.tupdesc.as_ptr(),
+                datums.as_mut_ptr(),
+                nulls.as_mut_ptr(),
+            );
+            pgrx::heap_tuple_get_datum(tuple)
+        };
+        Ok(Some(datum))
+    }
+}
+
+/// Convert a value with `arrow_value_to_datum`, or as its target requires
+fn value_to_datum(
+    array: &dyn Array,
+    row_idx: usize,
+    target: Option<&ValueTarget>,
+) -> Result<Option<pg_sys::Datum>, ConversionError> {
+    match target {
+        Some(ValueTarget::Composite(target)) => target.to_datum(array, row_idx),
+        Some(ValueTarget::JsonTextArray) => list_to_json_text_array(array, row_idx),
+        None => arrow_value_to_datum(array, row_idx),
+    }
+}
+
+/// Convert a list value into a `text[]` of its elements' JSON encodings
+///
+/// NULL elements become NULL array elements rather than JSON `null`.
+fn list_to_json_text_array(
+    array: &dyn Array,
+    row_idx: usize,
+) -> Result<Option<pg_sys::Datum>, ConversionError> {
+    if array.is_null(row_idx) {
+        return Ok(None);
+    }
+    let values = match array.data_type() {
+        DataType::LargeList(_) => {
+            downcast_array::<LargeListArray>(array, "LargeListArray")?.value(row_idx)
+        }
+        _ => downcast_array::<ListArray>(array, "ListArray")?.value(row_idx),
+    };
+    let elements = (0..values.len())
+        .map(|idx| {
+            if values.is_null(idx) {
+                return Ok(None);
+            }
+            Ok(Some(
+                arrow_value_to_serde_json(values.as_ref(), idx)?.to_string(),
+            ))
+        })
+        .collect::<Result<Vec<Option<String>>, ConversionError>>()?;
+    Ok(elements.into_datum())
+}
+
+/// Name of a PostgreSQL type as SQL would spell it
+fn format_type(oid: pg_sys::Oid) -> String {
+    // SAFETY: format_type_be returns a palloc'd, NUL-terminated string
+    unsafe { std::ffi::CStr::from_ptr(pg_sys::format_type_be(oid)) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Check that a declared composite type matches a struct's fields
+fn composite_target(
+    label: &str,
+    fields: &Fields,
+    type_oid: pg_sys::Oid,
+) -> Result<CompositeTarget, ConversionError> {
+    let mismatch =
+        |message: String| ConversionError::new(PgSqlErrorCode::ERRCODE_DATATYPE_MISMATCH, message);
+    let tupdesc = PgTupleDesc::for_composite_type_by_oid(type_oid).ok_or_else(|| {
+        mismatch(format!(
+            "{} is declared as {}, which is not a composite type",
+            label,
+            format_type(type_oid)
+        ))
+    })?;
+
+    let attributes: Vec<_> = tupdesc
+        .iter()
+        .enumerate()
+        .filter(|(_, attribute)| !attribute.is_dropped())
+        .collect();
+    if attributes.len() != fields.len() {
+        return Err(mismatch(format!(
+            "{} has {} fields, but composite type {} has {} attributes",
+            label,
+            fields.len(),
+            format_type(type_oid),
+            attributes.len()
+        )));
+    }
+
+    let mut positions = Vec::with_capacity(fields.len());
+    let mut targets = Vec::with_capacity(fields.len());
+    for (field_idx, (field, (position, attribute))) in fields.iter().zip(attributes).enumerate() {
+        if attribute.name() != field.name() {
+            return Err(mismatch(format!(
+                "field {} of {} is named \"{}\", but composite type {} names it \"{}\"",
+                field_idx + 1,
+                label,
+                field.name(),
+                format_type(type_oid),
+                attribute.name()
+            )));
+        }
+        let field_label = format!("field \"{}\" of {}", field.name(), label);
+        targets.push(check_value_type(&field_label, field, attribute.atttypid)?);
+        positions.push(position);
+    }
+    Ok(CompositeTarget {
+        tupdesc,
+        positions,
+        fields: targets,
+    })
+}
+
+/// Check that a value is declared with the type it converts to
+///
+/// Struct values may also be declared as a composite type whose attributes
+/// match the struct's fields, and are then read as records instead of JSONB.
+/// List values may also be declared as `text[]`, holding the JSON encoding of
+/// each element.
+fn check_value_type(
+    label: &str,
+    field: &Field,
+    type_oid: pg_sys::Oid,
+) -> Result<Option<ValueTarget>, ConversionError> {
+    match field.data_type() {
+        DataType::Struct(fields) => {
+            // SAFETY: looking up a type has no preconditions
+            if type_oid != pg_sys::RECORDOID && unsafe { pg_sys::type_is_rowtype(type_oid) } {
+                return composite_target(label, fields, type_oid)
+                    .map(|target| Some(ValueTarget::Composite(target)));
+            }
+        }
+        DataType::List(_) | DataType::LargeList(_) if type_oid == pg_sys::TEXTARRAYOID => {
+            return Ok(Some(ValueTarget::JsonTextArray));
+        }
+        _ => {}
+    }
+
+    let expected = arrow_to_pg_type(field)
+        .map_err(|code| ConversionError::new(code, format!("{} has no PostgreSQL type", label)))?;
+    if type_oid != expected.value() {
+        let declared_as = match field.data_type() {
+            DataType::Struct(_) => {
+                format!("{} or a matching composite type", pg_type_name(expected))
+            }
+            DataType::List(_) | DataType::LargeList(_) => {
+                format!("{} or text[]", pg_type_name(expected))
+            }
+            _ => pg_type_name(expected).to_string(),
+        };
+        return Err(ConversionError::new(
+            PgSqlErrorCode::ERRCODE_DATATYPE_MISMATCH,
+            format!(
+                "{} of type {:?} must be declared as {}",
+                label,
+                field.data_type(),
+                declared_as
+            ),
+        ));
+    }
+    Ok(None)
+}
+
+/// Check that the column definition list given by the caller matches the table
+///
+/// Returns the target of each column declared with a type other than its
+/// default one.
+fn check_row_type(
+    schema: &Schema,
+    tupdesc: &PgTupleDesc,
+) -> Result<Vec<Option<ValueTarget>>, ConversionError> {
+    if tupdesc.len() != schema.fields().len() {
+        return Err(ConversionError::new(
+            PgSqlErrorCode::ERRCODE_DATATYPE_MISMATCH,
+            format!(
+                "column definition list has {} columns, but the Lance table has {}",
+                tupdesc.len(),
+                schema.fields().len()
+            ),
+        ));
+    }
+
+    schema
+        .fields()
+        .iter()
+        .enumerate()
+        .map(|(idx, field)| {
+            let attribute = tupdesc.get(idx).expect("attribute index is in range");
+            let label = format!("column \"{}\"", field.name());
+            check_value_type(&label, field, attribute.atttypid)
+        })
+        .collect()
+}
+
+/// Compare a planned column definition list against a Lance table
+///
+/// `pg_types` lists the PostgreSQL type of each column in table order, in any
+/// spelling PostgreSQL accepts. Returns a JSONB array with one
+/// `{position, column, expected, requested}` object per column whose type
+/// is not one `lance_scan_typed` accepts, with `expected` naming the default
+/// one; an empty array means the list would be accepted. Missing and extra
+/// entries are reported with `requested` or `column` set to NULL.
+#[pg_extern]
+pub fn lance_check_typed(table_path: &str, pg_types: Vec<String>) -> pgrx::JsonB {
+    let scanner = crate::open_scanner(table_path, None);
+    let schema = scanner.schema();
+
+    let mut mismatches = Vec::new();
+    for position in 0..schema.fields().len().max(pg_types.len()) {
+        let field = schema.fields().get(position);
+        let expected = field.and_then(|field| arrow_to_pg_type(field).ok());
+        let requested = pg_types.get(position);
+        let requested_oid = requested.and_then(|name| {
+            Spi::get_one::<pg_sys::Oid>(&format!(
+                "SELECT to_regtype({})::oid",
+                pgrx::spi::quote_literal(name)
+            ))
+            .ok()
+            .flatten()
+        });
+        let accepted = match (field, requested_oid) {
+            (Some(field), Some(oid)) => check_value_type("", field, oid).is_ok(),
+            _ => false,
+        };
+        if accepted {
+            continue;
+        }
+        mismatches.push(json!({
+            "position": position + 1,
+            "column": field.map(|field| field.name()),
+            "expected": expected.map(pg_type_name),
+            "requested": requested,
+        }));
+    }
+    pgrx::JsonB(Value::Array(mismatches))
+}
+
+#[no_mangle]
+#[doc(hidden)]
+pub extern "C" fn pg_finfo_lance_scan_typed_wrapper() -> &'static pg_sys::Pg_finfo_record {
+    const V1_API: pg_sys::Pg_finfo_record = pg_sys::Pg_finfo_record { api_version: 1 };
+    &V1_API
+}
+
+/// Scan Lance table and return rows typed per the caller's column definition list
+///
+/// Materializes the rows into a tuplestore; each column is converted with
+/// `arrow_value_to_datum` and must be declared with the type that
+/// `arrow_to_pg_type` reports for it. Struct columns may instead be declared
+/// as a composite type with the struct's field names, in order, and are then
+/// returned as records. List columns may be declared as `text[]` to get the
+/// JSON encoding of each element.
+///
+/// # Safety
+///
+/// Called by PostgreSQL through the function manager.
+#[no_mangle]
+#[pg_guard]
+pub unsafe extern "C-unwind" fn lance_scan_typed_wrapper(
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> pg_sys::Datum {
+    let table_path: &str = pgrx::pg_getarg(fcinfo, 0).unwrap_or_else(|| {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_NULL_VALUE_NOT_ALLOWED,
+            "table_path must not be NULL"
+        )
+    });
+    let limit: Option<i64> = pgrx::pg_getarg(fcinfo, 1);
+    let timeout = crate::timeout_arg(pgrx::pg_getarg(fcinfo, 2));
+
+    let rsinfo = materialize_target(fcinfo, "lance_scan_typed");
+
+    let scanner = crate::open_scanner(table_path, None);
+    let schema = scanner.schema();
+    check_typed_column_count(&schema).unwrap_or_else(|e| ereport!(ERROR, e.code, e.message));
+    let expected = PgTupleDesc::from_pg_unchecked((*rsinfo).expectedDesc);
+    let targets =
+        check_row_type(&schema, &expected).unwrap_or_else(|e| ereport!(ERROR, e.code, e.message));
+
+    let options = ScanOptions {
+        limit,
+        timeout,
+        ..Default::default()
+    };
+    let scan_iter = scanner
+        .scan(&options)
+        .unwrap_or_else(|e| crate::raise_scan_error(e));
+
+    let mut sink = TupleSink::new(rsinfo);
+    let num_columns = schema.fields().len();
+    let mut rows_outputted_count = 0i64;
+    'batch_loop: for record_batch in scan_iter.batches {
+        'row_loop: for row_idx in 0..record_batch.num_rows() {
+            if limit.is_some_and(|l| rows_outputted_count >= l) {
+                break 'batch_loop;
+            }
+
+            let mut datums = Vec::with_capacity(num_columns);
+            let mut nulls = Vec::with_capacity(num_columns);
+            for (col_idx, field) in schema.fields().iter().enumerate() {
+                let column = record_batch.column(col_idx);
+                match value_to_datum(column.as_ref(), row_idx, targets[col_idx].as_ref()) {
+                    Ok(datum) => {
+                        nulls.push(datum.is_none());
+                        datums.push(datum.unwrap_or(pg_sys::Datum::from(0)));
+                    }
+                    Err(e) => {
+                        let message = format!("column \"{}\": {}", field.name(), e.message);
+                        skip_row_error(e.code, message);
+                        continue 'row_loop;
+                    }
+                }
+            }
+
+            sink.put(&mut datums, &mut nulls);
+            rows_outputted_count += 1;
+        }
+    }
+
+    sink.finish()
+}
+
+/// Check that a set-returning function may materialize its result
+///
+/// Returns the call's `ReturnSetInfo`, which must carry the caller's column
+/// definition list.
+unsafe fn materialize_target(
+    fcinfo: pg_sys::FunctionCallInfo,
+    function_name: &str,
+) -> *mut pg_sys::ReturnSetInfo {
+    let rsinfo = (*fcinfo).resultinfo as *mut pg_sys::ReturnSetInfo;
+    if rsinfo.is_null()
+        || !pgrx::is_a(
+            rsinfo as *mut pg_sys::Node,
+            pg_sys::NodeTag::T_ReturnSetInfo,
+        )
+        || (*rsinfo).allowedModes & pg_sys::SetFunctionReturnMode::SFRM_Materialize as i32 == 0
+    {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_FEATURE_NOT_SUPPORTED,
+            "set-valued function called in context that cannot accept a set"
+        );
+    }
+    if (*rsinfo).expectedDesc.is_null() {
+        ereport!(
+            ERROR,
+            PgSqlErrorCode::ERRCODE_SYNTAX_ERROR,
+            format!("a column definition list is required for {}", function_name)
+        );
+    }
+    rsinfo
+}
+
+/// Report a value that failed to convert according to `pglance.on_row_error`
+///
+/// Returns only when the row is to be skipped, after warning about it.
+fn skip_row_error(code: PgSqlErrorCode, message: String) {
+    match guc::ON_ROW_ERROR.get() {
+        guc::OnRowError::Abort => ereport!(ERROR, code, message),
+        guc::OnRowError::Skip => {
+            pgrx::warning!("Skipping row that could not be converted: {}", message);
+        }
+    }
+}
+
+/// Tuplestore collecting the rows of a materialized set-returning function
+struct TupleSink {
+    rsinfo: *mut pg_sys::ReturnSetInfo,
+    tupstore: *mut pg_sys::Tuplestorestate,
+    tupdesc: pg_sys::TupleDesc,
+}
+
+impl TupleSink {
+    /// Create the tuplestore in the per-query memory context
+    unsafe fn new(rsinfo: *mut pg_sys::ReturnSetInfo) -> Self {
+        let per_query = (*(*rsinfo).econtext).ecxt_per_query_memory;
+        let (tupstore, tupdesc) = PgMemoryContexts::For(per_query).switch_to(|_| {
+            (
+                pg_sys::tuplestore_begin_heap(true, false, pg_sys::work_mem),
+                pg_sys::CreateTupleDescCopy((*rsinfo).expectedDesc),
+            )
+        });
+        Self {
+            rsinfo,
+            tupstore,
+            tupdesc,
+        }
+    }
+
+    unsafe fn put(&mut self, datums: &mut [pg_sys::Datum], nulls: &mut [bool]) {
+        let tuple = pg_sys::heap_form_tuple(self.tupdesc, datums.as_mut_ptr(), nulls.as_mut_ptr());
+        pg_sys::tuplestore_puttuple(self.tupstore, tuple);
+    }
+
+    /// Hand the rows to PostgreSQL, returning the function's result datum
+    unsafe fn finish(self) -> pg_sys::Datum {
+        (*self.rsinfo).returnMode = pg_sys::SetFunctionReturnMode::SFRM_Materialize;
+        (*self.rsinfo).setResult = self.tupstore;
+        (*self.rsinfo).setDesc = self.tupdesc;
+        pg_sys::Datum::from(0)
+    }
+}
+
+/// Resolve a dotted path such as `metadata.author` to a nested struct field
+///
+/// Returns the top-level column index, the child index at each struct level
+/// below it, and the field the path names.
+fn resolve_field_path(
+    schema: &Schema,
+    field_path: &str,
+) -> Result<(usize, Vec<usize>, FieldRef), ConversionError> {
+    let undefined = || {
+        ConversionError::new(
+            PgSqlErrorCode::ERRCODE_UNDEFINED_COLUMN,
+            format!("Field \"{}\" does not exist in Lance table", field_path),
+        )
+    };
+    let mut names = field_path.split('.');
+    let first = names.next().unwrap_or_default();
+    let (column_idx, mut field) = schema
+        .fields()
+        .find(first)
+        .map(|(idx, field)| (idx, Arc::clone(field)))
+        .ok_or_else(undefined)?;
+    let mut child_indices = Vec::new();
+    for name in names {
+        let DataType::Struct(children) = field.data_type() else {
+            return Err(undefined());
+        };
+        let (child_idx, child) = children.find(name).ok_or_else(undefined)?;
+        let child = Arc::clone(child);
+        child_indices.push(child_idx);
+        field = child;
+    }
+    Ok((column_idx, child_indices, field))
+}
+
+#[no_mangle]
+#[doc(hidden)]
+pub extern "C" fn pg_finfo_lance_scan_field_wrapper() -> &'static pg_sys::Pg_finfo_record {
+    const V1_API: pg_sys::Pg_finfo_record = pg_sys::Pg_finfo_record { api_version: 1 };
+    &V1_API
+}
+
+/// Scan one nested struct field of a Lance table as a typed column
+///
+/// Only the top-level column holding the field is read. A row is NULL when
+/// the field or any struct containing it is NULL. The column definition list
+/// must have one column of the type `arrow_to_pg_type` reports for the field.
+///
+/// # Safety
+///
+/// Called by PostgreSQL through the function manager.
+#[no_mangle]
+#[pg_guard]
+pub unsafe extern "C-unwind" fn lance_scan_field_wrapper(
+    fcinfo: pg_sys::FunctionCallInfo,
+) -> pg_sys::Datum {
+    let (table_path, field_path): (&str, &str) =
+        match (pgrx::pg_getarg(fcinfo, 0), pgrx::pg_getarg(fcinfo, 1)) {
+            (Some(table_path), Some(field_path)) => (table_path, field_path),
+            _ => ereport!(
+                ERROR,
+                PgSqlErrorCode::ERRCODE_NULL_VALUE_NOT_ALLOWED,
+                "table_path and field_path must not be NULL"
+            ),
+        };
+    let limit: Option<i64> = pgrx::pg_getarg(fcinfo, 2);
+    let timeout = crate::timeout_arg(pgrx::pg_getarg(fcinfo, 3));
+
+    let rsinfo = materialize_target(fcinfo, "lance_scan_field");
+
+    let scanner = crate::open_scanner(table_path, None);
+    let schema = scanner.schema();
+    let (column_idx, child_indices, field) = resolve_field_path(&schema, field_path)
+        .unwrap_or_else(|e| ereport!(ERROR, e.code, e.message));
+    let column = schema.field(column_idx).name();
+    let field_schema = Schema::new(vec![field.as_ref().clone().with_name(field_path)]);
+    let expected = PgTupleDesc::from_pg_unchecked((*rsinfo).expectedDesc);
+    let targets = check_row_type(&field_schema, &expected)
+        .unwrap_or_else(|e| ereport!(ERROR, e.code, e.message));
+
+    let mut options = ScanOptions::new().with_columns(&[column]);
+    options.timeout = timeout;
+    let scan_iter = scanner
+        .scan(&options)
+        .unwrap_or_else(|e| crate::raise_scan_error(e));
+
+    let mut sink = TupleSink::new(rsinfo);
+    let mut rows_outputted_count = 0i64;
+    'batch_loop: for record_batch in scan_iter.batches {
+        // Struct children do not carry their parents' nulls, so every level
+        // on the way down is kept to check them
+        let mut levels = vec![Arc::clone(record_batch.column(0))];
+        for &child_idx in &child_indices {
+            let parent = levels.last().expect("levels start with the column");
+            let child = downcast_array::<StructArray>(parent.as_ref(), "StructArray")
+                .unwrap_or_else(|e| ereport!(ERROR, e.code, e.message))
+                .column(child_idx);
+            levels.push(Arc::clone(child));
+        }
+        let (leaf, ancestors) = levels.split_last().expect("levels start with the column");
+
+        for row_idx in 0..record_batch.num_rows() {
+            if limit.is_some_and(|l| rows_outputted_count >= l) {
+                break 'batch_loop;
+            }
+
+            let datum = if ancestors.iter().any(|level| level.is_null(row_idx)) {
+                Ok(None)
+            } else {
+                value_to_datum(leaf.as_ref(), row_idx, targets[0].as_ref())
+            };
+            let datum = match datum {
+                Ok(datum) => datum,
+                Err(e) => {
+                    skip_row_error(e.code, format!("field \"{}\": {}", field_path, e.message));
+                    continue;
+                }
+            };
+            let mut nulls = [datum.is_none()];
+            let mut datums = [datum.unwrap_or(pg_sys::Datum::from(0))];
+            sink.put(&mut datums, &mut nulls);
+            rows_outputted_count += 1;
+        }
+    }
+
+    sink.finish()
+}
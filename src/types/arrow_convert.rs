@@ -1,3 +1,4 @@
+use arrow::array::Array;
 use arrow::datatypes::*;
 
 pub fn arrow_schema_to_pg_columns(schema: &Schema) -> Vec<(String, pgrx::PgOid, bool)> {
@@ -6,10 +7,47 @@ pub fn arrow_schema_to_pg_columns(schema: &Schema) -> Vec<(String, pgrx::PgOid,
         .iter()
         .map(|field| {
             let name = field.name().clone();
-            let pg_type = super::conversion::arrow_to_pg_type(field.data_type())
+            let pg_type = super::conversion::arrow_to_pg_type(field)
                 .unwrap_or(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::TEXTOID));
             let nullable = field.is_nullable();
             (name, pg_type, nullable)
         })
         .collect()
 }
+
+/// Error raised when a single Arrow value cannot be converted
+#[derive(Debug)]
+pub struct ConversionError {
+    pub code: pgrx::PgSqlErrorCode,
+    pub message: String,
+}
+
+impl ConversionError {
+    pub fn new(code: pgrx::PgSqlErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// Downcast an Arrow array to its concrete type
+///
+/// Fails with `ERRCODE_DATATYPE_MISMATCH` instead of panicking when the
+/// physical array does not match what its logical data type promises.
+/// `expected` names the array type in the error, e.g. `"Int32Array"`.
+pub fn downcast_array<'a, T: Array + 'static>(
+    array: &'a dyn Array,
+    expected: &str,
+) -> Result<&'a T, ConversionError> {
+    array.as_any().downcast_ref::<T>().ok_or_else(|| {
+        ConversionError::new(
+            pgrx::PgSqlErrorCode::ERRCODE_DATATYPE_MISMATCH,
+            format!(
+                "expected {} for data type {:?}",
+                expected,
+                array.data_type()
+            ),
+        )
+    })
+}
@@ -1,44 +1,89 @@
 use arrow::datatypes::*;
 
-/// Arrow to PostgreSQL data type mapping
-pub fn arrow_to_pg_type(arrow_type: &DataType) -> Result<pgrx::PgOid, pgrx::PgSqlErrorCode> {
-    match arrow_type {
-        DataType::Boolean => Ok(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::BOOLOID)),
-        DataType::Int8 => Ok(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::CHAROID)),
-        DataType::Int16 => Ok(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::INT2OID)),
-        DataType::Int32 => Ok(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::INT4OID)),
-        DataType::Int64 => Ok(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::INT8OID)),
-        DataType::UInt8 => Ok(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::CHAROID)),
-        DataType::UInt16 => Ok(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::INT2OID)),
-        DataType::UInt32 => Ok(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::INT4OID)),
-        DataType::UInt64 => Ok(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::INT8OID)),
-        DataType::Float16 => Ok(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::FLOAT4OID)),
-        DataType::Float32 => Ok(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::FLOAT4OID)),
-        DataType::Float64 => Ok(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::FLOAT8OID)),
-        DataType::Utf8 => Ok(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::TEXTOID)),
-        DataType::LargeUtf8 => Ok(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::TEXTOID)),
-        DataType::Binary => Ok(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::BYTEAOID)),
-        DataType::LargeBinary => Ok(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::BYTEAOID)),
-        DataType::Date32 => Ok(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::DATEOID)),
-        DataType::Date64 => Ok(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::DATEOID)),
-        DataType::Time32(_) => Ok(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::TIMEOID)),
-        DataType::Time64(_) => Ok(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::TIMEOID)),
-        DataType::Timestamp(_, _) => Ok(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::TIMESTAMPOID)),
-        DataType::Interval(_) => Ok(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::INTERVALOID)),
-        DataType::List(_) => Ok(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::JSONBOID)),
-        DataType::LargeList(_) => Ok(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::JSONBOID)),
-        DataType::FixedSizeList(field, _) => match field.data_type() {
-            DataType::Float32 => Ok(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::FLOAT4ARRAYOID)),
-            DataType::Float64 => Ok(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::FLOAT8ARRAYOID)),
-            _ => Ok(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::JSONBOID)),
+/// Field metadata key holding the name of an Arrow extension type
+pub const EXTENSION_NAME_KEY: &str = "ARROW:extension:name";
+
+/// Canonical Arrow extension type for fixed-shape tensors
+pub const FIXED_SHAPE_TENSOR_EXTENSION: &str = "arrow.fixed_shape_tensor";
+
+/// Get the Arrow extension type name of a field, if it has one
+pub fn extension_name(field: &Field) -> Option<&str> {
+    field.metadata().get(EXTENSION_NAME_KEY).map(String::as_str)
+}
+
+/// Render an Arrow data type compactly, keeping nested item types and sizes
+///
+/// Nested types name their children's types without the field boilerplate of
+/// `Debug`, e.g. `FixedSizeList(Float32, 4)` or `Struct(a: Int32, b: Utf8)`.
+pub fn arrow_type_name(data_type: &DataType) -> String {
+    match data_type {
+        DataType::List(item) => format!("List({})", arrow_type_name(item.data_type())),
+        DataType::LargeList(item) => format!("LargeList({})", arrow_type_name(item.data_type())),
+        DataType::FixedSizeList(item, size) => {
+            format!(
+                "FixedSizeList({}, {})",
+                arrow_type_name(item.data_type()),
+                size
+            )
+        }
+        DataType::Struct(fields) => format!(
+            "Struct({})",
+            fields
+                .iter()
+                .map(|f| format!("{}: {}", f.name(), arrow_type_name(f.data_type())))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        DataType::Map(entries, _) => match entries.data_type() {
+            DataType::Struct(fields) if fields.len() == 2 => format!(
+                "Map({}, {})",
+                arrow_type_name(fields[0].data_type()),
+                arrow_type_name(fields[1].data_type())
+            ),
+            other => format!("Map({})", arrow_type_name(other)),
         },
-        DataType::Struct(_) => Ok(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::JSONBOID)),
-        DataType::Union(_, _) => Ok(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::JSONBOID)),
-        DataType::Dictionary(_, value_type) => arrow_to_pg_type(value_type),
-        DataType::Decimal128(_, _) => Ok(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::NUMERICOID)),
-        DataType::Decimal256(_, _) => Ok(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::NUMERICOID)),
-        DataType::Map(_, _) => Ok(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::JSONBOID)),
-        _ => {
+        DataType::Dictionary(key_type, value_type) => format!(
+            "Dictionary({}, {})",
+            arrow_type_name(key_type),
+            arrow_type_name(value_type)
+        ),
+        other => format!("{:?}", other),
+    }
+}
+
+/// Arrow field to PostgreSQL data type mapping
+///
+/// Known extension types are mapped here; any other extension type falls back
+/// to the mapping of its storage type.
+pub fn arrow_to_pg_type(field: &Field) -> Result<pgrx::PgOid, pgrx::PgSqlErrorCode> {
+    match extension_name(field) {
+        // Tensors are stored flattened in row-major order, so a numeric tensor
+        // becomes a one-dimensional array like any other fixed-size list
+        Some(FIXED_SHAPE_TENSOR_EXTENSION) => match field.data_type() {
+            DataType::FixedSizeList(_, _) => arrow_data_type_to_pg_type(field.data_type()),
+            _ => Err(pgrx::PgSqlErrorCode::ERRCODE_DATATYPE_MISMATCH),
+        },
+        Some(name) => {
+            pgrx::debug1!(
+                "Column {} has unrecognized extension type {}, using its storage type",
+                field.name(),
+                name
+            );
+            arrow_data_type_to_pg_type(field.data_type())
+        }
+        None => arrow_data_type_to_pg_type(field.data_type()),
+    }
+}
+
+/// Arrow to PostgreSQL data type mapping
+///
+/// Types without a mapping of their own are read as text.
+pub fn arrow_data_type_to_pg_type(
+    arrow_type: &DataType,
+) -> Result<pgrx::PgOid, pgrx::PgSqlErrorCode> {
+    match mapped_pg_type(arrow_type) {
+        Some(oid) => Ok(oid),
+        None => {
             pgrx::warning!(
                 "Unsupported Arrow type: {:?}, converting to TEXT",
                 arrow_type
@@ -48,6 +93,87 @@ pub fn arrow_to_pg_type(arrow_type: &DataType) -> Result<pgrx::PgOid, pgrx::PgSq
     }
 }
 
+/// PostgreSQL type an Arrow type maps to, `None` for types without a mapping
+pub fn mapped_pg_type(arrow_type: &DataType) -> Option<pgrx::PgOid> {
+    match arrow_type {
+        DataType::Boolean => Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::BOOLOID)),
+        DataType::Int8 => Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::CHAROID)),
+        DataType::Int16 => Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::INT2OID)),
+        DataType::Int32 => Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::INT4OID)),
+        DataType::Int64 => Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::INT8OID)),
+        DataType::UInt8 => Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::CHAROID)),
+        DataType::UInt16 => Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::INT2OID)),
+        DataType::UInt32 => Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::INT4OID)),
+        DataType::UInt64 if crate::guc::UINT64_AS_BIGINT.get() => {
+            Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::INT8OID))
+        }
+        DataType::UInt64 => Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::NUMERICOID)),
+        DataType::Float16 => Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::FLOAT4OID)),
+        DataType::Float32 => Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::FLOAT4OID)),
+        DataType::Float64 => Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::FLOAT8OID)),
+        DataType::Utf8 => Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::TEXTOID)),
+        DataType::LargeUtf8 => Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::TEXTOID)),
+        DataType::Binary => Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::BYTEAOID)),
+        DataType::LargeBinary => Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::BYTEAOID)),
+        DataType::FixedSizeBinary(_) => Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::BYTEAOID)),
+        DataType::Date32 => Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::DATEOID)),
+        DataType::Date64 => Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::DATEOID)),
+        DataType::Time32(_) => Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::TIMEOID)),
+        DataType::Time64(_) => Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::TIMEOID)),
+        DataType::Timestamp(_, Some(_)) => {
+            Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::TIMESTAMPTZOID))
+        }
+        DataType::Timestamp(_, None) => {
+            Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::TIMESTAMPOID))
+        }
+        DataType::Interval(_) => Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::INTERVALOID)),
+        DataType::Duration(_) => Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::INTERVALOID)),
+        DataType::List(_) | DataType::LargeList(_) | DataType::FixedSizeList(_, _)
+            if super::is_bool_list_type(arrow_type) =>
+        {
+            Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::BOOLARRAYOID))
+        }
+        DataType::List(_) => Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::JSONBOID)),
+        DataType::LargeList(_) => Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::JSONBOID)),
+        DataType::FixedSizeList(field, _) => match field.data_type() {
+            DataType::Float32 => Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::FLOAT4ARRAYOID)),
+            DataType::Float64 => Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::FLOAT8ARRAYOID)),
+            _ => Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::JSONBOID)),
+        },
+        DataType::Struct(_) => Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::JSONBOID)),
+        DataType::Union(_, _) => Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::JSONBOID)),
+        DataType::Dictionary(_, value_type) => mapped_pg_type(value_type),
+        DataType::Decimal128(_, _) => Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::NUMERICOID)),
+        DataType::Decimal256(_, _) => Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::NUMERICOID)),
+        DataType::Map(_, _) => Some(pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::JSONBOID)),
+        _ => None,
+    }
+}
+
+/// How decimal values are written to JSON
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DecimalFormat {
+    /// A JSON number, which keeps about 15 significant digits
+    #[default]
+    Numeric,
+    /// An exact `{"unscaled": "<digits>", "scale": n}` object
+    ScaledInt,
+    /// An exact JSON string such as `"123.45"`
+    Text,
+}
+
+impl DecimalFormat {
+    /// Parse `numeric`, `scaled_int` or `text`, ignoring case
+    pub fn parse(value: &str) -> Result<Self, pgrx::PgSqlErrorCode> {
+        match value.to_ascii_lowercase().as_str() {
+            "numeric" => Ok(Self::Numeric),
+            "scaled_int" => Ok(Self::ScaledInt),
+            "text" => Ok(Self::Text),
+            _ => Err(pgrx::PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE),
+        }
+    }
+}
+
 /// Get readable name for PostgreSQL type
 pub fn pg_type_name(oid: pgrx::PgOid) -> &'static str {
     match oid {
@@ -63,9 +189,11 @@ pub fn pg_type_name(oid: pgrx::PgOid) -> &'static str {
         pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::DATEOID) => "date",
         pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::TIMEOID) => "time",
         pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::TIMESTAMPOID) => "timestamp",
+        pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::TIMESTAMPTZOID) => "timestamptz",
         pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::INTERVALOID) => "interval",
         pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::NUMERICOID) => "numeric",
         pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::JSONBOID) => "jsonb",
+        pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::BOOLARRAYOID) => "boolean[]",
         pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::FLOAT4ARRAYOID) => "float4[]",
         pgrx::PgOid::BuiltIn(pgrx::PgBuiltInOids::FLOAT8ARRAYOID) => "float8[]",
         _ => "unknown",
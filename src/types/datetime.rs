@@ -0,0 +1,87 @@
+use arrow::datatypes::TimeUnit;
+use chrono::{DateTime, Utc};
+use pgrx::datum::TimestampWithTimeZone;
+use pgrx::{pg_sys, IntoDatum};
+
+use super::ConversionError;
+
+/// Microseconds between the Unix epoch and the PostgreSQL epoch (2000-01-01)
+const PG_EPOCH_OFFSET_MICROS: i64 = 946_684_800_000_000;
+
+/// Convert a chrono UTC timestamp into a PostgreSQL timestamptz
+pub fn chrono_to_pg_timestamptz(
+    dt: DateTime<Utc>,
+) -> Result<TimestampWithTimeZone, ConversionError> {
+    let pg_micros = dt.timestamp_micros() - PG_EPOCH_OFFSET_MICROS;
+    TimestampWithTimeZone::try_from(pg_micros).map_err(|_| {
+        ConversionError::new(
+            pgrx::PgSqlErrorCode::ERRCODE_DATETIME_VALUE_OUT_OF_RANGE,
+            format!("timestamp {} is out of range", dt),
+        )
+    })
+}
+
+/// Render an instant the way PostgreSQL prints a timestamptz
+///
+/// Uses the session's `TimeZone` and `DateStyle`, so the result matches what
+/// `SELECT ts::timestamptz` shows in the same session.
+pub fn session_timestamp_string(dt: DateTime<Utc>) -> Result<String, ConversionError> {
+    let timestamp = chrono_to_pg_timestamptz(dt)?;
+    let text = unsafe {
+        pgrx::direct_function_call::<&core::ffi::CStr>(
+            pg_sys::timestamptz_out,
+            &[timestamp.into_datum()],
+        )
+    };
+    Ok(text
+        .map(|text| text.to_string_lossy().into_owned())
+        .unwrap_or_default())
+}
+
+/// Number of `unit` ticks in one second
+pub fn units_per_second(unit: &TimeUnit) -> i64 {
+    match unit {
+        TimeUnit::Second => 1,
+        TimeUnit::Millisecond => 1_000,
+        TimeUnit::Microsecond => 1_000_000,
+        TimeUnit::Nanosecond => 1_000_000_000,
+    }
+}
+
+/// Convert a count of `unit` ticks into microseconds, truncating nanoseconds
+pub fn duration_to_micros(value: i64, unit: &TimeUnit) -> Option<i64> {
+    match unit {
+        TimeUnit::Second => value.checked_mul(1_000_000),
+        TimeUnit::Millisecond => value.checked_mul(1_000),
+        TimeUnit::Microsecond => Some(value),
+        TimeUnit::Nanosecond => Some(value / 1_000),
+    }
+}
+
+/// Render a count of `unit` ticks as an ISO-8601 duration such as `PT1.5S`
+pub fn iso8601_duration(value: i64, unit: &TimeUnit) -> String {
+    let per_second = units_per_second(unit) as u64;
+    let sign = if value < 0 { "-" } else { "" };
+    let abs = value.unsigned_abs();
+    let seconds = abs / per_second;
+    let fraction = abs % per_second;
+
+    if fraction == 0 {
+        return format!("{}PT{}S", sign, seconds);
+    }
+    let width = per_second.ilog10() as usize;
+    let fraction = format!("{:0width$}", fraction, width = width);
+    format!("{}PT{}.{}S", sign, seconds, fraction.trim_end_matches('0'))
+}
+
+/// Render an Arrow interval as an ISO-8601 duration such as `P1M2DT0.5S`
+///
+/// Each component keeps its own sign, as in PostgreSQL intervals.
+pub fn iso8601_interval(months: i32, days: i32, nanos: i64) -> String {
+    let time = iso8601_duration(nanos, &TimeUnit::Nanosecond);
+    let time = match time.strip_prefix("-P") {
+        Some(rest) => format!("T-{}", &rest[1..]),
+        None => time[1..].to_string(),
+    };
+    format!("P{}M{}D{}", months, days, time)
+}
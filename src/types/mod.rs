@@ -1,5 +1,9 @@
 pub mod arrow_convert;
 pub mod conversion;
+pub mod datetime;
+pub mod vector;
 
 pub use arrow_convert::*;
 pub use conversion::*;
+pub use datetime::*;
+pub use vector::*;
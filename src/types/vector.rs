@@ -0,0 +1,231 @@
+use arrow::array::{
+    Array, BooleanArray, FixedSizeListArray, Float16Array, Float32Array, Float64Array,
+    GenericListArray, OffsetSizeTrait,
+};
+use arrow::datatypes::DataType;
+
+use super::ConversionError;
+
+/// Whether a column holds float vectors that can be emitted as `float4[]`
+pub fn is_float_vector_type(data_type: &DataType) -> bool {
+    match data_type {
+        DataType::List(field) | DataType::LargeList(field) | DataType::FixedSizeList(field, _) => {
+            matches!(
+                field.data_type(),
+                DataType::Float16 | DataType::Float32 | DataType::Float64
+            )
+        }
+        _ => false,
+    }
+}
+
+/// Whether a column holds lists of booleans that can be emitted as `boolean[]`
+pub fn is_bool_list_type(data_type: &DataType) -> bool {
+    match data_type {
+        DataType::List(field) | DataType::LargeList(field) | DataType::FixedSizeList(field, _) => {
+            field.data_type() == &DataType::Boolean
+        }
+        _ => false,
+    }
+}
+
+/// Convert the list value at `row_idx` into a float vector
+///
+/// Supports `List`, `LargeList` and `FixedSizeList` of floats. A null list
+/// yields `None`; null elements inside the list are kept as `None`.
+pub fn arrow_list_to_f32_vec(
+    array: &dyn Array,
+    row_idx: usize,
+) -> Result<Option<Vec<Option<f32>>>, ConversionError> {
+    if array.is_null(row_idx) {
+        return Ok(None);
+    }
+
+    let values = list_value_at(array, row_idx, "floats")?;
+    floats_to_f32_vec(values.as_ref()).map(Some)
+}
+
+/// Convert the list value at `row_idx` into a boolean vector
+///
+/// Supports `List`, `LargeList` and `FixedSizeList` of booleans. A null list
+/// yields `None`; null elements inside the list are kept as `None`.
+pub fn arrow_list_to_bool_vec(
+    array: &dyn Array,
+    row_idx: usize,
+) -> Result<Option<Vec<Option<bool>>>, ConversionError> {
+    if array.is_null(row_idx) {
+        return Ok(None);
+    }
+
+    let values = list_value_at(array, row_idx, "booleans")?;
+    let vector = values
+        .as_any()
+        .downcast_ref::<BooleanArray>()
+        .ok_or_else(|| mismatch(values.data_type(), "booleans"))?
+        .iter()
+        .collect();
+    Ok(Some(vector))
+}
+
+/// Euclidean distance between a stored vector and a query vector
+///
+/// Returns `None` when the stored vector has null elements. Both vectors must
+/// have the same length.
+pub fn euclidean_distance(vector: &[Option<f32>], query: &[f32]) -> Option<f64> {
+    let mut sum = 0.0f64;
+    for (value, q) in vector.iter().zip(query) {
+        let diff = f64::from((*value)?) - f64::from(*q);
+        sum += diff * diff;
+    }
+    Some(sum.sqrt())
+}
+
+/// What to do with NULL elements inside an otherwise non-NULL vector
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NullElementPolicy {
+    /// Keep them as NULL array elements
+    #[default]
+    Null,
+    /// Replace them with 0.0, as pgvector cannot hold NULL elements
+    Zero,
+    /// Fail the conversion
+    Error,
+}
+
+impl NullElementPolicy {
+    /// Parse `null`, `zero` or `error`, ignoring case
+    pub fn parse(value: &str) -> Result<Self, pgrx::PgSqlErrorCode> {
+        match value.to_ascii_lowercase().as_str() {
+            "null" => Ok(Self::Null),
+            "zero" => Ok(Self::Zero),
+            "error" => Ok(Self::Error),
+            _ => Err(pgrx::PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE),
+        }
+    }
+
+    /// Apply the policy to the elements of a vector
+    pub fn apply(self, vector: Vec<Option<f32>>) -> Result<Vec<Option<f32>>, ConversionError> {
+        match self {
+            Self::Null => Ok(vector),
+            Self::Zero => Ok(vector.into_iter().map(|v| v.or(Some(0.0))).collect()),
+            Self::Error => match vector.iter().position(Option::is_none) {
+                Some(position) => Err(ConversionError::new(
+                    pgrx::PgSqlErrorCode::ERRCODE_NULL_VALUE_NOT_ALLOWED,
+                    format!("vector has a NULL element at position {}", position + 1),
+                )),
+                None => Ok(vector),
+            },
+        }
+    }
+}
+
+/// How `vector_distance` compares two vectors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DistanceMetric {
+    /// Euclidean distance
+    L2,
+    /// One minus the cosine similarity
+    Cosine,
+    /// Negated dot product, so that smaller means closer
+    Dot,
+}
+
+impl DistanceMetric {
+    /// Parse `l2`, `cosine` or `dot`, ignoring case
+    pub fn parse(value: &str) -> Result<Self, pgrx::PgSqlErrorCode> {
+        match value.to_ascii_lowercase().as_str() {
+            "l2" | "euclidean" => Ok(Self::L2),
+            "cosine" => Ok(Self::Cosine),
+            "dot" => Ok(Self::Dot),
+            _ => Err(pgrx::PgSqlErrorCode::ERRCODE_INVALID_PARAMETER_VALUE),
+        }
+    }
+}
+
+/// Distance between two stored vectors of the same length
+///
+/// Returns `None` when either vector has null elements, or for the cosine
+/// distance when either vector is all zeros.
+pub fn vector_distance(
+    a: &[Option<f32>],
+    b: &[Option<f32>],
+    metric: DistanceMetric,
+) -> Option<f64> {
+    let (mut dot, mut norm_a, mut norm_b, mut squared) = (0.0f64, 0.0f64, 0.0f64, 0.0f64);
+    for (x, y) in a.iter().zip(b) {
+        let (x, y) = (f64::from((*x)?), f64::from((*y)?));
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+        squared += (x - y) * (x - y);
+    }
+    match metric {
+        DistanceMetric::L2 => Some(squared.sqrt()),
+        DistanceMetric::Cosine if norm_a == 0.0 || norm_b == 0.0 => None,
+        DistanceMetric::Cosine => Some(1.0 - dot / (norm_a.sqrt() * norm_b.sqrt())),
+        DistanceMetric::Dot => Some(-dot),
+    }
+}
+
+fn list_value_at(
+    array: &dyn Array,
+    row_idx: usize,
+    expected: &str,
+) -> Result<arrow::array::ArrayRef, ConversionError> {
+    match array.data_type() {
+        DataType::List(_) => list_values::<i32>(array, row_idx, expected),
+        DataType::LargeList(_) => list_values::<i64>(array, row_idx, expected),
+        DataType::FixedSizeList(_, _) => Ok(array
+            .as_any()
+            .downcast_ref::<FixedSizeListArray>()
+            .ok_or_else(|| mismatch(array.data_type(), expected))?
+            .value(row_idx)),
+        other => Err(mismatch(other, expected)),
+    }
+}
+
+fn list_values<O: OffsetSizeTrait>(
+    array: &dyn Array,
+    row_idx: usize,
+    expected: &str,
+) -> Result<arrow::array::ArrayRef, ConversionError> {
+    let list_array = array
+        .as_any()
+        .downcast_ref::<GenericListArray<O>>()
+        .ok_or_else(|| mismatch(array.data_type(), expected))?;
+    Ok(list_array.value(row_idx))
+}
+
+fn floats_to_f32_vec(values: &dyn Array) -> Result<Vec<Option<f32>>, ConversionError> {
+    let vector = match values.data_type() {
+        DataType::Float16 => values
+            .as_any()
+            .downcast_ref::<Float16Array>()
+            .ok_or_else(|| mismatch(values.data_type(), "floats"))?
+            .iter()
+            .map(|v| v.map(|v| v.to_f32()))
+            .collect(),
+        DataType::Float32 => values
+            .as_any()
+            .downcast_ref::<Float32Array>()
+            .ok_or_else(|| mismatch(values.data_type(), "floats"))?
+            .iter()
+            .collect(),
+        DataType::Float64 => values
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .ok_or_else(|| mismatch(values.data_type(), "floats"))?
+            .iter()
+            .map(|v| v.map(|v| v as f32))
+            .collect(),
+        other => return Err(mismatch(other, "floats")),
+    };
+    Ok(vector)
+}
+
+fn mismatch(data_type: &DataType, expected: &str) -> ConversionError {
+    ConversionError::new(
+        pgrx::PgSqlErrorCode::ERRCODE_DATATYPE_MISMATCH,
+        format!("expected a list of {}, found {:?}", expected, data_type),
+    )
+}